@@ -1,7 +1,8 @@
 use std::vec;
 
 use actix_web::{web, App, HttpResponse, HttpServer, HttpRequest, Responder, Result, get};
-use insolvent_detect_signal::types::TransferFromFixedFloatJson;
+use alloy_primitives::U256;
+use insolvent_detect_signal::{address::CanonicalAddress, types::TransferFromFixedFloatJson, units::format_ether};
 use serde::{Deserialize, Serialize};
 
 struct FakeDB;
@@ -14,8 +15,9 @@ impl FakeDB {
     pub fn logged_events(&self) -> Vec<LoggedEvent> {
         //This is generated by signals, is the payload saved in the db
         let fake_event_json = TransferFromFixedFloatJson {
-            recipient: "fake".to_string(),
-            value: 10,
+            recipient: CanonicalAddress::default(),
+            value: U256::from(10),
+            value_eth: format_ether(U256::from(10)),
             block_timestamp: 10,
             block: 10,
             transaction_hash: "fake_hash".to_string(),
@@ -92,7 +94,7 @@ mod tests {
         let str = std::str::from_utf8(&body).unwrap();
         let events = serde_json::from_str::<ListEventsResponse>(str).unwrap();
 
-        assert_eq!(events.events.get(0).unwrap().id, 10);
+        assert_eq!(events.events.first().unwrap().id, 10);
         assert_eq!(events.events.get(1).unwrap().id, 11);
     }
 
@@ -107,6 +109,6 @@ mod tests {
         let str = std::str::from_utf8(&body).unwrap();
         let events = serde_json::from_str::<LoggedEventsResponse>(str).unwrap();
 
-        assert_eq!(events.logged_events.get(0).unwrap().id, 100);
+        assert_eq!(events.logged_events.first().unwrap().id, 100);
     }
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use std::{
+    env,
+    io::{self, BufRead, Write},
+};
+
+use alloy_primitives::B256;
+use insolvent_detect_signal::{
+    api::{BlockTag, EthJsonRpc, HttpJsonRpc},
+    expr::{Expr, Scope},
+    model::{Block, Transaction},
+};
+
+const USAGE: &str = "eval_expr [--block <number>] [--tx <hash>] [<expression>]";
+
+/// Evaluates a detector expression against a node, or reads them line by line without one.
+///
+/// `--tx` binds `tx.*` and evaluates at the transaction's block, `--block` evaluates at that
+/// block, otherwise state is read at `latest`. In the REPL `:type <expression>` only checks it.
+#[tokio::main]
+pub async fn main() {
+    let mut args = env::args().skip(1);
+    let mut block_number = None;
+    let mut tx_hash = None;
+    let mut source = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--block" => block_number = Some(args.next().and_then(|n| n.parse::<u64>().ok()).expect(USAGE)),
+            "--tx" => tx_hash = Some(args.next().and_then(|h| h.parse::<B256>().ok()).expect(USAGE)),
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ if source.is_none() => source = Some(arg),
+            _ => panic!("{}", USAGE),
+        }
+    }
+
+    let api = HttpJsonRpc::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let transaction: Option<Transaction> = match tx_hash {
+        Some(hash) => Some(
+            api.get_transaction_by_hash(hash)
+                .await
+                .unwrap_or_else(|e| panic!("{}", e))
+                .unwrap_or_else(|| panic!("No transaction {}", hash)),
+        ),
+        None => None,
+    };
+    let block_number = block_number.or(transaction.as_ref().and_then(|tx| tx.block_number));
+    let block: Option<Block> = match block_number {
+        Some(number) => Some(
+            api.get_block(BlockTag::Number(number))
+                .await
+                .unwrap_or_else(|e| panic!("{}", e))
+                .unwrap_or_else(|| panic!("No block {}", number)),
+        ),
+        None => None,
+    };
+
+    let mut scope = Scope::new(api.chain_id(), &api);
+    if let Some(block) = &block {
+        scope = scope.with_block(block);
+    }
+    if let Some(transaction) = &transaction {
+        scope = scope.with_transaction(transaction);
+    }
+
+    if let Some(source) = source {
+        if !eval(&scope, &source).await {
+            std::process::exit(1);
+        }
+        return;
+    }
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        match line.trim() {
+            "" => {}
+            line => match line.strip_prefix(":type ") {
+                Some(source) => match Expr::parse(source) {
+                    Ok(expr) => println!("{}", expr.ty()),
+                    Err(e) => print_error(source, &e.to_string(), e.pos()),
+                },
+                None => {
+                    eval(&scope, line).await;
+                }
+            },
+        }
+    }
+}
+
+/// Prints the value or the error, true if it evaluated.
+async fn eval(scope: &Scope<'_>, source: &str) -> bool {
+    let expr = match Expr::parse(source) {
+        Ok(expr) => expr,
+        Err(e) => {
+            print_error(source, &e.to_string(), e.pos());
+            return false;
+        }
+    };
+    match expr.eval(scope).await {
+        Ok(value) => {
+            println!("{}", value);
+            true
+        }
+        Err(e) => {
+            print_error(source, &e.to_string(), e.pos());
+            false
+        }
+    }
+}
+
+fn print_error(source: &str, message: &str, pos: usize) {
+    eprintln!("{}\n{}^\n{}", source, " ".repeat(pos), message);
+}
@@ -1,39 +1,255 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, fmt::Display, future::Future, time::Duration};
 
-use insolvent_detect_signal::api::{ EthJsonRpc, InfuraAPIHttp};
-use insolvent_detect_signal::types::{Event, TransferFromFixedFloatEvent, Signal, AnonymouslyFundedSmartContractTriggeredSignal, BlockJson};
+use alloy_primitives::B256;
+use futures_util::{future::join_all, StreamExt};
+#[cfg(unix)]
+use insolvent_detect_signal::api::IpcJsonRpc;
+use insolvent_detect_signal::api::{
+    EthJsonRpc, EthPubSub, HttpJsonRpc, ProviderConfigError, RateLimit, RetryPolicy, RpcError, WsJsonRpc,
+};
+use insolvent_detect_signal::chain::{ChainConfig, ChainRegistry};
+use insolvent_detect_signal::follower::{ChainFollower, ChainNotification, ProcessingDepth};
+use insolvent_detect_signal::model::Block;
+use insolvent_detect_signal::registry::{DetectorRegistry, Detectors};
+use insolvent_detect_signal::rules::RuleSet;
+use insolvent_detect_signal::score::ScoreConfig;
+use insolvent_detect_signal::sink::Match;
+use insolvent_detect_signal::state::StateStore;
+use insolvent_detect_signal::types::Detection;
 
-//Not working, needs proper DB setup
-#[tokio::main]
-pub async fn main() {
-    // Load caches
-    let suspicious_addresses = HashSet::new();
-    let suspicious_contracts= HashSet::new();
-    let api = InfuraAPIHttp;
-
-    let events: Vec<Event> = vec![
-        Event::TransferFromFixedFloat(TransferFromFixedFloatEvent)
-    ];
-
-    let signals: Vec<Signal> = vec![
-        Signal::AnonymouslyFundedSmartContractTriggered(AnonymouslyFundedSmartContractTriggeredSignal)
-    ];
+/// Until there is a DB, output goes to stdout as one JSON object per line. `report` is `new`,
+/// `finalized` (an earlier detection's block finalized), `retracted` (its block was reorged out)
+/// or `expired` (its block left the follower window before either was seen, it isn't tracked any
+/// more).
+fn report(report: &str, detection: &Detection) {
+    println!("{}", serde_json::json!({"report": report, "detection": detection}));
+}
 
-    // Fetch blocks
+/// Runs `attempt` until it works, retrying what `retryable` allows with the provider's backoff
+/// until its retries run out. Failures along the way are logged.
+async fn retry<T, E: Display, F: Future<Output = Result<T, E>>>(
+    chain: &str,
+    what: &str,
+    policy: &RetryPolicy,
+    retryable: impl Fn(&E) -> bool,
+    mut attempt: impl FnMut() -> F,
+) -> Result<T, String> {
+    let mut retries = 0;
     loop {
-        if let Ok(block_raw) = api.get_block_by_number_latest().await {
-            let block_json = BlockJson::new(block_raw);
-            for event in &events {
-                let _res = event.event(&api, &block_json, &suspicious_addresses).await;
-            }
-            for signal in &signals {
-                let _res = signal.signal(&block_json, &suspicious_contracts).await;
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retries < policy.max_retries && retryable(&e) => {
+                eprintln!("{}: {} failed, retrying: {}", chain, what, e);
+                tokio::time::sleep(policy.backoff(retries)).await;
+                retries += 1;
             }
+            Err(e) => return Err(format!("{}: {}", what, e)),
         }
     }
 }
 
+/// Only a connection that didn't come up is worth trying again, the rest is config.
+fn connect_retryable(e: &ProviderConfigError) -> bool {
+    matches!(e, ProviderConfigError::Client(_))
+}
+
+/// One per chain, chains don't share any state.
+struct Runner {
+    chain_id: u64,
+    depth: ProcessingDepth,
+    window: usize,
+    block_time: Duration,
+    detectors: Detectors,
+    scoring: ScoreConfig,
+    // Suspicious addresses and contracts, filled in by events as blocks are processed
+    state: StateStore,
+    // Non-final output per block hash, kept so it can be upgraded or retracted later
+    pending: HashMap<B256, Vec<Detection>>,
+}
+
+impl Runner {
+    fn new(chain: &ChainConfig, detectors: Detectors, scoring: ScoreConfig) -> Result<Self, String> {
+        Ok(Self {
+            chain_id: chain.chain_id,
+            depth: chain.depth,
+            window: chain.window,
+            block_time: chain.block_time,
+            detectors,
+            scoring,
+            state: match &chain.state_path {
+                Some(path) => StateStore::open(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+                None => StateStore::new(),
+            },
+            pending: HashMap::new(),
+        })
+    }
+
+    async fn process_block(&mut self, api: &(impl EthJsonRpc + Sync), block_json: &Block) {
+        let mut matches: Vec<Match> = Vec::new();
+        self.detectors
+            .run(self.chain_id, block_json, api, &mut self.state, &mut matches)
+            .await;
+        self.scoring.observe(block_json, &matches, api, &mut self.state).await;
+        if self.depth == ProcessingDepth::Finalized {
+            // No reorg to undo
+            self.state.finalize(block_json.hash());
+        }
+        self.save_state();
+        let output: Vec<Detection> = matches
+            .into_iter()
+            .map(|res| {
+                let score = self.scoring.score_match(&res.1, &self.state);
+                Detection::new(self.chain_id, res, block_json, self.depth).with_score(score)
+            })
+            .collect();
+        for detection in &output {
+            report("new", detection);
+        }
+        if self.depth != ProcessingDepth::Finalized && !output.is_empty() {
+            self.pending.insert(block_json.hash(), output);
+        }
+    }
+
+    fn save_state(&mut self) {
+        // Not fatal, the next block tries again
+        if let Err(e) = self.state.save() {
+            eprintln!("chain {}: saving state: {}", self.chain_id, e);
+        }
+    }
+
+    async fn apply(&mut self, api: &(impl EthJsonRpc + Sync), notifications: Vec<ChainNotification>) {
+        for notification in notifications {
+            match notification {
+                ChainNotification::BlockAdded(mut block_json) => {
+                    // One call for the whole block instead of one per event that needs receipts
+                    if let Ok(receipts) = api.get_block_receipts(&block_json).await {
+                        block_json.attach_receipts(receipts);
+                    }
+                    self.process_block(api, &block_json).await
+                }
+                ChainNotification::BlockReverted(block) => {
+                    for detection in self.pending.remove(&block.hash).unwrap_or_default() {
+                        report("retracted", &detection);
+                    }
+                    self.state.revert(block.hash);
+                    self.save_state();
+                }
+                ChainNotification::BlockFinalized(block) => {
+                    for mut detection in self.pending.remove(&block.hash).unwrap_or_default() {
+                        detection.finalize();
+                        report("finalized", &detection);
+                    }
+                    self.state.finalize(block.hash);
+                }
+                ChainNotification::BlockEvicted(block) => {
+                    // Finality wasn't seen in time, stop tracking rather than hold these forever
+                    for detection in self.pending.remove(&block.hash).unwrap_or_default() {
+                        report("expired", &detection);
+                    }
+                    // Its state entries stay, same as a finalized block's
+                    self.state.finalize(block.hash);
+                }
+            }
+        }
+    }
 
+    /// Node pushes new heads, the follower fetches whatever is new when one arrives. Only
+    /// returns once subscribing has failed more often than `policy` retries.
+    async fn follow_heads(
+        &mut self,
+        chain: &str,
+        api: &(impl EthJsonRpc + EthPubSub + Sync),
+        policy: &RetryPolicy,
+    ) -> Result<(), String> {
+        let mut follower = ChainFollower::new(api).with_depth(self.depth).with_window(self.window);
+        loop {
+            let subscribe = || api.subscribe_new_heads();
+            let mut heads = retry(chain, "subscribing to new heads", policy, |_: &RpcError| true, subscribe).await?;
+            while heads.next().await.is_some() {
+                if let Ok(notifications) = follower.poll().await {
+                    self.apply(api, notifications).await;
+                }
+            }
+            // The node refused the subscription again after a reconnect
+            eprintln!("{}: new heads subscription ended, subscribing again", chain);
+        }
+    }
 
+    async fn poll_heads(&mut self, api: &(impl EthJsonRpc + Sync)) {
+        let mut follower = ChainFollower::new(api).with_depth(self.depth).with_window(self.window);
+        loop {
+            match follower.poll().await {
+                // Catching up comes back for more straight away
+                Ok(notifications) if !notifications.is_empty() => self.apply(api, notifications).await,
+                // Nothing new or the node is unreachable, next block is about a block time away
+                _ => tokio::time::sleep(self.block_time).await,
+            }
+        }
+    }
+}
 
+/// Follows the chain until its provider can't be reached any more, the error says why.
+async fn run(chain: ChainConfig, detectors: Detectors, scoring: ScoreConfig) -> Result<(), String> {
+    let mut runner = Runner::new(&chain, detectors, scoring)?;
+    let mut config = chain.provider;
+    let policy = config.retry.clone();
+    if config.is_ipc() {
+        // Co-located node, skip the network stack entirely
+        #[cfg(unix)]
+        {
+            let connect = || IpcJsonRpc::connect(config.clone());
+            let api = retry(&chain.name, "connecting", &policy, connect_retryable, connect).await?;
+            runner.follow_heads(&chain.name, &api, &policy).await
+        }
+        #[cfg(not(unix))]
+        Err("IPC providers are only supported on unix".to_string())
+    } else if config.is_websocket() {
+        let connect = || WsJsonRpc::connect(config.clone());
+        let api = retry(&chain.name, "connecting", &policy, connect_retryable, connect).await?;
+        runner.follow_heads(&chain.name, &api, &policy).await
+    } else {
+        // Polling waits a block time when there is nothing new, the limit is a backstop for
+        // catching up. Each block takes a few requests (head, receipts, finalized head), fast
+        // chains need more than mainnet's 1/s
+        if config.rate_limit.is_none() {
+            let per_block = 4.0 / chain.block_time.as_secs_f64();
+            config = config.with_rate_limit(RateLimit::per_second(per_block.max(1.0)));
+        }
+        let api = HttpJsonRpc::new(config).map_err(|e| e.to_string())?;
+        runner.poll_heads(&api).await;
+        Ok(())
+    }
+}
 
+//Not working, needs proper DB setup
+#[tokio::main]
+pub async fn main() {
+    // One chain from RPC_URL/CHAIN_ID, or several from CHAINS, all monitored concurrently
+    let registry = ChainRegistry::from_env().unwrap_or_else(|e| panic!("{}", e));
+    // Built up front so a bad detector config stops every chain before any of them start
+    let rules = RuleSet::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let detectors = DetectorRegistry::builtin().with_rules(&rules).unwrap_or_else(|e| panic!("{}", e));
+    let scoring = ScoreConfig::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let chains: Vec<_> = registry
+        .into_chains()
+        .map(|chain| {
+            let built = detectors.build(&chain).unwrap_or_else(|e| panic!("{}: {}", chain.name, e));
+            (chain, built)
+        })
+        .collect();
+    // Each on its own task, one chain failing leaves the others running
+    let tasks = chains.into_iter().map(|(chain, detectors)| {
+        let name = chain.name.clone();
+        let scoring = scoring.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run(chain, detectors, scoring).await {
+                eprintln!("{}: stopped, {}", name, e);
+            }
+        })
+    });
+    for result in join_all(tasks).await {
+        if let Err(e) = result {
+            eprintln!("chain task panicked: {}", e);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use insolvent_detect_signal::api::{EthJsonRpc, InfuraAPIHttp};
+use insolvent_detect_signal::api::{EthJsonRpc, HttpJsonRpc};
 use std::{env, fs::File, io::BufWriter};
 
 /// Retrieves a single block and writes to file.
@@ -12,7 +12,7 @@ pub async fn main() {
         panic!("Accepts a single block");
     }
 
-    let api = InfuraAPIHttp;
+    let api = HttpJsonRpc::from_env().unwrap_or_else(|e| panic!("{}", e));
     if let Ok(block_number) = args.get(1).unwrap().parse::<u128>() {
         if let Ok(block) = api.get_block_by_number_hash(block_number).await {
             let file = File::create("block.json").unwrap();
@@ -1,4 +1,4 @@
-use insolvent_detect_signal::api::{EthJsonRpc, InfuraAPIHttp};
+use insolvent_detect_signal::api::{EthJsonRpc, HttpJsonRpc};
 use std::{env, fs::File, io::BufWriter};
 
 /// Retrieves a single receipt and writes to file.
@@ -12,16 +12,13 @@ pub async fn main() {
         panic!("Accepts a single transaction hash");
     }
 
-    let api = InfuraAPIHttp;
-    if let Ok(transaction_hash) = args.get(1).unwrap().parse::<String>() {
-        if let Ok(receipt) = api.get_transaction_receipt(&transaction_hash).await {
-            let file = File::create("transaction.json").unwrap();
-            let writer = BufWriter::new(file);
-            // Tried to print to file here and there was an issue with how strings are escaped by
-            // println!, easier although less flexible to do this
-            let _ = serde_json::to_writer(writer, &receipt);
-        }
-    } else {
-        panic!("Must pass String parseable value");
+    let api = HttpJsonRpc::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let transaction_hash = args.get(1).unwrap();
+    if let Ok(receipt) = api.get_transaction_receipt(transaction_hash).await {
+        let file = File::create("transaction.json").unwrap();
+        let writer = BufWriter::new(file);
+        // Tried to print to file here and there was an issue with how strings are escaped by
+        // println!, easier although less flexible to do this
+        let _ = serde_json::to_writer(writer, &receipt);
     }
-}
\ No newline at end of file
+}
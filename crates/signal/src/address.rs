@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use alloy_primitives::{hex, Address};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The one form addresses are compared, used as keys and stored in. Equality is on the bytes so
+/// formatting never matters once parsed, and it is always written out as lowercase `0x` hex so
+/// payloads and DB rows for the same address are identical.
+///
+/// Parsing accepts all lowercase or all uppercase hex, mixed case has to be a valid EIP-55
+/// checksum. A typo in a checksummed address from a config list is an error rather than an
+/// address that never matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CanonicalAddress(Address);
+
+impl CanonicalAddress {
+    pub const fn new(address: Address) -> Self {
+        Self(address)
+    }
+
+    pub fn address(&self) -> Address {
+        self.0
+    }
+
+    /// EIP-55 form, for display to people. Payloads use the lowercase form.
+    pub fn to_checksum(&self) -> String {
+        self.0.to_checksum(None)
+    }
+}
+
+impl From<Address> for CanonicalAddress {
+    fn from(address: Address) -> Self {
+        Self(address)
+    }
+}
+
+impl From<CanonicalAddress> for Address {
+    fn from(address: CanonicalAddress) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for CanonicalAddress {
+    type Err = AddressParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AddressParseError::Invalid(s.to_string()));
+        }
+        let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper {
+            return Address::parse_checksummed(format!("0x{}", digits), None)
+                .map(Self)
+                .map_err(|_| AddressParseError::Checksum(s.to_string()));
+        }
+        digits.parse().map(Self).map_err(|_| AddressParseError::Invalid(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for CanonicalAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Address's own Display is checksummed
+        f.write_str(&hex::encode_prefixed(self.0))
+    }
+}
+
+impl Serialize for CanonicalAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CanonicalAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressParseError {
+    /// Not 20 bytes of hex
+    Invalid(String),
+    /// Mixed case that isn't the EIP-55 checksum of the address
+    Checksum(String),
+}
+
+impl std::error::Error for AddressParseError {}
+
+impl std::fmt::Display for AddressParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressParseError::Invalid(s) => write!(f, "Invalid address: {}", s),
+            AddressParseError::Checksum(s) => write!(f, "Invalid checksum for address: {}", s),
+        }
+    }
+}
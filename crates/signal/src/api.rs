@@ -1,44 +1,292 @@
-use serde::{Deserialize, Serialize};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::env;
+use std::{env, time::Duration};
 
+use crate::address::CanonicalAddress;
+use crate::model::{Block, Log, Receipt, Transaction};
+
+mod batch;
+mod cache;
+mod error;
+#[cfg(unix)]
+mod ipc;
+mod multi;
+mod params;
+mod pubsub;
+mod replay;
+mod retry;
+mod trace;
+mod ws;
+
+pub use batch::{BatchResponse, JsonRpcBatch};
+pub use cache::{CacheConfig, CacheStats, CachedJsonRpc};
+pub use error::RpcError;
+#[cfg(unix)]
+pub use ipc::IpcJsonRpc;
+pub use multi::{MultiJsonRpc, MultiMode, ProviderHealth};
+pub use params::{AccountOverride, BlockTag, CallRequest, StateOverride};
+pub use retry::{RateLimit, RetryPolicy};
+use retry::TokenBucket;
+pub use pubsub::{EthPubSub, SubscriptionStream};
+pub use replay::{RecordingJsonRpc, ReplayJsonRpc};
+pub use trace::{AccountState, CallFrame, CallKind, PrestateTrace, TransactionTrace};
+pub use ws::WsJsonRpc;
+
+/// Every transport implements [EthJsonRpc::request] and [EthJsonRpc::batch], the named methods are
+/// built on top of those so a new transport (or a wrapper around one) gets all of them for free.
+///
+/// The `Value` methods return the whole response body, the fixture writers in `bin/` use those.
+/// Everything else decodes `result` into the types in [crate::model].
 #[async_trait::async_trait]
 pub trait EthJsonRpc {
-    async fn get_transaction_receipt(&self, hash: &str) -> Result<Value, Box<dyn std::error::Error>>;
+    /// Sends a single request and returns the full response body. A JSON-RPC error object in the
+    /// body is returned as [RpcError::JsonRpc].
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError>;
+
+    /// Sends every request in one round trip. The outer error is for the whole batch failing
+    /// (transport etc.), the inner results are per request and in the order they were pushed.
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError>;
+
+    /// Fails with [RpcError::NullResult] if the transaction hasn't been mined yet.
+    async fn get_transaction_receipt(&self, hash: &str) -> Result<Value, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_transaction_receipt(hash);
+        require_body(&req, self.request(&req).await?)
+    }
+
     async fn get_block_by_number_hash(
         &self,
         block_number: u128,
-    ) -> Result<Value, Box<dyn std::error::Error>>;
-    async fn get_block_by_number_latest(&self) -> Result<Value, Box<dyn std::error::Error>>;
+    ) -> Result<Value, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_block_by_number_hash(block_number);
+        require_body(&req, self.request(&req).await?)
+    }
+
+    async fn get_block_by_number_latest(&self) -> Result<Value, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_block_by_number_latest();
+        require_body(&req, self.request(&req).await?)
+    }
+
+    /// Block with full transactions, `None` if the node doesn't have it (yet). `safe`/`finalized`
+    /// need a post-merge node, older nodes answer with an error.
+    async fn get_block(&self, block: BlockTag) -> Result<Option<Block>, RpcError> {
+        let req = match block {
+            BlockTag::Hash(hash) => JsonRpcApiRequestBuilder::get_block_by_hash(hash),
+            block => JsonRpcApiRequestBuilder::get_block_by_number_tag(block),
+        };
+        decode_result(self.request(&req).await?)
+    }
+
+    async fn get_block_by_hash(&self, hash: B256) -> Result<Option<Block>, RpcError> {
+        self.get_block(BlockTag::Hash(hash)).await
+    }
+
+    /// Receipts for every transaction in `block`, in block order, ready for
+    /// [Block::attach_receipts]. Uses `eth_getBlockReceipts` and falls back to one batch of
+    /// `eth_getTransactionReceipt` on nodes that don't have it.
+    async fn get_block_receipts(&self, block: &Block) -> Result<Vec<Receipt>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_block_receipts(block.hash());
+        match self.request(&req).await.and_then(|value| require_result(&req, value)) {
+            Ok(receipts) => return Ok(receipts),
+            Err(err) if err.is_method_not_found() || err.is_null_result() => {}
+            Err(err) => return Err(err),
+        }
+
+        let batch = block
+            .transactions
+            .iter()
+            .fold(JsonRpcBatch::new(), |batch, transaction| batch.get_receipt(transaction.hash));
+        self.batch(batch)
+            .await?
+            .into_iter()
+            .map(|receipt| Ok(serde_json::from_value(receipt?["result"].take())?))
+            .collect()
+    }
+
+    /// `None` until the transaction is mined.
+    async fn get_receipt(&self, hash: B256) -> Result<Option<Receipt>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_receipt(hash);
+        decode_result(self.request(&req).await?)
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        hash: B256,
+    ) -> Result<Option<Transaction>, RpcError> {
+        let value = self.request(&JsonRpcApiRequestBuilder::get_transaction_by_hash(hash)).await?;
+        decode_result(value)
+    }
+
+    async fn get_code(&self, address: Address, block: BlockTag) -> Result<Bytes, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_code(address, block);
+        require_result(&req, self.request(&req).await?)
+    }
+
+    async fn get_balance(&self, address: Address, block: BlockTag) -> Result<U256, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_balance(address, block);
+        require_result(&req, self.request(&req).await?)
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: U256,
+        block: BlockTag,
+    ) -> Result<B256, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_storage_at(address, slot, block);
+        require_result(&req, self.request(&req).await?)
+    }
+
+    async fn get_transaction_count(
+        &self,
+        address: Address,
+        block: BlockTag,
+    ) -> Result<u64, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_transaction_count(address, block);
+        let count: U64 = require_result(&req, self.request(&req).await?)?;
+        Ok(count.to::<u64>())
+    }
+
+    async fn call(
+        &self,
+        call: &CallRequest,
+        block: BlockTag,
+        overrides: Option<&StateOverride>,
+    ) -> Result<Bytes, RpcError> {
+        let req = JsonRpcApiRequestBuilder::call(call, block, overrides);
+        require_result(&req, self.request(&req).await?)
+    }
+
+    async fn get_logs(&self, filter: &LogFilter) -> Result<Vec<Log>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::get_logs(filter);
+        require_result(&req, self.request(&req).await?)
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, RpcError> {
+        let req = JsonRpcApiRequestBuilder::chain_id();
+        let chain_id: U64 = require_result(&req, self.request(&req).await?)?;
+        Ok(chain_id.to::<u64>())
+    }
+
+    async fn get_block_number(&self) -> Result<u64, RpcError> {
+        let req = JsonRpcApiRequestBuilder::block_number();
+        let number: U64 = require_result(&req, self.request(&req).await?)?;
+        Ok(number.to::<u64>())
+    }
+
+    // Tracing needs a node with the debug or trace namespace enabled, most public endpoints
+    // answer with method not found.
+
+    /// Call tree of a transaction from geth's callTracer.
+    async fn debug_trace_transaction(&self, hash: B256) -> Result<CallFrame, RpcError> {
+        let req = JsonRpcApiRequestBuilder::debug_trace_transaction(hash, "callTracer");
+        require_result(&req, self.request(&req).await?)
+    }
+
+    /// Accounts a transaction touched as they were before it ran, from geth's prestateTracer.
+    async fn debug_trace_transaction_prestate(&self, hash: B256) -> Result<PrestateTrace, RpcError> {
+        let req = JsonRpcApiRequestBuilder::debug_trace_transaction(hash, "prestateTracer");
+        require_result(&req, self.request(&req).await?)
+    }
+
+    /// Call tree of every transaction in a block, in block order. `BlockTag::Hash` isn't
+    /// accepted here.
+    async fn debug_trace_block_by_number(&self, block: BlockTag) -> Result<Vec<TransactionTrace>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::debug_trace_block_by_number(block, "callTracer");
+        trace::decode_geth_block(require_result(&req, self.request(&req).await?)?)
+    }
+
+    async fn debug_trace_block_by_number_prestate(
+        &self,
+        block: BlockTag,
+    ) -> Result<Vec<TransactionTrace<PrestateTrace>>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::debug_trace_block_by_number(block, "prestateTracer");
+        trace::decode_geth_block(require_result(&req, self.request(&req).await?)?)
+    }
+
+    /// Parity-style `trace_block` (Erigon, Reth, Nethermind) turned into the same call trees as
+    /// [EthJsonRpc::debug_trace_block_by_number]. Block rewards are dropped.
+    async fn trace_block(&self, block: BlockTag) -> Result<Vec<TransactionTrace>, RpcError> {
+        let req = JsonRpcApiRequestBuilder::trace_block(block);
+        trace::decode_parity_block(require_result(&req, self.request(&req).await?)?)
+    }
 }
 
-/// Need to consider standardization here.
-///
-/// Do we want a JsonRpcRequest type for each type of request to eth source? Or are these
-/// standard structs enough?
+/// Checks the body for a JSON-RPC error object. Every transport runs responses through this so
+/// an error is never handed back as a successful [Value].
+fn check_response(value: Value) -> Result<Value, RpcError> {
+    match RpcError::from_response(&value) {
+        Some(err) => Err(err),
+        None => Ok(value),
+    }
+}
 
-///Because ETH JSON-RPC API is fixed this should be reusable code
-#[derive(Serialize, Deserialize, Debug)]
-struct JsonRpcResponse {
-    jsonrpc: String,
-    id: String,
-    result: String,
+/// Pulls `result` out of a response, a null result becomes `None`.
+fn decode_result<T: DeserializeOwned>(mut value: Value) -> Result<Option<T>, RpcError> {
+    check_response(value.take()).map(|mut value| value["result"].take()).and_then(|result| match result {
+        Value::Null => Ok(None),
+        result => Ok(Some(serde_json::from_value(result)?)),
+    })
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct JsonRpcRequest {
+fn require_result<T: DeserializeOwned>(req: &JsonRpcRequest, value: Value) -> Result<T, RpcError> {
+    decode_result(value)?.ok_or_else(|| RpcError::NullResult(req.method.clone()))
+}
+
+/// Older methods hand back the whole body, null result is still an error for those.
+fn require_body(req: &JsonRpcRequest, value: Value) -> Result<Value, RpcError> {
+    if value["result"].is_null() {
+        return Err(RpcError::NullResult(req.method.clone()));
+    }
+    Ok(value)
+}
+
+// Need to consider standardization here.
+//
+// Do we want a JsonRpcRequest type for each type of request to eth source? Or are these
+// standard structs enough?
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonRpcRequest {
     pub jsonrpc: String,
     pub method: String,
     pub id: String,
     pub params: Vec<MultipleTypes>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
-enum MultipleTypes {
+pub enum MultipleTypes {
     Str(String),
     Bool(bool),
     Int(i64),
+    Json(Value),
+}
+
+/// Filter for `eth_getLogs` and `logs` subscriptions. Empty `address` matches every contract,
+/// `None` in a topic position matches any value at that position. Block range is ignored by
+/// subscriptions.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<CanonicalAddress>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Option<Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<BlockTag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_block: Option<BlockTag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<B256>,
+}
+
+/// Streams that can be opened with `eth_subscribe`, only available on push transports.
+#[derive(Clone, Debug)]
+pub enum SubscriptionKind {
+    NewHeads,
+    Logs(LogFilter),
+    NewPendingTransactions,
 }
 
 /// This should produce something like [JsonRpcRequest] because these are mostly standardized across
@@ -46,6 +294,93 @@ enum MultipleTypes {
 struct JsonRpcApiRequestBuilder;
 
 impl JsonRpcApiRequestBuilder {
+    fn request(method: &str, params: Vec<MultipleTypes>) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: "1".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    fn json<T: Serialize>(value: &T) -> MultipleTypes {
+        // Only used with our own param types which always serialize
+        MultipleTypes::Json(serde_json::to_value(value).unwrap())
+    }
+
+    fn get_block_receipts(hash: B256) -> JsonRpcRequest {
+        Self::request("eth_getBlockReceipts", vec![Self::json(&hash)])
+    }
+
+    fn get_receipt(hash: B256) -> JsonRpcRequest {
+        Self::request("eth_getTransactionReceipt", vec![Self::json(&hash)])
+    }
+
+    fn get_block_by_number_tag(block: BlockTag) -> JsonRpcRequest {
+        Self::request("eth_getBlockByNumber", vec![Self::json(&block), MultipleTypes::Bool(true)])
+    }
+
+    fn get_block_by_hash(hash: B256) -> JsonRpcRequest {
+        Self::request("eth_getBlockByHash", vec![Self::json(&hash), MultipleTypes::Bool(true)])
+    }
+
+    fn get_transaction_by_hash(hash: B256) -> JsonRpcRequest {
+        Self::request("eth_getTransactionByHash", vec![Self::json(&hash)])
+    }
+
+    fn get_code(address: Address, block: BlockTag) -> JsonRpcRequest {
+        Self::request("eth_getCode", vec![Self::json(&address), Self::json(&block)])
+    }
+
+    fn get_balance(address: Address, block: BlockTag) -> JsonRpcRequest {
+        Self::request("eth_getBalance", vec![Self::json(&address), Self::json(&block)])
+    }
+
+    fn get_storage_at(address: Address, slot: U256, block: BlockTag) -> JsonRpcRequest {
+        Self::request(
+            "eth_getStorageAt",
+            vec![Self::json(&address), Self::json(&slot), Self::json(&block)],
+        )
+    }
+
+    fn get_transaction_count(address: Address, block: BlockTag) -> JsonRpcRequest {
+        Self::request("eth_getTransactionCount", vec![Self::json(&address), Self::json(&block)])
+    }
+
+    fn call(call: &CallRequest, block: BlockTag, overrides: Option<&StateOverride>) -> JsonRpcRequest {
+        let mut params = vec![Self::json(call), Self::json(&block)];
+        if let Some(overrides) = overrides {
+            params.push(Self::json(overrides));
+        }
+        Self::request("eth_call", params)
+    }
+
+    fn get_logs(filter: &LogFilter) -> JsonRpcRequest {
+        Self::request("eth_getLogs", vec![Self::json(filter)])
+    }
+
+    fn debug_trace_transaction(hash: B256, tracer: &str) -> JsonRpcRequest {
+        let options = MultipleTypes::Json(serde_json::json!({ "tracer": tracer }));
+        Self::request("debug_traceTransaction", vec![Self::json(&hash), options])
+    }
+
+    fn debug_trace_block_by_number(block: BlockTag, tracer: &str) -> JsonRpcRequest {
+        let options = MultipleTypes::Json(serde_json::json!({ "tracer": tracer }));
+        Self::request("debug_traceBlockByNumber", vec![Self::json(&block), options])
+    }
+
+    fn trace_block(block: BlockTag) -> JsonRpcRequest {
+        Self::request("trace_block", vec![Self::json(&block)])
+    }
+
+    fn chain_id() -> JsonRpcRequest {
+        Self::request("eth_chainId", Vec::new())
+    }
+
+    fn block_number() -> JsonRpcRequest {
+        Self::request("eth_blockNumber", Vec::new())
+    }
+
     fn get_transaction_receipt(hash: &str) -> JsonRpcRequest {
         JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
@@ -67,6 +402,36 @@ impl JsonRpcApiRequestBuilder {
         }
     }
 
+    fn subscribe(kind: &SubscriptionKind) -> JsonRpcRequest {
+        let params = match kind {
+            SubscriptionKind::NewHeads => vec![MultipleTypes::Str("newHeads".to_string())],
+            SubscriptionKind::Logs(filter) => vec![
+                MultipleTypes::Str("logs".to_string()),
+                // Serializing a struct of strings can't fail
+                MultipleTypes::Json(serde_json::to_value(filter).unwrap()),
+            ],
+            SubscriptionKind::NewPendingTransactions => {
+                vec![MultipleTypes::Str("newPendingTransactions".to_string())]
+            }
+        };
+
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: "1".to_string(),
+            method: "eth_subscribe".to_string(),
+            params,
+        }
+    }
+
+    fn unsubscribe(subscription_id: &str) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: "1".to_string(),
+            method: "eth_unsubscribe".to_string(),
+            params: vec![MultipleTypes::Str(subscription_id.to_string())],
+        }
+    }
+
     fn get_block_by_number_hash(block_number: u128) -> JsonRpcRequest {
         let mut block_hex = format!("{:x}", block_number);
         block_hex.insert_str(0, "0x");
@@ -80,83 +445,315 @@ impl JsonRpcApiRequestBuilder {
     }
 }
 
+/// Returned when a [ProviderConfig] can't be turned into a working provider. We want to fail
+/// when the provider is built rather than on the first request.
 #[derive(Debug)]
-struct InfuraAPIHttpJsonError;
+pub enum ProviderConfigError {
+    MissingEnv(&'static str),
+    InvalidEnv(&'static str, String),
+    InvalidUrl(String),
+    InvalidAuthHeader(String),
+    EmptyToken,
+    InvalidChainId,
+    InvalidRateLimit,
+    /// [MultiMode::Quorum] that no answer could meet
+    InvalidQuorum { required: usize, providers: usize },
+    Client(String),
+}
 
-impl std::error::Error for InfuraAPIHttpJsonError {}
+impl std::error::Error for ProviderConfigError {}
 
-impl std::fmt::Display for InfuraAPIHttpJsonError {
+impl std::fmt::Display for ProviderConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Error when tryign to unpack text response")
+        match self {
+            ProviderConfigError::MissingEnv(var) => write!(f, "${} is not set", var),
+            ProviderConfigError::InvalidEnv(var, val) => write!(f, "${} has invalid value: {}", var, val),
+            ProviderConfigError::InvalidUrl(url) => write!(f, "Invalid provider url: {}", url),
+            ProviderConfigError::InvalidAuthHeader(name) => write!(f, "Invalid auth header: {}", name),
+            ProviderConfigError::EmptyToken => write!(f, "Provider path token is empty"),
+            ProviderConfigError::InvalidChainId => write!(f, "Chain id must be non-zero"),
+            ProviderConfigError::InvalidRateLimit => write!(f, "Rate limit must be positive"),
+            ProviderConfigError::InvalidQuorum { required, providers } => {
+                write!(f, "Quorum of {} can't be met by {} providers", required, providers)
+            }
+            ProviderConfigError::Client(err) => write!(f, "Could not build http client: {}", err),
+        }
+    }
+}
+
+/// How the provider expects to be authenticated. Infura/Alchemy put a token in the path, hosted
+/// Reth nodes usually sit behind a header, local nodes need nothing.
+#[derive(Clone, Debug)]
+pub enum ProviderAuth {
+    None,
+    Header { name: String, value: String },
+    PathToken(String),
+}
+
+/// Everything needed to talk to a single JSON-RPC provider.
+#[derive(Clone, Debug)]
+pub struct ProviderConfig {
+    pub url: String,
+    pub auth: ProviderAuth,
+    pub timeout: Duration,
+    pub chain_id: u64,
+    pub retry: RetryPolicy,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl ProviderConfig {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const MAINNET_CHAIN_ID: u64 = 1;
+    const INFURA_MAINNET_URL: &'static str = "https://mainnet.infura.io/v3/";
+
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            auth: ProviderAuth::None,
+            timeout: Self::DEFAULT_TIMEOUT,
+            chain_id: Self::MAINNET_CHAIN_ID,
+            retry: RetryPolicy::default(),
+            rate_limit: None,
+        }
+    }
+
+    pub fn infura(token: &str) -> Self {
+        Self::new(Self::INFURA_MAINNET_URL).with_auth(ProviderAuth::PathToken(token.to_string()))
+    }
+
+    pub fn with_auth(mut self, auth: ProviderAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Reads `$RPC_URL` if set, otherwise falls back to Infura mainnet with `$TOKEN`. `$CHAIN_ID`
+    /// and everything in [ProviderConfig::with_env_overrides] are optional.
+    pub fn from_env() -> Result<Self, ProviderConfigError> {
+        let mut config = match (env::var("RPC_URL"), env::var("TOKEN")) {
+            (Ok(url), _) => Self::new(&url),
+            (Err(_), Ok(token)) => Self::infura(&token),
+            _ => return Err(ProviderConfigError::MissingEnv("TOKEN")),
+        };
+
+        if let Ok(chain_id) = env::var("CHAIN_ID") {
+            let id = chain_id
+                .parse::<u64>()
+                .map_err(|_| ProviderConfigError::InvalidEnv("CHAIN_ID", chain_id.clone()))?;
+            config = config.with_chain_id(id);
+        }
+        config.with_env_overrides()
+    }
+
+    /// Applies `$RPC_TIMEOUT` (seconds), `$RPC_MAX_RETRIES` and `$RPC_RATE_LIMIT` (requests per
+    /// second) if they are set, for configs built without [ProviderConfig::from_env].
+    pub fn with_env_overrides(self) -> Result<Self, ProviderConfigError> {
+        let mut config = self;
+        if let Ok(timeout) = env::var("RPC_TIMEOUT") {
+            let secs = timeout
+                .parse::<u64>()
+                .map_err(|_| ProviderConfigError::InvalidEnv("RPC_TIMEOUT", timeout.clone()))?;
+            config = config.with_timeout(Duration::from_secs(secs));
+        }
+
+        if let Ok(max_retries) = env::var("RPC_MAX_RETRIES") {
+            let retries = max_retries
+                .parse::<u32>()
+                .map_err(|_| ProviderConfigError::InvalidEnv("RPC_MAX_RETRIES", max_retries.clone()))?;
+            config.retry.max_retries = retries;
+        }
+
+        if let Ok(rate_limit) = env::var("RPC_RATE_LIMIT") {
+            let rps = rate_limit
+                .parse::<f64>()
+                .map_err(|_| ProviderConfigError::InvalidEnv("RPC_RATE_LIMIT", rate_limit.clone()))?;
+            config = config.with_rate_limit(RateLimit::per_second(rps));
+        }
+        Ok(config)
+    }
+
+    /// Full url that requests are sent to, including any path token.
+    pub fn endpoint(&self) -> Result<reqwest::Url, ProviderConfigError> {
+        let mut url = self.url.clone();
+        if let ProviderAuth::PathToken(token) = &self.auth {
+            if token.is_empty() {
+                return Err(ProviderConfigError::EmptyToken);
+            }
+            if !url.ends_with('/') {
+                url.push('/');
+            }
+            url.push_str(token);
+        }
+
+        let parsed = reqwest::Url::parse(&url).map_err(|_| ProviderConfigError::InvalidUrl(self.url.clone()))?;
+        match parsed.scheme() {
+            "http" | "https" | "ws" | "wss" => Ok(parsed),
+            _ => Err(ProviderConfigError::InvalidUrl(self.url.clone())),
+        }
+    }
+
+    pub fn is_websocket(&self) -> bool {
+        self.url.starts_with("ws://") || self.url.starts_with("wss://")
+    }
+
+    /// A url without a scheme is taken as the path to a node's IPC socket, e.g.
+    /// `/var/run/reth/reth.ipc`.
+    pub fn is_ipc(&self) -> bool {
+        !self.url.is_empty() && !self.url.contains("://")
+    }
+
+    fn headers(&self) -> Result<reqwest::header::HeaderMap, ProviderConfigError> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let ProviderAuth::Header { name, value } = &self.auth {
+            let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ProviderConfigError::InvalidAuthHeader(name.clone()))?;
+            let mut header_value = reqwest::header::HeaderValue::from_str(value)
+                .map_err(|_| ProviderConfigError::InvalidAuthHeader(name.clone()))?;
+            header_value.set_sensitive(true);
+            headers.insert(header_name, header_value);
+        }
+        Ok(headers)
     }
 }
 
 /// Produces transactions/blocks/smart contracts/etc. Should have little logic beyond wrapping the
 /// implementation details of how these things are fetched. Because eth API is standardized across
-/// nodes, this logic should also remain abstracted away.
+/// nodes, this logic should also remain abstracted away and any provider (Infura, Alchemy, our
+/// own Reth node, anvil) can be used by changing [ProviderConfig].
 ///
 /// Currently, this is tightly bound to serde_json and the block module. In practice, it would be
 /// ideal to build out types that can be passed around internally that are totally separated from
 /// the method of calling but this will require a lot deeper research into eth API.
 ///
-/// Request management should also be wrapped here i.e. the re-use of reqwest::Client (or the re-use)
-/// of a WSS impl, etc.
-pub struct InfuraAPIHttp;
-
-impl InfuraAPIHttp {
-    fn path() -> String {
-        let token = match env::var_os("TOKEN") {
-            Some(v) => v.into_string().unwrap(),
-            None => panic!("$TOKENis not set"),
+/// One pooled [reqwest::Client] is kept per provider. Transient failures are retried according to
+/// [ProviderConfig::retry] and every attempt, including retries, counts against
+/// [ProviderConfig::rate_limit].
+#[derive(Debug)]
+pub struct HttpJsonRpc {
+    config: ProviderConfig,
+    endpoint: reqwest::Url,
+    client: reqwest::Client,
+    limiter: Option<TokenBucket>,
+}
+
+impl HttpJsonRpc {
+    pub fn new(config: ProviderConfig) -> Result<Self, ProviderConfigError> {
+        if config.chain_id == 0 {
+            return Err(ProviderConfigError::InvalidChainId);
+        }
+        let limiter = match &config.rate_limit {
+            Some(limit) if limit.requests_per_second > 0.0 => Some(TokenBucket::new(limit)),
+            Some(_) => return Err(ProviderConfigError::InvalidRateLimit),
+            None => None,
         };
-        return "https://mainnet.infura.io/v3/".to_owned() + &token;
+        if config.is_websocket() {
+            return Err(ProviderConfigError::InvalidUrl(config.url.clone()));
+        }
+        let endpoint = config.endpoint()?;
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .default_headers(config.headers()?)
+            .build()
+            .map_err(|e| ProviderConfigError::Client(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            endpoint,
+            client,
+            limiter,
+        })
     }
-}
 
-#[async_trait::async_trait]
-impl EthJsonRpc for InfuraAPIHttp {
-    async fn get_transaction_receipt(&self, hash: &str) -> Result<Value, Box<dyn std::error::Error>> {
-        let path = InfuraAPIHttp::path();
-        let req = JsonRpcApiRequestBuilder::get_transaction_receipt(hash);
+    pub fn from_env() -> Result<Self, ProviderConfigError> {
+        Self::new(ProviderConfig::from_env()?)
+    }
 
-        //Possible perf implications of doing this, I don't know what this does
-        let client = reqwest::Client::new();
-        let resp = client.post(path).json(&req).send().await?;
-        if let Ok(txt) = resp.text().await {
-            Ok(serde_json::from_str(&txt)?)
-        } else {
-            Err(Box::new(InfuraAPIHttpJsonError))
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+
+    async fn send<T: Serialize + Sync>(&self, req: &T) -> Result<Value, RpcError> {
+        let mut attempt = 0;
+        loop {
+            let wait = match self.send_once(req).await {
+                Ok(value) => return Ok(value),
+                Err((err, retry_after)) if err.is_retryable() && attempt < self.config.retry.max_retries => {
+                    let backoff = self.config.retry.backoff(attempt);
+                    retry_after.map_or(backoff, |after| after.min(self.config.retry.max_backoff))
+                }
+                Err((err, _)) => return Err(err),
+            };
+            tokio::time::sleep(wait).await;
+            attempt += 1;
         }
     }
 
-    async fn get_block_by_number_latest(&self) -> Result<Value, Box<dyn std::error::Error>> {
-        let path = InfuraAPIHttp::path();
-        let req = JsonRpcApiRequestBuilder::get_block_by_number_latest();
+    /// Error comes with the provider's Retry-After, if it sent one.
+    async fn send_once<T: Serialize + Sync>(&self, req: &T) -> Result<Value, (RpcError, Option<Duration>)> {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
 
-        //Possible perf implications of doing this, I don't know what this does
-        let client = reqwest::Client::new();
-        let resp = client.post(path).json(&req).send().await?;
-        if let Ok(txt) = resp.text().await {
-            Ok(serde_json::from_str(&txt)?)
-        } else {
-            Err(Box::new(InfuraAPIHttpJsonError))
+        let resp = self
+            .client
+            .post(self.endpoint.clone())
+            .json(req)
+            .send()
+            .await
+            .map_err(|e| (RpcError::from(e), None))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let retry_after = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err((RpcError::HttpStatus(status.as_u16()), retry_after));
         }
+
+        let txt = resp.text().await.map_err(|e| (RpcError::from(e), None))?;
+        let value: Value = serde_json::from_str(&txt).map_err(|e| (RpcError::from(e), None))?;
+        check_response(value).map_err(|e| (e, None))
     }
+}
 
-    async fn get_block_by_number_hash(
-        &self,
-        block_number: u128,
-    ) -> Result<Value, Box<dyn std::error::Error>> {
-        let path = InfuraAPIHttp::path();
-        let req = JsonRpcApiRequestBuilder::get_block_by_number_hash(block_number);
+#[async_trait::async_trait]
+impl EthJsonRpc for HttpJsonRpc {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        self.send(req).await
+    }
 
-        //Possible perf implications of doing this, I don't know what this does
-        let client = reqwest::Client::new();
-        let resp = client.post(path).json(&req).send().await?;
-        if let Ok(txt) = resp.text().await {
-            Ok(serde_json::from_str(&txt)?)
-        } else {
-            Err(Box::new(InfuraAPIHttpJsonError))
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        if batch.is_empty() {
+            return Ok(Vec::new());
         }
+        let requests = batch.into_requests();
+        let responses = self.send(&requests).await?;
+        Ok(batch::correlate(&requests, responses))
     }
 }
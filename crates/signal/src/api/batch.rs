@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use alloy_primitives::B256;
+use serde_json::Value;
+
+use super::{JsonRpcApiRequestBuilder, JsonRpcRequest, RpcError};
+
+/// Per-item results, items fail independently of each other.
+pub type BatchResponse = Vec<Result<Value, RpcError>>;
+
+/// Several requests sent to the provider as one JSON array. Each request gets a unique id when
+/// the batch is sent and responses are matched back to requests by that id, so results are always
+/// returned in the order the requests were pushed regardless of the order the node answers in.
+///
+/// Each successful item is the full response object (with `result`), same as the single request
+/// methods on [super::EthJsonRpc]. `result` decodes into the types in [crate::model]. A null result
+/// is returned as [RpcError::NullResult].
+#[derive(Debug, Default)]
+pub struct JsonRpcBatch {
+    requests: Vec<JsonRpcRequest>,
+}
+
+impl JsonRpcBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn get_transaction_receipt(mut self, hash: &str) -> Self {
+        self.requests.push(JsonRpcApiRequestBuilder::get_transaction_receipt(hash));
+        self
+    }
+
+    pub fn get_receipt(mut self, hash: B256) -> Self {
+        self.requests.push(JsonRpcApiRequestBuilder::get_receipt(hash));
+        self
+    }
+
+    pub fn get_block_by_number_hash(mut self, block_number: u128) -> Self {
+        self.requests.push(JsonRpcApiRequestBuilder::get_block_by_number_hash(block_number));
+        self
+    }
+
+    pub fn get_block_by_number_latest(mut self) -> Self {
+        self.requests.push(JsonRpcApiRequestBuilder::get_block_by_number_latest());
+        self
+    }
+
+    /// Any request, for methods without a helper above.
+    pub fn request(mut self, req: JsonRpcRequest) -> Self {
+        self.requests.push(req);
+        self
+    }
+
+    /// Requests in the order they were pushed, for wrappers and test stand-ins that answer
+    /// batches themselves.
+    pub fn requests(&self) -> &[JsonRpcRequest] {
+        &self.requests
+    }
+
+    /// Gives every request a unique id, ids are only unique within the batch.
+    pub(super) fn into_requests(self) -> Vec<JsonRpcRequest> {
+        self.requests
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut req)| {
+                req.id = (i + 1).to_string();
+                req
+            })
+            .collect()
+    }
+}
+
+fn id_key(id: &Value) -> Option<String> {
+    // Some nodes echo string ids back as numbers and vice versa
+    match id {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn missing(id: &str) -> RpcError {
+    RpcError::JsonRpc {
+        code: RpcError::MISSING_RESPONSE_CODE,
+        message: format!("No response for request id {}", id),
+        data: None,
+    }
+}
+
+fn item_result(req: &JsonRpcRequest, value: Value) -> Result<Value, RpcError> {
+    match RpcError::from_response(&value) {
+        Some(err) => Err(err),
+        None if value["result"].is_null() => Err(RpcError::NullResult(req.method.clone())),
+        None => Ok(value),
+    }
+}
+
+/// Matches responses to the ids of the requests that were sent. `responses` is the array the node
+/// returned, anything else means the node didn't understand the batch and every item fails, with
+/// the node's error if it sent one.
+pub(super) fn correlate(requests: &[JsonRpcRequest], responses: Value) -> BatchResponse {
+    let items = match responses {
+        Value::Array(items) => items,
+        responses => {
+            let err = RpcError::from_response(&responses)
+                .unwrap_or_else(|| RpcError::Decode("Batch response was not an array".to_string()));
+            return requests.iter().map(|_| Err(err.clone())).collect();
+        }
+    };
+
+    let mut by_id: HashMap<String, Value> = HashMap::new();
+    for item in items {
+        if let Some(key) = id_key(&item["id"]) {
+            by_id.insert(key, item);
+        }
+    }
+
+    requests
+        .iter()
+        .map(|req| match by_id.remove(&req.id) {
+            Some(value) => item_result(req, value),
+            None => Err(missing(&req.id)),
+        })
+        .collect()
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use alloy_primitives::keccak256;
+use serde_json::{json, Value};
+
+use super::{BatchResponse, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, RpcError};
+
+/// Where [CachedJsonRpc] keeps responses and how much it keeps.
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+    /// Least recently used entries are evicted once the cache is over this size.
+    pub max_bytes: u64,
+    /// Blocks this far behind the head are taken as final. 64 is two epochs, which is roughly
+    /// where mainnet finalizes.
+    pub finality_depth: u64,
+}
+
+impl CacheConfig {
+    pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+    pub const DEFAULT_FINALITY_DEPTH: u64 = 64;
+
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            finality_depth: Self::DEFAULT_FINALITY_DEPTH,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_finality_depth(mut self, finality_depth: u64) -> Self {
+        self.finality_depth = finality_depth;
+        self
+    }
+}
+
+/// Counters since the cache was opened, `entries` and `bytes` are what is on disk now.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Why a response can be cached.
+enum Rule {
+    /// Addressed by hash, the answer can never change.
+    Immutable,
+    /// Only stable once this block is final.
+    AtBlock(u64),
+}
+
+fn parse_number(value: &Value) -> Option<u64> {
+    let number = value.as_str()?.strip_prefix("0x")?;
+    u64::from_str_radix(number, 16).ok()
+}
+
+/// `None` for anything that can change, which includes every `latest`/`pending`/`safe` request.
+fn rule(req: &JsonRpcRequest, result: &Value) -> Option<Rule> {
+    let params = serde_json::to_value(&req.params).ok()?;
+    match req.method.as_str() {
+        "eth_getBlockByHash" => Some(Rule::Immutable),
+        "eth_getBlockByNumber" => parse_number(&params[0]).map(Rule::AtBlock),
+        // We only ask by block hash, a number is only final like a block
+        "eth_getBlockReceipts" => match &params[0] {
+            Value::String(block) if block.len() == 66 => Some(Rule::Immutable),
+            block => parse_number(block).map(Rule::AtBlock),
+        },
+        // A receipt moves to another block if its block is reorged out
+        "eth_getTransactionReceipt" => parse_number(&result["blockNumber"]).map(Rule::AtBlock),
+        "eth_getCode" => match &params[1] {
+            Value::Object(block) if block.contains_key("blockHash") => Some(Rule::Immutable),
+            block => parse_number(block).map(Rule::AtBlock),
+        },
+        _ => None,
+    }
+}
+
+/// Content address of a request: method plus params, ids don't matter. serde_json sorts object
+/// keys so the same params always give the same key.
+fn cache_key(req: &JsonRpcRequest) -> String {
+    let params = serde_json::to_value(&req.params).unwrap_or(Value::Null);
+    keccak256(format!("{}{}", req.method, params)).to_string()
+}
+
+struct Entry {
+    size: u64,
+    last_used: u64,
+}
+
+/// Which keys are on disk and when they were last used, so misses and eviction don't touch the
+/// file system.
+#[derive(Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    bytes: u64,
+    clock: u64,
+}
+
+impl Index {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.last_used = self.clock;
+        }
+    }
+
+    fn insert(&mut self, key: String, size: u64) {
+        self.clock += 1;
+        let entry = Entry {
+            size,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.bytes -= old.size;
+        }
+        self.bytes += size;
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(old) = self.entries.remove(key) {
+            self.bytes -= old.size;
+        }
+    }
+
+    /// Drops least recently used keys until we're under `max_bytes`, returns them so the files
+    /// can be deleted.
+    fn evict(&mut self, max_bytes: u64) -> Vec<String> {
+        let mut evicted = Vec::new();
+        if self.bytes <= max_bytes {
+            return evicted;
+        }
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort();
+        for (_, key) in by_age {
+            if self.bytes <= max_bytes {
+                break;
+            }
+            self.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+/// [EthJsonRpc] decorator that keeps immutable responses on disk, so backfills and re-runs over
+/// blocks we've already seen don't hit the provider at all.
+///
+/// Only responses that can't change are stored: blocks and code by hash, and blocks by number,
+/// receipts and code at a block number once that block is [CacheConfig::finality_depth] behind
+/// the head. `latest`, `pending` and other tags always go to the provider, as does everything
+/// not listed here. Each response is a file named by the hash of the request.
+///
+/// The cache is best effort, a file we can't read or write is treated as a miss.
+pub struct CachedJsonRpc<P> {
+    inner: P,
+    config: CacheConfig,
+    index: Mutex<Index>,
+    stats: Mutex<CacheStats>,
+    // Highest final block we know of, and when we last asked for the head
+    final_block: Mutex<(u64, Option<Instant>)>,
+}
+
+impl<P: EthJsonRpc + Send + Sync> CachedJsonRpc<P> {
+    /// Don't ask for the head more often than this, a batch of recent blocks would otherwise ask
+    /// once per block.
+    const HEAD_REFRESH: Duration = Duration::from_secs(2);
+
+    /// Creates the cache directory if needed and picks up entries from earlier runs, oldest
+    /// first in eviction order.
+    pub fn new(inner: P, config: CacheConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+
+        let mut existing = Vec::new();
+        for dir_entry in fs::read_dir(&config.dir)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()) else {
+                continue;
+            };
+            let metadata = dir_entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            existing.push((modified, key, metadata.len()));
+        }
+        existing.sort();
+
+        let mut index = Index::default();
+        for (_, key, size) in existing {
+            index.insert(key, size);
+        }
+        for key in index.evict(config.max_bytes) {
+            let _ = fs::remove_file(config.dir.join(format!("{}.json", key)));
+        }
+
+        Ok(Self {
+            inner,
+            config,
+            index: Mutex::new(index),
+            stats: Mutex::new(CacheStats::default()),
+            final_block: Mutex::new((0, None)),
+        })
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let index = self.index.lock().unwrap();
+        CacheStats {
+            entries: index.entries.len(),
+            bytes: index.bytes,
+            ..self.stats.lock().unwrap().clone()
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.config.dir.join(format!("{}.json", key))
+    }
+
+    async fn lookup(&self, req: &JsonRpcRequest) -> Option<Value> {
+        let key = cache_key(req);
+        let cached = if self.index.lock().unwrap().entries.contains_key(&key) {
+            let bytes = tokio::fs::read(self.path(&key)).await.ok();
+            let result = bytes.and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+            let mut index = self.index.lock().unwrap();
+            match &result {
+                Some(_) => index.touch(&key),
+                // Deleted or corrupted under us
+                None => index.remove(&key),
+            }
+            result
+        } else {
+            None
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        match cached {
+            Some(result) => {
+                stats.hits += 1;
+                Some(json!({"jsonrpc": "2.0", "id": req.id, "result": result}))
+            }
+            None => {
+                stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    async fn is_final(&self, block: u64) -> bool {
+        let (known, checked) = *self.final_block.lock().unwrap();
+        if block <= known {
+            return true;
+        }
+        if checked.is_some_and(|at| at.elapsed() < Self::HEAD_REFRESH) {
+            return false;
+        }
+
+        let head = self.inner.get_block_number().await;
+        let mut final_block = self.final_block.lock().unwrap();
+        final_block.1 = Some(Instant::now());
+        if let Ok(head) = head {
+            final_block.0 = final_block.0.max(head.saturating_sub(self.config.finality_depth));
+        }
+        block <= final_block.0
+    }
+
+    async fn store(&self, req: &JsonRpcRequest, value: &Value) {
+        let result = &value["result"];
+        if result.is_null() || RpcError::from_response(value).is_some() {
+            return;
+        }
+        let cacheable = match rule(req, result) {
+            Some(Rule::Immutable) => true,
+            Some(Rule::AtBlock(block)) => self.is_final(block).await,
+            None => false,
+        };
+        if !cacheable {
+            return;
+        }
+
+        let bytes = result.to_string().into_bytes();
+        let size = bytes.len() as u64;
+        if size > self.config.max_bytes {
+            return;
+        }
+        let key = cache_key(req);
+        let path = self.path(&key);
+        // Write then rename so a reader never sees half a file
+        let tmp = path.with_extension("tmp");
+        if tokio::fs::write(&tmp, &bytes).await.is_err() || tokio::fs::rename(&tmp, &path).await.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return;
+        }
+
+        let evicted = {
+            let mut index = self.index.lock().unwrap();
+            index.insert(key, size);
+            index.evict(self.config.max_bytes)
+        };
+        for key in evicted {
+            let _ = tokio::fs::remove_file(self.path(&key)).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: EthJsonRpc + Send + Sync> EthJsonRpc for CachedJsonRpc<P> {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        if let Some(value) = self.lookup(req).await {
+            return Ok(value);
+        }
+        let value = self.inner.request(req).await?;
+        self.store(req, &value).await;
+        Ok(value)
+    }
+
+    /// Cached items are answered locally and only the misses are sent, as one batch. A batch
+    /// that is fully cached costs nothing.
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        let requests = batch.into_requests();
+        let mut results = Vec::with_capacity(requests.len());
+        let mut misses = Vec::new();
+        for (i, req) in requests.iter().enumerate() {
+            let cached = self.lookup(req).await;
+            if cached.is_none() {
+                misses.push(i);
+            }
+            results.push(cached.map(Ok));
+        }
+
+        if !misses.is_empty() {
+            let batch = misses
+                .iter()
+                .fold(JsonRpcBatch::new(), |batch, &i| batch.request(requests[i].clone()));
+            let responses = self.inner.batch(batch).await?;
+            for (&i, res) in misses.iter().zip(responses) {
+                if let Ok(value) = &res {
+                    self.store(&requests[i], value).await;
+                }
+                results[i] = Some(res);
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|res| {
+                res.unwrap_or_else(|| {
+                    Err(RpcError::JsonRpc {
+                        code: RpcError::MISSING_RESPONSE_CODE,
+                        message: "missing response".to_string(),
+                        data: None,
+                    })
+                })
+            })
+            .collect())
+    }
+}
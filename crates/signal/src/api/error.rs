@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+/// Everything that can go wrong talking to a provider. Callers mostly care about the split between
+/// "the provider is down" ([RpcError::Transport], [RpcError::Timeout], [RpcError::HttpStatus]),
+/// "the provider answered but said no" ([RpcError::JsonRpc]) and "the thing doesn't exist (yet)"
+/// ([RpcError::NullResult], e.g. a receipt for a transaction that hasn't been mined).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    /// Couldn't connect, connection dropped, or the transport task has gone away.
+    Transport(String),
+    Timeout,
+    HttpStatus(u16),
+    /// Error object returned in the response body.
+    JsonRpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    /// Request succeeded but `result` was null, holds the method name.
+    NullResult(String),
+    /// Response wasn't JSON or `result` didn't have the expected shape.
+    Decode(String),
+    /// Not enough providers agreed on a block, see [super::MultiMode::Quorum].
+    NoQuorum { required: usize, agreeing: usize },
+}
+
+impl RpcError {
+    pub const LIMIT_EXCEEDED_CODE: i64 = -32005;
+    pub const METHOD_NOT_FOUND_CODE: i64 = -32601;
+    /// Used when a batch response is missing one of our ids.
+    pub const MISSING_RESPONSE_CODE: i64 = -32603;
+
+    /// Builds from the `error` field of a response, if there is one.
+    pub fn from_response(value: &Value) -> Option<Self> {
+        let error = value.get("error").filter(|e| !e.is_null())?;
+        Some(RpcError::JsonRpc {
+            code: error["code"].as_i64().unwrap_or(0),
+            message: error["message"].as_str().unwrap_or("").to_string(),
+            data: error.get("data").cloned(),
+        })
+    }
+
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            RpcError::JsonRpc { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Worth trying the same request again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RpcError::Transport(_) | RpcError::Timeout => true,
+            RpcError::HttpStatus(status) => *status == 429 || *status >= 500,
+            RpcError::JsonRpc { code, .. } => *code == Self::LIMIT_EXCEEDED_CODE,
+            RpcError::NullResult(_) | RpcError::Decode(_) => false,
+            // Providers can disagree for a moment around a new head
+            RpcError::NoQuorum { .. } => true,
+        }
+    }
+
+    /// The node doesn't have the method. Some providers answer with their own code for this so the
+    /// message is checked as well.
+    pub fn is_method_not_found(&self) -> bool {
+        match self {
+            RpcError::JsonRpc { code, message, .. } => {
+                let message = message.to_lowercase();
+                *code == Self::METHOD_NOT_FOUND_CODE
+                    || message.contains("method not found")
+                    || message.contains("not supported")
+                    || message.contains("does not exist")
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_null_result(&self) -> bool {
+        matches!(self, RpcError::NullResult(_))
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RpcError::Transport(err) => write!(f, "Transport error: {}", err),
+            RpcError::Timeout => write!(f, "Request timed out"),
+            RpcError::HttpStatus(status) => write!(f, "Provider returned http status {}", status),
+            RpcError::JsonRpc { code, message, .. } => write!(f, "JSON-RPC error {}: {}", code, message),
+            RpcError::NullResult(method) => write!(f, "{} returned a null result", method),
+            RpcError::Decode(err) => write!(f, "Could not decode response: {}", err),
+            RpcError::NoQuorum { required, agreeing } => {
+                write!(f, "Only {} providers agreed, {} required", agreeing, required)
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for RpcError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            RpcError::Timeout
+        } else if let Some(status) = err.status() {
+            RpcError::HttpStatus(status.as_u16())
+        } else if err.is_decode() {
+            RpcError::Decode(err.to_string())
+        } else {
+            RpcError::Transport(err.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(err: serde_json::Error) -> Self {
+        RpcError::Decode(err.to_string())
+    }
+}
@@ -0,0 +1,131 @@
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+
+use super::{
+    pubsub::{PubSubClient, PubSubConnector, PubSubSocket},
+    BatchResponse, EthJsonRpc, EthPubSub, JsonRpcBatch, JsonRpcRequest, ProviderConfig, ProviderConfigError,
+    RpcError, SubscriptionKind, SubscriptionStream,
+};
+
+/// The IPC socket is a plain byte stream of JSON values, there is no framing. Reth and geth write
+/// one value per line but we don't rely on it, values are cut out of the buffer as soon as they
+/// are complete.
+struct IpcSocket {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+}
+
+impl IpcSocket {
+    /// Takes the next complete value off the front of the buffer. `Err` means the node sent
+    /// something that isn't JSON and we can't find where the next message starts.
+    fn take_value(&mut self) -> Result<Option<Value>, ()> {
+        let mut values = serde_json::Deserializer::from_slice(&self.buffer).into_iter::<Value>();
+        match values.next() {
+            Some(Ok(value)) => {
+                let offset = values.byte_offset();
+                self.buffer.drain(..offset);
+                Ok(Some(value))
+            }
+            // Value is split across reads
+            Some(Err(err)) if err.is_eof() => Ok(None),
+            Some(Err(_)) => Err(()),
+            // Only whitespace left
+            None => {
+                self.buffer.clear();
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PubSubSocket for IpcSocket {
+    async fn send_text(&mut self, mut text: String) -> bool {
+        text.push('\n');
+        self.stream.write_all(text.as_bytes()).await.is_ok()
+    }
+
+    async fn next_message(&mut self) -> Option<Value> {
+        loop {
+            match self.take_value() {
+                Ok(Some(value)) => return Some(value),
+                Ok(None) => {}
+                Err(()) => return None,
+            }
+            // read_buf is cancel safe, anything read before a cancel stays in the buffer
+            match self.stream.read_buf(&mut self.buffer).await {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+struct IpcConnector(String);
+
+#[async_trait::async_trait]
+impl PubSubConnector for IpcConnector {
+    async fn connect(&self) -> Result<Box<dyn PubSubSocket>, ProviderConfigError> {
+        let stream = UnixStream::connect(&self.0)
+            .await
+            .map_err(|e| ProviderConfigError::Client(format!("{}: {}", self.0, e)))?;
+        Ok(Box::new(IpcSocket {
+            stream,
+            buffer: Vec::new(),
+        }))
+    }
+}
+
+/// [EthJsonRpc] over a node's IPC socket (`reth.ipc`, `geth.ipc`), for when the node runs on the
+/// same machine. Same requests, batches and subscriptions as [super::WsJsonRpc] without the http
+/// or websocket overhead.
+///
+/// The config url is the socket path, auth is ignored since access is controlled by file
+/// permissions.
+pub struct IpcJsonRpc {
+    config: ProviderConfig,
+    client: PubSubClient,
+}
+
+impl IpcJsonRpc {
+    pub async fn connect(config: ProviderConfig) -> Result<Self, ProviderConfigError> {
+        if config.chain_id == 0 {
+            return Err(ProviderConfigError::InvalidChainId);
+        }
+        if !config.is_ipc() {
+            return Err(ProviderConfigError::InvalidUrl(config.url.clone()));
+        }
+        let connector = Box::new(IpcConnector(config.url.clone()));
+        let client = PubSubClient::connect(connector, config.retry.clone(), config.timeout).await?;
+        Ok(Self { config, client })
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+}
+
+#[async_trait::async_trait]
+impl EthPubSub for IpcJsonRpc {
+    async fn subscribe(&self, kind: SubscriptionKind) -> Result<SubscriptionStream, RpcError> {
+        self.client.subscribe(kind).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for IpcJsonRpc {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        self.client.request(req).await
+    }
+
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        self.client.batch(batch).await
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{join_all, select_ok};
+use serde_json::Value;
+
+use super::{BatchResponse, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, ProviderConfigError, RpcError};
+
+/// How [MultiJsonRpc] spreads requests over its providers.
+#[derive(Clone, Debug)]
+pub enum MultiMode {
+    /// Providers are tried in order. We move on to the next one on error, or if a provider's head
+    /// is more than `max_lag` blocks behind the best head we have seen.
+    Failover { max_lag: u64 },
+    /// Every provider gets the request, the first successful answer wins.
+    Fastest,
+    /// Block requests go to every provider and `required` of them must return the same block hash.
+    /// A provider that doesn't have the block, or answers without a hash, doesn't vote, if none of
+    /// them have it the result is null. Everything
+    /// else is sent as in [MultiMode::Failover] with no lag limit.
+    ///
+    /// Around a new head providers will often disagree on `latest`, so it is better to resolve a
+    /// number first and then ask for that block by number.
+    Quorum { required: usize },
+}
+
+/// Running stats for one provider. `head` is the highest block number seen from it.
+#[derive(Clone, Debug, Default)]
+pub struct ProviderHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u64,
+    pub avg_latency: Duration,
+    pub head: Option<u64>,
+}
+
+impl ProviderHealth {
+    const UNHEALTHY_AFTER: u64 = 3;
+
+    /// Between 0 and 1. Success rate, pulled down hard by recent consecutive failures.
+    pub fn score(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            return 1.0;
+        }
+        let success_rate = self.successes as f64 / total as f64;
+        success_rate / (1.0 + self.consecutive_failures as f64)
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures < Self::UNHEALTHY_AFTER
+    }
+
+    fn record(&mut self, ok: bool, latency: Duration) {
+        if ok {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+        }
+        // Simple moving average, recent requests weigh more
+        self.avg_latency = if self.successes + self.failures == 1 {
+            latency
+        } else {
+            (self.avg_latency * 4 + latency) / 5
+        };
+    }
+}
+
+fn is_block_request(req: &JsonRpcRequest) -> bool {
+    req.method == "eth_getBlockByNumber" || req.method == "eth_getBlockByHash"
+}
+
+fn block_number(value: &Value) -> Option<u64> {
+    let number = value["result"]["number"].as_str()?;
+    u64::from_str_radix(number.trim_start_matches("0x"), 16).ok()
+}
+
+/// [EthJsonRpc] over several providers so one outage doesn't blind us. See [MultiMode] for how
+/// requests are routed, per-provider stats are available from [MultiJsonRpc::health].
+pub struct MultiJsonRpc {
+    providers: Vec<Box<dyn EthJsonRpc + Send + Sync>>,
+    mode: MultiMode,
+    health: Mutex<Vec<ProviderHealth>>,
+}
+
+impl MultiJsonRpc {
+    /// A [MultiMode::Quorum] has to be reachable, between 1 and the number of providers.
+    pub fn new(
+        providers: Vec<Box<dyn EthJsonRpc + Send + Sync>>,
+        mode: MultiMode,
+    ) -> Result<Self, ProviderConfigError> {
+        if let MultiMode::Quorum { required } = mode {
+            if required == 0 || required > providers.len() {
+                return Err(ProviderConfigError::InvalidQuorum {
+                    required,
+                    providers: providers.len(),
+                });
+            }
+        }
+        let health = Mutex::new(vec![ProviderHealth::default(); providers.len()]);
+        Ok(Self {
+            providers,
+            mode,
+            health,
+        })
+    }
+
+    pub fn health(&self) -> Vec<ProviderHealth> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Asks every provider for its head so lagging providers are skipped in failover mode. Heads
+    /// are also picked up from block responses, this is for when a provider hasn't been used.
+    pub async fn refresh_heads(&self) {
+        let heads = join_all(self.providers.iter().map(|provider| async move {
+            let start = Instant::now();
+            (provider.get_block_number().await, start.elapsed())
+        }))
+        .await;
+
+        let mut health = self.health.lock().unwrap();
+        for (i, (head, latency)) in heads.into_iter().enumerate() {
+            health[i].record(head.is_ok(), latency);
+            if let Ok(head) = head {
+                health[i].head = Some(health[i].head.map_or(head, |h| h.max(head)));
+            }
+        }
+    }
+
+    fn record<T>(&self, index: usize, res: &Result<T, RpcError>, latency: Duration, head: Option<u64>) {
+        let mut health = self.health.lock().unwrap();
+        health[index].record(res.is_ok(), latency);
+        if let Some(head) = head {
+            health[index].head = Some(health[index].head.map_or(head, |h| h.max(head)));
+        }
+    }
+
+    /// Provider indexes in the order failover should try them. Healthy, in-sync providers first in
+    /// configured order, then the rest so we still try everything before giving up.
+    fn failover_order(&self, max_lag: Option<u64>) -> Vec<usize> {
+        let health = self.health.lock().unwrap();
+        let best = health.iter().filter_map(|h| h.head).max();
+        let mut order: Vec<usize> = (0..self.providers.len()).collect();
+        order.sort_by_key(|&i| {
+            let lagging = match (max_lag, best, health[i].head) {
+                (Some(max_lag), Some(best), Some(head)) => best.saturating_sub(head) > max_lag,
+                _ => false,
+            };
+            (lagging || !health[i].is_healthy(), i)
+        });
+        order
+    }
+
+    async fn request_one(&self, index: usize, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        let start = Instant::now();
+        let res = self.providers[index].request(req).await;
+        let head = match &res {
+            Ok(value) if req.method == "eth_getBlockByNumber" => block_number(value),
+            _ => None,
+        };
+        self.record(index, &res, start.elapsed(), head);
+        res
+    }
+
+    async fn failover(&self, req: &JsonRpcRequest, max_lag: Option<u64>) -> Result<Value, RpcError> {
+        let mut last_err = RpcError::Transport("no providers configured".to_string());
+        for index in self.failover_order(max_lag) {
+            match self.request_one(index, req).await {
+                // A JSON-RPC error about the request itself, e.g. invalid params or a revert, is
+                // what every node would say. A missing method only means this node lacks it
+                Err(err @ RpcError::JsonRpc { .. }) if !err.is_retryable() && !err.is_method_not_found() => {
+                    return Err(err)
+                }
+                Err(err) => last_err = err,
+                Ok(value) => return Ok(value),
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn fastest(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        if self.providers.is_empty() {
+            return Err(RpcError::Transport("no providers configured".to_string()));
+        }
+        let requests = (0..self.providers.len()).map(|index| Box::pin(self.request_one(index, req)));
+        select_ok(requests).await.map(|(value, _)| value)
+    }
+
+    async fn quorum(&self, req: &JsonRpcRequest, required: usize) -> Result<Value, RpcError> {
+        let responses = join_all((0..self.providers.len()).map(|index| self.request_one(index, req))).await;
+
+        let mut groups: HashMap<String, Vec<Value>> = HashMap::new();
+        let mut last_err = None;
+        let mut answered = false;
+        for res in responses {
+            match res {
+                // Null (unknown block) or malformed, lagging providers mustn't agree on nothing
+                Ok(value) => match value["result"]["hash"].as_str() {
+                    Some(hash) => groups.entry(hash.to_string()).or_default().push(value),
+                    None => answered = true,
+                },
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        let best = groups.into_values().max_by_key(|group| group.len());
+        match best {
+            Some(mut group) if group.len() >= required => Ok(group.remove(0)),
+            Some(group) => Err(RpcError::NoQuorum {
+                required,
+                agreeing: group.len(),
+            }),
+            // Nobody has the block yet, that's a null result rather than a disagreement
+            None if answered => Ok(serde_json::json!({"jsonrpc": "2.0", "id": req.id, "result": null})),
+            None => Err(last_err.unwrap_or(RpcError::NoQuorum { required, agreeing: 0 })),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for MultiJsonRpc {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        match &self.mode {
+            MultiMode::Failover { max_lag } => self.failover(req, Some(*max_lag)).await,
+            MultiMode::Fastest => self.fastest(req).await,
+            MultiMode::Quorum { required } if is_block_request(req) => self.quorum(req, *required).await,
+            MultiMode::Quorum { .. } => self.failover(req, None).await,
+        }
+    }
+
+    /// Batches always fail over, they can't be split across providers without losing the single
+    /// round trip.
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        let requests = batch.into_requests();
+        let max_lag = match &self.mode {
+            MultiMode::Failover { max_lag } => Some(*max_lag),
+            _ => None,
+        };
+
+        let mut last_err = RpcError::Transport("no providers configured".to_string());
+        for index in self.failover_order(max_lag) {
+            let batch = requests.iter().cloned().fold(JsonRpcBatch::new(), |batch, req| batch.request(req));
+            let start = Instant::now();
+            let res = self.providers[index].batch(batch).await;
+            self.record(index, &res, start.elapsed(), None);
+            match res {
+                Ok(res) => return Ok(res),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+/// Block parameter accepted by most state methods. `Hash` is the EIP-1898 form and is only valid
+/// where a single block is expected (not in log filter ranges).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockTag {
+    Latest,
+    Pending,
+    Earliest,
+    Safe,
+    Finalized,
+    Number(u64),
+    Hash(B256),
+}
+
+impl BlockTag {
+    pub fn to_value(&self) -> Value {
+        match self {
+            BlockTag::Latest => Value::from("latest"),
+            BlockTag::Pending => Value::from("pending"),
+            BlockTag::Earliest => Value::from("earliest"),
+            BlockTag::Safe => Value::from("safe"),
+            BlockTag::Finalized => Value::from("finalized"),
+            BlockTag::Number(number) => Value::from(format!("0x{:x}", number)),
+            BlockTag::Hash(hash) => serde_json::json!({ "blockHash": hash }),
+        }
+    }
+}
+
+impl Serialize for BlockTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+/// Transaction object for `eth_call`. Only `to` is required.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    pub to: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<Bytes>,
+}
+
+impl CallRequest {
+    pub fn new(to: Address, input: Bytes) -> Self {
+        Self {
+            to,
+            input: Some(input),
+            ..Default::default()
+        }
+    }
+}
+
+/// Per-account replacement state for `eth_call`. `state` replaces all storage, `state_diff` only
+/// the given slots, nodes reject both being set.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<HashMap<B256, B256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<HashMap<B256, B256>>,
+}
+
+pub type StateOverride = HashMap<Address, AccountOverride>;
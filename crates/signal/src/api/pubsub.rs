@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::Stream;
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{
+    batch, check_response, BatchResponse, JsonRpcApiRequestBuilder, JsonRpcBatch, JsonRpcRequest, LogFilter,
+    ProviderConfigError, RetryPolicy, RpcError, SubscriptionKind,
+};
+
+/// A connection carrying JSON-RPC messages both ways, websocket frames or the IPC byte stream.
+#[async_trait::async_trait]
+pub(super) trait PubSubSocket: Send {
+    /// Returns false once the connection is gone.
+    async fn send_text(&mut self, text: String) -> bool;
+    /// Next message from the node, `None` once the connection is gone. Has to be cancel safe, it
+    /// is raced against outgoing commands in a `select!`.
+    async fn next_message(&mut self) -> Option<Value>;
+}
+
+/// Opens sockets, for the first connection and every reconnect after it.
+#[async_trait::async_trait]
+pub(super) trait PubSubConnector: Send + Sync + 'static {
+    async fn connect(&self) -> Result<Box<dyn PubSubSocket>, ProviderConfigError>;
+}
+
+enum Command {
+    Call {
+        req: JsonRpcRequest,
+        responder: oneshot::Sender<Value>,
+    },
+    Subscribe {
+        kind: SubscriptionKind,
+        sink: mpsc::UnboundedSender<Value>,
+        responder: oneshot::Sender<Result<u64, RpcError>>,
+    },
+    Batch {
+        reqs: Vec<JsonRpcRequest>,
+        responder: oneshot::Sender<Value>,
+    },
+    Unsubscribe(u64),
+}
+
+struct PendingCall {
+    req: JsonRpcRequest,
+    responder: oneshot::Sender<Value>,
+}
+
+/// Batch items go out with connection-unique ids, responses have their id set back to the
+/// batch-local id before being handed to [batch::correlate].
+struct PendingBatch {
+    reqs: Vec<JsonRpcRequest>,
+    waiting: HashMap<u64, usize>,
+    responses: Vec<Value>,
+    responder: oneshot::Sender<Value>,
+}
+
+struct ActiveSubscription {
+    kind: SubscriptionKind,
+    sink: mpsc::UnboundedSender<Value>,
+    server_id: Option<String>,
+    // Only set until the first eth_subscribe succeeds, resubscribes after a reconnect are silent
+    responder: Option<oneshot::Sender<Result<u64, RpcError>>>,
+}
+
+/// State owned by the background connection task. Requests get unique ids here so responses can
+/// be matched up, and subscriptions are tracked by a local id that survives reconnects (the node
+/// hands out a new subscription id every time we resubscribe).
+#[derive(Default)]
+struct Connection {
+    next_id: u64,
+    next_local_id: u64,
+    calls: HashMap<u64, PendingCall>,
+    next_batch_id: u64,
+    batches: HashMap<u64, PendingBatch>,
+    batch_items: HashMap<u64, u64>,
+    subscribe_requests: HashMap<u64, u64>,
+    subscriptions: HashMap<u64, ActiveSubscription>,
+    server_ids: HashMap<String, u64>,
+}
+
+impl Connection {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    async fn send(socket: &mut dyn PubSubSocket, id: u64, req: &JsonRpcRequest) -> bool {
+        let mut value = serde_json::to_value(req).unwrap();
+        value["id"] = Value::from(id);
+        socket.send_text(value.to_string()).await
+    }
+
+    /// Forgets calls and batches whose caller timed out and went away, their answer may never
+    /// come.
+    fn sweep(&mut self) {
+        self.calls.retain(|_, call| !call.responder.is_closed());
+        self.batches.retain(|_, pending| !pending.responder.is_closed());
+        self.batch_items.retain(|_, batch_id| self.batches.contains_key(batch_id));
+    }
+
+    async fn handle_command(&mut self, socket: &mut dyn PubSubSocket, cmd: Command) -> bool {
+        self.sweep();
+        match cmd {
+            // Queued while we were reconnecting and the caller has given up since
+            Command::Call { responder, .. } | Command::Batch { responder, .. } if responder.is_closed() => true,
+            Command::Call { req, responder } => {
+                let id = self.next_id();
+                let ok = Self::send(socket, id, &req).await;
+                self.calls.insert(id, PendingCall { req, responder });
+                ok
+            }
+            Command::Subscribe { kind, sink, responder } => {
+                self.next_local_id += 1;
+                let local_id = self.next_local_id;
+                let id = self.next_id();
+                let ok = Self::send(socket, id, &JsonRpcApiRequestBuilder::subscribe(&kind)).await;
+                self.subscribe_requests.insert(id, local_id);
+                self.subscriptions.insert(
+                    local_id,
+                    ActiveSubscription {
+                        kind,
+                        sink,
+                        server_id: None,
+                        responder: Some(responder),
+                    },
+                );
+                ok
+            }
+            Command::Batch { reqs, responder } => {
+                self.next_batch_id += 1;
+                let batch_id = self.next_batch_id;
+                let waiting = (0..reqs.len()).collect();
+                self.batches.insert(
+                    batch_id,
+                    PendingBatch {
+                        reqs,
+                        waiting: HashMap::new(),
+                        responses: Vec::new(),
+                        responder,
+                    },
+                );
+                self.send_batch(socket, batch_id, waiting).await
+            }
+            Command::Unsubscribe(local_id) => self.unsubscribe(socket, local_id).await,
+        }
+    }
+
+    async fn send_batch(&mut self, socket: &mut dyn PubSubSocket, batch_id: u64, indexes: Vec<usize>) -> bool {
+        let mut wire = Vec::new();
+        for index in indexes {
+            let id = self.next_id();
+            if let Some(pending) = self.batches.get_mut(&batch_id) {
+                let mut value = serde_json::to_value(&pending.reqs[index]).unwrap();
+                value["id"] = Value::from(id);
+                wire.push(value);
+                pending.waiting.insert(id, index);
+                self.batch_items.insert(id, batch_id);
+            }
+        }
+        socket.send_text(Value::Array(wire).to_string()).await
+    }
+
+    async fn unsubscribe(&mut self, socket: &mut dyn PubSubSocket, local_id: u64) -> bool {
+        if let Some(sub) = self.subscriptions.remove(&local_id) {
+            if let Some(server_id) = sub.server_id {
+                self.server_ids.remove(&server_id);
+                let id = self.next_id();
+                return Self::send(socket, id, &JsonRpcApiRequestBuilder::unsubscribe(&server_id)).await;
+            }
+        }
+        true
+    }
+
+    async fn handle_message(&mut self, socket: &mut dyn PubSubSocket, message: Value) -> bool {
+        match message {
+            Value::Array(items) => {
+                for item in items {
+                    if !self.handle_value(socket, item).await {
+                        return false;
+                    }
+                }
+                true
+            }
+            value => self.handle_value(socket, value).await,
+        }
+    }
+
+    async fn handle_value(&mut self, socket: &mut dyn PubSubSocket, mut value: Value) -> bool {
+        if value["method"].as_str() == Some("eth_subscription") {
+            let server_id = value["params"]["subscription"].as_str().unwrap_or("");
+            if let Some(local_id) = self.server_ids.get(server_id).copied() {
+                let delivered = self
+                    .subscriptions
+                    .get(&local_id)
+                    .map(|sub| sub.sink.send(value["params"]["result"].clone()).is_ok())
+                    .unwrap_or(false);
+                if !delivered {
+                    return self.unsubscribe(socket, local_id).await;
+                }
+            }
+            return true;
+        }
+
+        let id = match value["id"].as_u64() {
+            Some(id) => id,
+            // An error without an id is the node rejecting a batch as a whole, which one can't be
+            // told so they all fail rather than wait for the timeout
+            None => {
+                if !value["error"].is_null() {
+                    self.batch_items.clear();
+                    for (_, pending) in self.batches.drain() {
+                        let _ = pending.responder.send(value.clone());
+                    }
+                }
+                return true;
+            }
+        };
+
+        if let Some(local_id) = self.subscribe_requests.remove(&id) {
+            match value["result"].as_str() {
+                Some(server_id) => {
+                    if let Some(sub) = self.subscriptions.get_mut(&local_id) {
+                        sub.server_id = Some(server_id.to_string());
+                        self.server_ids.insert(server_id.to_string(), local_id);
+                        if let Some(responder) = sub.responder.take() {
+                            let _ = responder.send(Ok(local_id));
+                        }
+                    }
+                }
+                None => {
+                    let err = RpcError::from_response(&value)
+                        .unwrap_or_else(|| RpcError::Decode("eth_subscribe did not return an id".to_string()));
+                    // A resubscribe after a reconnect has no caller waiting, dropping the sink
+                    // ends its stream so the holder can tell instead of waiting forever
+                    if let Some(sub) = self.subscriptions.remove(&local_id) {
+                        if let Some(responder) = sub.responder {
+                            let _ = responder.send(Err(err));
+                        }
+                    }
+                }
+            }
+        } else if let Some(call) = self.calls.remove(&id) {
+            // Caller may have timed out and gone away, nothing to do then
+            let _ = call.responder.send(value);
+        } else if let Some(batch_id) = self.batch_items.remove(&id) {
+            let done = match self.batches.get_mut(&batch_id) {
+                Some(pending) => {
+                    if let Some(index) = pending.waiting.remove(&id) {
+                        value["id"] = Value::from(pending.reqs[index].id.clone());
+                        pending.responses.push(value);
+                    }
+                    pending.waiting.is_empty()
+                }
+                None => false,
+            };
+            if done {
+                if let Some(pending) = self.batches.remove(&batch_id) {
+                    let _ = pending.responder.send(Value::Array(pending.responses));
+                }
+            }
+        }
+        true
+    }
+
+    /// Called on a fresh socket after a reconnect. Anything in flight is sent again, calls keep
+    /// their responder and subscriptions keep their local id and stream. Calls and batches whose
+    /// caller timed out and went away are dropped instead.
+    async fn restore(&mut self, socket: &mut dyn PubSubSocket) -> bool {
+        self.server_ids.clear();
+        self.subscribe_requests.clear();
+
+        let calls: Vec<PendingCall> = self
+            .calls
+            .drain()
+            .map(|(_, call)| call)
+            .filter(|call| !call.responder.is_closed())
+            .collect();
+        for call in calls {
+            let id = self.next_id();
+            if !Self::send(socket, id, &call.req).await {
+                return false;
+            }
+            self.calls.insert(id, call);
+        }
+
+        self.batch_items.clear();
+        self.batches.retain(|_, pending| !pending.responder.is_closed());
+        let mut batch_ids: Vec<u64> = self.batches.keys().copied().collect();
+        batch_ids.sort();
+        for batch_id in batch_ids {
+            let indexes = match self.batches.get_mut(&batch_id) {
+                Some(pending) => pending.waiting.drain().map(|(_, index)| index).collect(),
+                None => continue,
+            };
+            if !self.send_batch(socket, batch_id, indexes).await {
+                return false;
+            }
+        }
+
+        let mut local_ids: Vec<u64> = self.subscriptions.keys().copied().collect();
+        local_ids.sort();
+        for local_id in local_ids {
+            let req = match self.subscriptions.get_mut(&local_id) {
+                Some(sub) => {
+                    sub.server_id = None;
+                    JsonRpcApiRequestBuilder::subscribe(&sub.kind)
+                }
+                None => continue,
+            };
+            let id = self.next_id();
+            if !Self::send(socket, id, &req).await {
+                return false;
+            }
+            self.subscribe_requests.insert(id, local_id);
+        }
+        true
+    }
+}
+
+async fn run(
+    connector: Box<dyn PubSubConnector>,
+    retry: RetryPolicy,
+    mut socket: Box<dyn PubSubSocket>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut conn = Connection::default();
+    loop {
+        let mut healthy = true;
+        while healthy {
+            tokio::select! {
+                cmd = commands.recv() => match cmd {
+                    Some(cmd) => healthy = conn.handle_command(socket.as_mut(), cmd).await,
+                    // Every handle has been dropped
+                    None => return,
+                },
+                msg = socket.next_message() => match msg {
+                    Some(message) => healthy = conn.handle_message(socket.as_mut(), message).await,
+                    None => healthy = false,
+                },
+            }
+        }
+
+        // Reconnect forever, subscriptions would otherwise go silent. Calls are bounded by the
+        // timeout on the caller side.
+        let mut attempt = 0;
+        loop {
+            tokio::time::sleep(retry.backoff(attempt)).await;
+            if let Ok(mut new_socket) = connector.connect().await {
+                if conn.restore(new_socket.as_mut()).await {
+                    socket = new_socket;
+                    break;
+                }
+            }
+            attempt = attempt.saturating_add(1);
+        }
+    }
+}
+
+/// Stream of `eth_subscription` results for one subscription. The item is the `result` field of
+/// each notification (a header for `newHeads`, a log for `logs`, a hash for
+/// `newPendingTransactions`).
+///
+/// The stream keeps going across reconnects, and ends if the node refuses the resubscribe after
+/// one. Dropping it unsubscribes.
+pub struct SubscriptionStream {
+    local_id: u64,
+    items: mpsc::UnboundedReceiver<Value>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Stream for SubscriptionStream {
+    type Item = Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.items.poll_recv(cx)
+    }
+}
+
+impl Drop for SubscriptionStream {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Unsubscribe(self.local_id));
+    }
+}
+
+/// `eth_subscribe` support, only available over a long lived connection ([super::WsJsonRpc],
+/// [super::IpcJsonRpc]).
+#[async_trait::async_trait]
+pub trait EthPubSub {
+    async fn subscribe(&self, kind: SubscriptionKind) -> Result<SubscriptionStream, RpcError>;
+
+    async fn subscribe_new_heads(&self) -> Result<SubscriptionStream, RpcError> {
+        self.subscribe(SubscriptionKind::NewHeads).await
+    }
+
+    async fn subscribe_logs(&self, filter: LogFilter) -> Result<SubscriptionStream, RpcError> {
+        self.subscribe(SubscriptionKind::Logs(filter)).await
+    }
+
+    async fn subscribe_pending_transactions(&self) -> Result<SubscriptionStream, RpcError> {
+        self.subscribe(SubscriptionKind::NewPendingTransactions).await
+    }
+}
+
+/// Handle to the background connection task behind the websocket and IPC transports.
+///
+/// The socket is owned by the task, this handle is cheap to share. If the connection drops the
+/// task reconnects using [RetryPolicy] backoff, resends in-flight requests and resubscribes every
+/// live [SubscriptionStream].
+pub(super) struct PubSubClient {
+    timeout: Duration,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl PubSubClient {
+    pub async fn connect(
+        connector: Box<dyn PubSubConnector>,
+        retry: RetryPolicy,
+        timeout: Duration,
+    ) -> Result<Self, ProviderConfigError> {
+        let socket = connector.connect().await?;
+        let (commands, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(connector, retry, socket, rx));
+        Ok(Self { timeout, commands })
+    }
+
+    fn stopped() -> RpcError {
+        RpcError::Transport("connection task has stopped".to_string())
+    }
+
+    pub async fn subscribe(&self, kind: SubscriptionKind) -> Result<SubscriptionStream, RpcError> {
+        let (sink, items) = mpsc::unbounded_channel();
+        let (responder, response) = oneshot::channel();
+        self.commands
+            .send(Command::Subscribe { kind, sink, responder })
+            .map_err(|_| Self::stopped())?;
+
+        let local_id = match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(Ok(local_id))) => local_id,
+            Ok(Ok(Err(err))) => return Err(err),
+            Ok(Err(_)) => return Err(Self::stopped()),
+            Err(_) => return Err(RpcError::Timeout),
+        };
+        Ok(SubscriptionStream {
+            local_id,
+            items,
+            commands: self.commands.clone(),
+        })
+    }
+
+    pub async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        let (responder, response) = oneshot::channel();
+        self.commands
+            .send(Command::Call {
+                req: req.clone(),
+                responder,
+            })
+            .map_err(|_| Self::stopped())?;
+
+        match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(value)) => check_response(value),
+            Ok(Err(_)) => Err(Self::stopped()),
+            Err(_) => Err(RpcError::Timeout),
+        }
+    }
+
+    pub async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        if batch.is_empty() {
+            return Ok(Vec::new());
+        }
+        let reqs = batch.into_requests();
+        let (responder, response) = oneshot::channel();
+        self.commands
+            .send(Command::Batch {
+                reqs: reqs.clone(),
+                responder,
+            })
+            .map_err(|_| Self::stopped())?;
+
+        match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(responses)) => Ok(batch::correlate(&reqs, responses)),
+            Ok(Err(_)) => Err(Self::stopped()),
+            Err(_) => Err(RpcError::Timeout),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{check_response, BatchResponse, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, RpcError};
+
+/// One request/response pair in a fixture file. Ids aren't kept, requests are matched on method
+/// and params.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    params: Value,
+    response: Value,
+}
+
+fn params(req: &JsonRpcRequest) -> Value {
+    serde_json::to_value(&req.params).unwrap_or(Value::Null)
+}
+
+/// Response body to store for a result. Errors the node returned are kept since replaying them
+/// is part of the point, transport errors and timeouts are not.
+fn response_body(req: &JsonRpcRequest, res: &Result<Value, RpcError>) -> Option<Value> {
+    match res {
+        Ok(value) => Some(value.clone()),
+        Err(RpcError::NullResult(_)) => Some(json!({"jsonrpc": "2.0", "id": req.id, "result": null})),
+        Err(RpcError::JsonRpc { code, message, data }) => {
+            let mut error = json!({"code": code, "message": message});
+            if let Some(data) = data {
+                error["data"] = data.clone();
+            }
+            Some(json!({"jsonrpc": "2.0", "id": req.id, "error": error}))
+        }
+        Err(_) => None,
+    }
+}
+
+/// [EthJsonRpc] decorator that passes everything through to a live provider and keeps every
+/// request/response pair, so they can be written to a fixture for [ReplayJsonRpc].
+///
+/// Batches are stored item by item, a replayed batch doesn't need to match the recorded one.
+/// The fixture is only written by [RecordingJsonRpc::save], a run that fails halfway doesn't
+/// replace a good one.
+pub struct RecordingJsonRpc<P> {
+    inner: P,
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl<P: EthJsonRpc + Send + Sync> RecordingJsonRpc<P> {
+    pub fn new(inner: P, path: impl AsRef<Path>) -> Self {
+        Self {
+            inner,
+            path: path.as_ref().to_path_buf(),
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let interactions = self.interactions.lock().unwrap();
+        let json = serde_json::to_string_pretty(&*interactions)?;
+        fs::write(&self.path, json)
+    }
+
+    fn record(&self, req: &JsonRpcRequest, res: &Result<Value, RpcError>) {
+        if let Some(response) = response_body(req, res) {
+            self.interactions.lock().unwrap().push(Interaction {
+                method: req.method.clone(),
+                params: params(req),
+                response,
+            });
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: EthJsonRpc + Send + Sync> EthJsonRpc for RecordingJsonRpc<P> {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        let res = self.inner.request(req).await;
+        self.record(req, &res);
+        res
+    }
+
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        let requests = batch.requests().to_vec();
+        let results = self.inner.batch(batch).await?;
+        for (req, res) in requests.iter().zip(&results) {
+            self.record(req, res);
+        }
+        Ok(results)
+    }
+}
+
+/// [EthJsonRpc] that answers from a fixture written by [RecordingJsonRpc], no network needed.
+///
+/// Meant for tests. A request that wasn't recorded panics with the method and params rather than
+/// returning an error, events swallow provider errors and the test would fail somewhere
+/// unhelpful. If the same request was recorded more than once the responses are replayed in
+/// order and the last one repeats.
+pub struct ReplayJsonRpc {
+    path: PathBuf,
+    recorded: Mutex<HashMap<String, (Vec<Value>, usize)>>,
+}
+
+impl ReplayJsonRpc {
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let interactions: Vec<Interaction> = serde_json::from_slice(&fs::read(&path)?)?;
+
+        let mut recorded: HashMap<String, (Vec<Value>, usize)> = HashMap::new();
+        for interaction in interactions {
+            let key = format!("{} {}", interaction.method, interaction.params);
+            recorded.entry(key).or_default().0.push(interaction.response);
+        }
+        Ok(Self {
+            path,
+            recorded: Mutex::new(recorded),
+        })
+    }
+
+    fn next(&self, req: &JsonRpcRequest) -> Value {
+        let key = format!("{} {}", req.method, params(req));
+        let mut recorded = self.recorded.lock().unwrap();
+        let Some((responses, cursor)) = recorded.get_mut(&key) else {
+            panic!(
+                "No recorded response for {} in {}, re-record the fixture",
+                key,
+                self.path.display()
+            );
+        };
+        let mut response = responses[(*cursor).min(responses.len() - 1)].clone();
+        *cursor += 1;
+        response["id"] = Value::from(req.id.clone());
+        response
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for ReplayJsonRpc {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        check_response(self.next(req))
+    }
+
+    /// Same per-item results as a live batch, see [JsonRpcBatch].
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        Ok(batch
+            .into_requests()
+            .iter()
+            .map(|req| {
+                let value = check_response(self.next(req))?;
+                if value["result"].is_null() {
+                    return Err(RpcError::NullResult(req.method.clone()));
+                }
+                Ok(value)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// How many times a request is retried after a transient failure (timeouts, 429, 5xx, JSON-RPC
+/// `-32005` limit exceeded) and how long we wait between attempts.
+///
+/// Backoff is exponential, capped at `max_backoff`. With jitter enabled the wait is drawn from
+/// the upper half of the backoff window so many runners don't hit the provider in lockstep.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: u32,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Wait before retry number `attempt`, starting at zero.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.max(1).saturating_pow(attempt);
+        let backoff = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        if self.jitter && !backoff.is_zero() {
+            let half = backoff / 2;
+            let extra = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+            half + Duration::from_millis(extra)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Token bucket limit on requests sent to a provider. `burst` requests can go out at once, after
+/// that we refill at `requests_per_second`.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn per_second(requests_per_second: f64) -> Self {
+        Self {
+            requests_per_second,
+            burst: requests_per_second.ceil().max(1.0) as u32,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucketState {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Shared by every request made through one provider so concurrent events/signals all count
+/// against the same limit.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    state: tokio::sync::Mutex<TokenBucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(limit: &RateLimit) -> Self {
+        let capacity = limit.burst.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_second: limit.requests_per_second,
+            state: tokio::sync::Mutex::new(TokenBucketState {
+                tokens: capacity,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let now = Instant::now();
+                let refill = now.duration_since(state.last).as_secs_f64() * self.refill_per_second;
+                state.tokens = (state.tokens + refill).min(self.capacity);
+                state.last = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / self.refill_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
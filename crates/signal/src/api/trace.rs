@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::RpcError;
+
+/// What a [CallFrame] did. Geth's callTracer and Parity-style traces spell these differently, both
+/// are accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    StaticCall,
+    DelegateCall,
+    CallCode,
+    Create,
+    Create2,
+    SelfDestruct,
+}
+
+impl CallKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind.to_ascii_uppercase().as_str() {
+            "CALL" => Some(CallKind::Call),
+            "STATICCALL" => Some(CallKind::StaticCall),
+            "DELEGATECALL" => Some(CallKind::DelegateCall),
+            "CALLCODE" => Some(CallKind::CallCode),
+            "CREATE" => Some(CallKind::Create),
+            "CREATE2" => Some(CallKind::Create2),
+            "SELFDESTRUCT" | "SUICIDE" => Some(CallKind::SelfDestruct),
+            _ => None,
+        }
+    }
+
+    pub fn is_create(&self) -> bool {
+        matches!(self, CallKind::Create | CallKind::Create2)
+    }
+}
+
+/// One frame of a call tree, the root is the transaction itself.
+///
+/// For creates `to` is the new contract (`None` if the create failed) and `input` is the init
+/// code. For self-destructs `from` is the destroyed contract, `to` the beneficiary and `value` the
+/// balance it was sent.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "GethFrame")]
+pub struct CallFrame {
+    pub kind: CallKind,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas: u64,
+    pub gas_used: u64,
+    pub input: Bytes,
+    pub output: Bytes,
+    /// Set if the frame reverted, everything below it was rolled back too.
+    pub error: Option<String>,
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// Every frame depth first, starting with this one. Includes reverted frames.
+    pub fn walk(&self) -> impl Iterator<Item = &CallFrame> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let frame = stack.pop()?;
+            stack.extend(frame.calls.iter().rev());
+            Some(frame)
+        })
+    }
+
+    /// Like [CallFrame::walk] but stops at reverted frames, so only things that actually happened
+    /// on chain.
+    pub fn walk_successful(&self) -> impl Iterator<Item = &CallFrame> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || loop {
+            let frame = stack.pop()?;
+            if frame.error.is_some() {
+                continue;
+            }
+            stack.extend(frame.calls.iter().rev());
+            return Some(frame);
+        })
+    }
+
+    /// Contracts created anywhere in the tree, factories and CREATE2 included.
+    pub fn creates(&self) -> impl Iterator<Item = &CallFrame> {
+        self.walk_successful().filter(|frame| frame.kind.is_create())
+    }
+
+    pub fn self_destructs(&self) -> impl Iterator<Item = &CallFrame> {
+        self.walk_successful().filter(|frame| frame.kind == CallKind::SelfDestruct)
+    }
+
+    /// ETH moved by internal calls, creates and self-destructs. The top level transfer is left out,
+    /// that one is already on the transaction.
+    pub fn internal_transfers(&self) -> impl Iterator<Item = &CallFrame> {
+        self.walk_successful().skip(1).filter(|frame| {
+            // Delegate and static calls can't move value, their `value` is the caller's
+            !frame.value.is_zero() && !matches!(frame.kind, CallKind::DelegateCall | CallKind::StaticCall)
+        })
+    }
+}
+
+/// callTracer output as geth sends it.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GethFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    from: Address,
+    to: Option<Address>,
+    value: Option<U256>,
+    gas: Option<U64>,
+    gas_used: Option<U64>,
+    input: Option<Bytes>,
+    output: Option<Bytes>,
+    error: Option<String>,
+    #[serde(default)]
+    calls: Vec<CallFrame>,
+}
+
+impl TryFrom<GethFrame> for CallFrame {
+    type Error = String;
+
+    fn try_from(frame: GethFrame) -> Result<Self, Self::Error> {
+        let kind = CallKind::parse(&frame.kind).ok_or_else(|| format!("unknown call type {}", frame.kind))?;
+        Ok(Self {
+            kind,
+            from: frame.from,
+            to: frame.to,
+            value: frame.value.unwrap_or_default(),
+            gas: frame.gas.unwrap_or_default().to::<u64>(),
+            gas_used: frame.gas_used.unwrap_or_default().to::<u64>(),
+            input: frame.input.unwrap_or_default(),
+            output: frame.output.unwrap_or_default(),
+            error: frame.error,
+            calls: frame.calls,
+        })
+    }
+}
+
+/// An account as the prestateTracer saw it before the transaction ran. Only what the transaction
+/// touched is included.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct AccountState {
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    pub code: Option<Bytes>,
+    #[serde(default)]
+    pub storage: HashMap<B256, B256>,
+}
+
+pub type PrestateTrace = HashMap<Address, AccountState>;
+
+/// Trace for one transaction in a block trace. Older geth versions don't send the hash, the
+/// position in the returned list is the transaction index either way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionTrace<T = CallFrame> {
+    pub transaction_hash: Option<B256>,
+    pub trace: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GethBlockItem {
+    tx_hash: Option<B256>,
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+/// `debug_traceBlockByNumber` result, one item per transaction. A transaction the node failed to
+/// trace fails the whole block rather than leaving a hole.
+pub(super) fn decode_geth_block<T: serde::de::DeserializeOwned>(
+    items: Vec<Value>,
+) -> Result<Vec<TransactionTrace<T>>, RpcError> {
+    items
+        .into_iter()
+        .map(|item| {
+            let item: GethBlockItem = serde_json::from_value(item)?;
+            let Some(result) = item.result else {
+                let error = item.error.unwrap_or_else(|| "missing result".to_string());
+                return Err(RpcError::Decode(format!("trace failed for {:?}: {}", item.tx_hash, error)));
+            };
+            Ok(TransactionTrace {
+                transaction_hash: item.tx_hash,
+                trace: serde_json::from_value(result)?,
+            })
+        })
+        .collect()
+}
+
+/// One entry of a Parity-style `trace_block`. The tree is flattened, `trace_address` is the path
+/// of child indexes from the transaction's root frame.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityTrace {
+    #[serde(rename = "type")]
+    kind: String,
+    action: ParityAction,
+    result: Option<ParityResult>,
+    error: Option<String>,
+    trace_address: Vec<usize>,
+    transaction_hash: Option<B256>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityAction {
+    call_type: Option<String>,
+    creation_method: Option<String>,
+    from: Option<Address>,
+    to: Option<Address>,
+    value: Option<U256>,
+    gas: Option<U64>,
+    input: Option<Bytes>,
+    init: Option<Bytes>,
+    // Self-destructs
+    address: Option<Address>,
+    refund_address: Option<Address>,
+    balance: Option<U256>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityResult {
+    gas_used: Option<U64>,
+    output: Option<Bytes>,
+    code: Option<Bytes>,
+    address: Option<Address>,
+}
+
+impl ParityTrace {
+    /// `None` for block and uncle rewards, they aren't part of any transaction.
+    fn into_frame(self) -> Result<Option<CallFrame>, RpcError> {
+        let ParityTrace { kind, action, result, error, .. } = self;
+        let kind = match kind.as_str() {
+            "reward" => return Ok(None),
+            "call" => action.call_type.as_deref().unwrap_or("call"),
+            "create" => action.creation_method.as_deref().unwrap_or("create"),
+            kind => kind,
+        };
+        let kind = CallKind::parse(kind).ok_or_else(|| RpcError::Decode(format!("unknown trace type {}", kind)))?;
+        let result = result.unwrap_or_default();
+
+        let frame = match kind {
+            CallKind::SelfDestruct => CallFrame {
+                kind,
+                from: action.address.unwrap_or_default(),
+                to: action.refund_address,
+                value: action.balance.unwrap_or_default(),
+                gas: 0,
+                gas_used: 0,
+                input: Bytes::new(),
+                output: Bytes::new(),
+                error,
+                calls: Vec::new(),
+            },
+            _ => CallFrame {
+                kind,
+                from: action.from.unwrap_or_default(),
+                to: if kind.is_create() { result.address } else { action.to },
+                value: action.value.unwrap_or_default(),
+                gas: action.gas.unwrap_or_default().to::<u64>(),
+                gas_used: result.gas_used.unwrap_or_default().to::<u64>(),
+                input: action.input.or(action.init).unwrap_or_default(),
+                output: result.output.or(result.code).unwrap_or_default(),
+                error,
+                calls: Vec::new(),
+            },
+        };
+        Ok(Some(frame))
+    }
+}
+
+/// Rebuilds the call trees from a flat `trace_block` result. Traces come depth first, so a
+/// frame's parent is always already in place when it arrives.
+pub(super) fn decode_parity_block(traces: Vec<Value>) -> Result<Vec<TransactionTrace>, RpcError> {
+    let mut transactions: Vec<TransactionTrace> = Vec::new();
+    for trace in traces {
+        let trace: ParityTrace = serde_json::from_value(trace)?;
+        let transaction_hash = trace.transaction_hash;
+        let path = trace.trace_address.clone();
+        let Some(frame) = trace.into_frame()? else {
+            continue;
+        };
+
+        let Some((last, parents)) = path.split_last() else {
+            transactions.push(TransactionTrace { transaction_hash, trace: frame });
+            continue;
+        };
+        let orphan = || RpcError::Decode(format!("trace {:?} has no parent", path));
+        let root = transactions
+            .last_mut()
+            .filter(|tx| tx.transaction_hash == transaction_hash)
+            .ok_or_else(orphan)?;
+        let mut parent = &mut root.trace;
+        for index in parents {
+            parent = parent.calls.get_mut(*index).ok_or_else(orphan)?;
+        }
+        if *last != parent.calls.len() {
+            return Err(orphan());
+        }
+        parent.calls.push(frame);
+    }
+    Ok(transactions)
+}
@@ -0,0 +1,111 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use super::{
+    pubsub::{PubSubClient, PubSubConnector, PubSubSocket},
+    BatchResponse, EthJsonRpc, EthPubSub, JsonRpcBatch, JsonRpcRequest, ProviderConfig, ProviderConfigError,
+    RpcError, SubscriptionKind, SubscriptionStream,
+};
+
+struct WsSocket(WebSocketStream<MaybeTlsStream<TcpStream>>);
+
+#[async_trait::async_trait]
+impl PubSubSocket for WsSocket {
+    async fn send_text(&mut self, text: String) -> bool {
+        self.0.send(Message::Text(text)).await.is_ok()
+    }
+
+    async fn next_message(&mut self) -> Option<Value> {
+        loop {
+            match self.0.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    // Anything that isn't JSON can't be matched to a request, skip it
+                    if let Ok(value) = serde_json::from_str(&text) {
+                        return Some(value);
+                    }
+                }
+                Some(Ok(Message::Ping(data))) => {
+                    if self.0.send(Message::Pong(data)).await.is_err() {
+                        return None;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return None,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+struct WsConnector(ProviderConfig);
+
+#[async_trait::async_trait]
+impl PubSubConnector for WsConnector {
+    async fn connect(&self) -> Result<Box<dyn PubSubSocket>, ProviderConfigError> {
+        let config = &self.0;
+        let endpoint = config.endpoint()?;
+        let mut request = endpoint
+            .as_str()
+            .into_client_request()
+            .map_err(|_| ProviderConfigError::InvalidUrl(config.url.clone()))?;
+        request.headers_mut().extend(config.headers()?);
+        let (socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| ProviderConfigError::Client(e.to_string()))?;
+        Ok(Box::new(WsSocket(socket)))
+    }
+}
+
+/// [EthJsonRpc] over a websocket. Same requests as [super::HttpJsonRpc] but also implements
+/// [EthPubSub] so the runner can react to new heads rather than polling.
+///
+/// Dropped connections are re-established with [ProviderConfig::retry] backoff, in-flight requests
+/// and live subscriptions carry on.
+pub struct WsJsonRpc {
+    config: ProviderConfig,
+    client: PubSubClient,
+}
+
+impl WsJsonRpc {
+    pub async fn connect(config: ProviderConfig) -> Result<Self, ProviderConfigError> {
+        if config.chain_id == 0 {
+            return Err(ProviderConfigError::InvalidChainId);
+        }
+        if !config.is_websocket() {
+            return Err(ProviderConfigError::InvalidUrl(config.url.clone()));
+        }
+        let connector = Box::new(WsConnector(config.clone()));
+        let client = PubSubClient::connect(connector, config.retry.clone(), config.timeout).await?;
+        Ok(Self { config, client })
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+}
+
+#[async_trait::async_trait]
+impl EthPubSub for WsJsonRpc {
+    async fn subscribe(&self, kind: SubscriptionKind) -> Result<SubscriptionStream, RpcError> {
+        self.client.subscribe(kind).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for WsJsonRpc {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        self.client.request(req).await
+    }
+
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        self.client.batch(batch).await
+    }
+}
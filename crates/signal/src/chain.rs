@@ -0,0 +1,284 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    path::PathBuf,
+    time::Duration,
+};
+
+use alloy_primitives::{address, U256};
+
+use crate::{
+    address::CanonicalAddress,
+    api::{ProviderConfig, ProviderConfigError},
+    follower::{self, ProcessingDepth},
+    units::parse_ether,
+};
+
+/// Addresses detectors look for. These differ per chain and most only exist on some chains, an
+/// empty list just means the detector never fires there.
+#[derive(Clone, Debug, Default)]
+pub struct ChainAddresses {
+    /// Tornado Cash pools, address to pool name.
+    pub tornado_pools: HashMap<CanonicalAddress, String>,
+    /// FixedFloat hot wallets.
+    pub fixed_float: HashSet<CanonicalAddress>,
+}
+
+impl ChainAddresses {
+    pub fn mainnet() -> Self {
+        let tornado_pools = [
+            (address!("12d66f87a04a9e220743712ce6d9bb1b5616b8fc"), "ETH/0.1"),
+            (address!("47ce0c6ed5b0ce3d3a51fdb1c52dc66a7c3c2936"), "ETH/1"),
+        ];
+        Self {
+            tornado_pools: tornado_pools
+                .into_iter()
+                .map(|(address, name)| (address.into(), name.to_string()))
+                .collect(),
+            fixed_float: HashSet::from([address!("4e5b2e1dc63f6b91cb6cd759936495434c7e972f").into()]),
+        }
+    }
+
+    pub fn tornado_pool_name(&self, address: impl Into<CanonicalAddress>) -> Option<&str> {
+        self.tornado_pools.get(&address.into()).map(String::as_str)
+    }
+
+    pub fn is_fixed_float(&self, address: impl Into<CanonicalAddress>) -> bool {
+        self.fixed_float.contains(&address.into())
+    }
+}
+
+/// Everything the runner needs to monitor one chain.
+///
+/// `window` is how many recent blocks the [follower::ChainFollower] keeps. It has to cover the
+/// finality lag in blocks for detections to be upgraded to finalized, which is much longer on
+/// chains with fast blocks.
+///
+/// `min_transfer_value` is in wei of the chain's native token, value based detectors ignore
+/// transfers below it. `detectors` are names in the [crate::registry::DetectorRegistry], `None`
+/// runs all of them. `state_path` is where the chain's [crate::state::StateStore] is kept
+/// between runs, `None` keeps it in memory.
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: String,
+    pub provider: ProviderConfig,
+    pub block_time: Duration,
+    pub depth: ProcessingDepth,
+    pub window: usize,
+    pub addresses: ChainAddresses,
+    pub min_transfer_value: U256,
+    pub detectors: Option<Vec<String>>,
+    pub state_path: Option<PathBuf>,
+}
+
+impl ChainConfig {
+    pub const MAINNET: u64 = 1;
+    pub const OPTIMISM: u64 = 10;
+    pub const BSC: u64 = 56;
+    pub const POLYGON: u64 = 137;
+    pub const BASE: u64 = 8453;
+    pub const ARBITRUM: u64 = 42161;
+
+    /// Chain with generic defaults and no addresses, see [ChainConfig::known] for the chains we
+    /// have defaults for.
+    pub fn new(chain_id: u64, name: &str, provider: ProviderConfig) -> Self {
+        Self {
+            chain_id,
+            name: name.to_string(),
+            provider: provider.with_chain_id(chain_id),
+            block_time: Duration::from_secs(12),
+            depth: ProcessingDepth::Latest,
+            window: follower::DEFAULT_WINDOW,
+            addresses: ChainAddresses::default(),
+            min_transfer_value: U256::ZERO,
+            detectors: None,
+            state_path: None,
+        }
+    }
+
+    /// Defaults for the chains we monitor, `None` for anything else.
+    ///
+    /// L2 blocks are only final once the batch that includes them is final on L1, ~15-20 minutes
+    /// behind, so their windows cover that at their block time.
+    pub fn known(chain_id: u64, provider: ProviderConfig) -> Option<Self> {
+        let (name, block_time, window) = match chain_id {
+            Self::MAINNET => ("mainnet", Duration::from_secs(12), 128),
+            Self::OPTIMISM => ("optimism", Duration::from_secs(2), 1024),
+            Self::BSC => ("bsc", Duration::from_secs(3), 128),
+            Self::POLYGON => ("polygon", Duration::from_secs(2), 512),
+            Self::BASE => ("base", Duration::from_secs(2), 1024),
+            Self::ARBITRUM => ("arbitrum", Duration::from_millis(250), 6000),
+            _ => return None,
+        };
+        let config = Self::new(chain_id, name, provider)
+            .with_block_time(block_time)
+            .with_window(window);
+        Some(match chain_id {
+            Self::MAINNET => config.with_addresses(ChainAddresses::mainnet()),
+            _ => config,
+        })
+    }
+
+    pub fn with_block_time(mut self, block_time: Duration) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: ProcessingDepth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_addresses(mut self, addresses: ChainAddresses) -> Self {
+        self.addresses = addresses;
+        self
+    }
+
+    pub fn with_min_transfer_value(mut self, min_transfer_value: U256) -> Self {
+        self.min_transfer_value = min_transfer_value;
+        self
+    }
+
+    pub fn with_detectors(mut self, detectors: Vec<String>) -> Self {
+        self.detectors = Some(detectors);
+        self
+    }
+
+    pub fn with_state_path(mut self, state_path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(state_path.into());
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum ChainConfigError {
+    MissingEnv(String),
+    InvalidEnv(String, String),
+    Provider(ProviderConfigError),
+}
+
+impl std::error::Error for ChainConfigError {}
+
+impl std::fmt::Display for ChainConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainConfigError::MissingEnv(var) => write!(f, "Missing environment variable {}", var),
+            ChainConfigError::InvalidEnv(var, value) => write!(f, "Invalid value for {}: {}", var, value),
+            ChainConfigError::Provider(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ProviderConfigError> for ChainConfigError {
+    fn from(err: ProviderConfigError) -> Self {
+        ChainConfigError::Provider(err)
+    }
+}
+
+/// The chains one runner process monitors, by chain id.
+#[derive(Clone, Debug, Default)]
+pub struct ChainRegistry {
+    chains: BTreeMap<u64, ChainConfig>,
+}
+
+impl ChainRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces any chain already registered with the same id.
+    pub fn with_chain(mut self, chain: ChainConfig) -> Self {
+        self.chains.insert(chain.chain_id, chain);
+        self
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<&ChainConfig> {
+        self.chains.get(&chain_id)
+    }
+
+    pub fn chains(&self) -> impl Iterator<Item = &ChainConfig> {
+        self.chains.values()
+    }
+
+    pub fn into_chains(self) -> impl Iterator<Item = ChainConfig> {
+        self.chains.into_values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    /// `$CHAINS` is a comma separated list of chain ids, each with its node in `$RPC_URL_<id>`.
+    /// Without it there is a single chain from [ProviderConfig::from_env]. `$PROCESSING_DEPTH`
+    /// applies to every chain, `$PROCESSING_DEPTH_<id>` to one, same for `$MIN_TRANSFER_ETH`
+    /// which is a decimal amount of the native token, and `$DETECTORS`, a comma separated list of
+    /// detector names. With `$STATE_DIR` each chain keeps its state in `state_<id>.json` there.
+    pub fn from_env() -> Result<Self, ChainConfigError> {
+        let providers = match env::var("CHAINS") {
+            Ok(chains) => {
+                let mut providers = Vec::new();
+                for id in split_list(&chains) {
+                    let chain_id = id
+                        .parse::<u64>()
+                        .map_err(|_| ChainConfigError::InvalidEnv("CHAINS".to_string(), chains.clone()))?;
+                    let var = format!("RPC_URL_{}", chain_id);
+                    let url = env::var(&var).map_err(|_| ChainConfigError::MissingEnv(var))?;
+                    providers.push((chain_id, ProviderConfig::new(&url).with_env_overrides()?));
+                }
+                providers
+            }
+            Err(_) => {
+                let provider = ProviderConfig::from_env()?;
+                vec![(provider.chain_id, provider)]
+            }
+        };
+
+        let mut registry = Self::new();
+        for (chain_id, provider) in providers {
+            let mut chain = Self::known_or_new(chain_id, provider);
+            for var in ["PROCESSING_DEPTH".to_string(), format!("PROCESSING_DEPTH_{}", chain_id)] {
+                if let Ok(depth) = env::var(&var) {
+                    let depth = depth.parse().map_err(|_| ChainConfigError::InvalidEnv(var, depth))?;
+                    chain = chain.with_depth(depth);
+                }
+            }
+            for var in ["MIN_TRANSFER_ETH".to_string(), format!("MIN_TRANSFER_ETH_{}", chain_id)] {
+                if let Ok(eth) = env::var(&var) {
+                    let wei = parse_ether(&eth).map_err(|_| ChainConfigError::InvalidEnv(var, eth))?;
+                    chain = chain.with_min_transfer_value(wei);
+                }
+            }
+            for var in ["DETECTORS".to_string(), format!("DETECTORS_{}", chain_id)] {
+                if let Ok(detectors) = env::var(&var) {
+                    chain = chain.with_detectors(split_list(&detectors));
+                }
+            }
+            if let Ok(dir) = env::var("STATE_DIR") {
+                chain = chain.with_state_path(PathBuf::from(dir).join(format!("state_{}.json", chain_id)));
+            }
+            registry = registry.with_chain(chain);
+        }
+        Ok(registry)
+    }
+
+    fn known_or_new(chain_id: u64, provider: ProviderConfig) -> ChainConfig {
+        match ChainConfig::known(chain_id, provider.clone()) {
+            Some(chain) => chain,
+            None => ChainConfig::new(chain_id, &format!("chain-{}", chain_id), provider),
+        }
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect()
+}
@@ -0,0 +1,230 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    model::Block,
+    sink::{Match, Sink},
+    types::DetectorMeta,
+};
+
+/// One step of a [Pattern], a match from any of `events`.
+///
+/// `keys` bind payload fields to names shared by every step, e.g. the funding recipient and the
+/// contract creator both bound to `account`. A step only matches when its fields agree with what
+/// earlier steps bound, a key seen for the first time is bound to whatever the payload has.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub events: Vec<u32>,
+    /// (key, payload field)
+    pub keys: Vec<(String, String)>,
+}
+
+impl Step {
+    pub fn new(events: &[u32]) -> Self {
+        Self {
+            events: events.to_vec(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Binds payload `field` to `key`.
+    pub fn with_key(mut self, key: &str, field: &str) -> Self {
+        self.keys.push((key.to_string(), field.to_string()));
+        self
+    }
+
+    /// Bindings after this step, `None` if the match doesn't fit.
+    fn bind(&self, bindings: &BTreeMap<String, Value>, id: u32, payload: &Value) -> Option<BTreeMap<String, Value>> {
+        if !self.events.contains(&id) {
+            return None;
+        }
+        let mut bindings = bindings.clone();
+        for (key, field) in &self.keys {
+            let value = payload.get(field).filter(|value| !value.is_null())?;
+            match bindings.get(key) {
+                Some(bound) if bound != value => return None,
+                Some(_) => {}
+                None => {
+                    bindings.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Some(bindings)
+    }
+}
+
+/// An event a [PartialMatch] has gone through.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MatchedStep {
+    pub id: u32,
+    pub block: u64,
+    pub block_hash: B256,
+    pub timestamp: u64,
+    pub payload: Value,
+}
+
+/// A sequence some of the way through a [Pattern], the next step is `steps.len()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PartialMatch {
+    pub bindings: BTreeMap<String, Value>,
+    pub steps: Vec<MatchedStep>,
+}
+
+impl PartialMatch {
+    fn first(&self) -> Option<&MatchedStep> {
+        self.steps.first()
+    }
+
+    /// Block that took it to where it is, a reorg of that block takes it back out.
+    pub fn block_hash(&self) -> Option<B256> {
+        self.steps.last().map(|step| step.block_hash)
+    }
+
+    /// Same step with the same bindings, one stands in for the other.
+    fn same_place(&self, other: &PartialMatch) -> bool {
+        self.steps.len() == other.steps.len() && self.bindings == other.bindings
+    }
+}
+
+/// Drops partials replaced by the ones the block advanced, now that it can't be reorged out and
+/// take its own with it. True if any were dropped.
+pub fn prune_replaced(partials: &mut Vec<PartialMatch>, block_hash: B256) -> bool {
+    let replacing: Vec<PartialMatch> =
+        partials.iter().filter(|partial| partial.block_hash() == Some(block_hash)).cloned().collect();
+    let before = partials.len();
+    partials.retain(|partial| {
+        partial.block_hash() == Some(block_hash)
+            || !replacing.iter().any(|newer| {
+                let block = |p: &PartialMatch| p.steps.last().map_or(0, |step| step.block);
+                newer.same_place(partial) && block(partial) <= block(newer)
+            })
+    });
+    partials.len() != before
+}
+
+/// A signal declared as events in order, joined on [Step] keys. The signal fires with the
+/// pattern's id every time the last step matches.
+///
+/// Partial matches stay after they advance, an account funded once can create several contracts
+/// and a contract can be called many times. They expire once the block or time since their first
+/// step is over `block_window` / `time_window`, `None` never expires. A partial with the same
+/// bindings at the same step replaces the older one once its block is final (see
+/// [prune_replaced]), so the window runs from the latest start. Until then both are kept, a
+/// reorg would otherwise lose the older one, and only the latest start fires.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub meta: DetectorMeta,
+    pub steps: Vec<Step>,
+    pub block_window: Option<u64>,
+    pub time_window: Option<Duration>,
+}
+
+impl Pattern {
+    pub fn new(meta: DetectorMeta) -> Self {
+        Self {
+            meta,
+            steps: Vec::new(),
+            block_window: None,
+            time_window: None,
+        }
+    }
+
+    pub fn then(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn within_blocks(mut self, blocks: u64) -> Self {
+        self.block_window = Some(blocks);
+        self
+    }
+
+    pub fn within(mut self, time: Duration) -> Self {
+        self.time_window = Some(time);
+        self
+    }
+
+    fn expired(&self, partial: &PartialMatch, block: &Block) -> bool {
+        let Some(first) = partial.first() else {
+            return true;
+        };
+        let blocks = block.number().saturating_sub(first.block);
+        let secs = block.timestamp().saturating_sub(first.timestamp);
+        self.block_window.is_some_and(|window| blocks > window)
+            || self.time_window.is_some_and(|window| secs > window.as_secs())
+    }
+
+    /// Advances `partials` with the block's event matches, in the order they were found, and
+    /// pushes a match for every sequence completed. True if `partials` changed.
+    pub fn process(
+        &self,
+        partials: &mut Vec<PartialMatch>,
+        block: &Block,
+        matches: &[Match],
+        sink: &mut (dyn Sink + Send),
+    ) -> bool {
+        let before = partials.len();
+        partials.retain(|partial| !self.expired(partial, block));
+        let mut changed = partials.len() != before;
+        if self.steps.is_empty() {
+            return changed;
+        }
+
+        let start = PartialMatch::default();
+        for (id, payload) in matches {
+            let mut advanced: Vec<PartialMatch> = Vec::new();
+            for partial in partials.iter().chain(std::iter::once(&start)) {
+                let step = &self.steps[partial.steps.len()];
+                let Some(bindings) = step.bind(&partial.bindings, *id, payload) else {
+                    continue;
+                };
+                let mut steps = partial.steps.clone();
+                steps.push(MatchedStep {
+                    id: *id,
+                    block: block.number(),
+                    block_hash: block.hash(),
+                    timestamp: block.timestamp(),
+                    payload: payload.clone(),
+                });
+                let partial = PartialMatch { bindings, steps };
+                // A replaced partial and its replacement both advancing is one sequence
+                match advanced.iter_mut().find(|other| other.same_place(&partial)) {
+                    Some(other) if other.first().map(|s| s.block) <= partial.first().map(|s| s.block) => *other = partial,
+                    Some(_) => {}
+                    None => advanced.push(partial),
+                }
+            }
+
+            for partial in advanced {
+                if partial.steps.len() == self.steps.len() {
+                    sink.push(self.meta.id, self.output(&partial));
+                } else {
+                    // Within the block there's no reorg to keep the older one for
+                    partials.retain(|p| !p.same_place(&partial) || p.block_hash() != partial.block_hash());
+                    partials.push(partial);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Last event's payload with the bindings added and `sequence`, what happened at each step.
+    fn output(&self, partial: &PartialMatch) -> Value {
+        let last = partial.steps.last().map(|step| &step.payload);
+        let mut output = last.and_then(Value::as_object).cloned().unwrap_or_default();
+        for (key, value) in &partial.bindings {
+            output.insert(key.clone(), value.clone());
+        }
+        let sequence: Vec<Value> = partial
+            .steps
+            .iter()
+            .map(|step| json!({"id": step.id, "block": step.block, "transaction_hash": step.payload["transaction_hash"]}))
+            .collect();
+        output.insert("sequence".to_string(), Value::Array(sequence));
+        Value::Object(output)
+    }
+}
@@ -0,0 +1,326 @@
+//! Expression language for detector conditions, e.g.
+//! `get_balance(tx.to) > 100 ether && !is_contract(tx.from)`.
+//!
+//! Expressions are parsed and type checked once by [Expr::parse] and evaluated per transaction or
+//! block against a [Scope]. The chain-access builtins (`get_balance`, `is_contract`,
+//! `resolve_name`, `read_slot`, the `static_call` family and `get_contract_events`) go through
+//! [EthJsonRpc] at the scope's block, ethabi params and results use the syntax in [abi]. See
+//! docs/expressions.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{hex, Address, Bytes, U256};
+
+use crate::{
+    api::{BlockTag, EthJsonRpc, RpcError},
+    model::{Block, Transaction},
+    types::BlockContext,
+};
+
+pub mod abi;
+pub mod builtins;
+mod check;
+mod eval;
+mod parser;
+
+/// Static type of an expression. `Abi` is an ethabi value whose type is only known once a call
+/// returns, it is accepted anywhere and checked when evaluated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int,
+    Address,
+    Bytes,
+    String,
+    Abi,
+    List(Box<Type>),
+    /// Decoded event, parameter names to ethabi values
+    Map,
+}
+
+impl Type {
+    /// Expression type values of a solidity type evaluate to, see [Value::from_abi].
+    pub fn from_sol(ty: &DynSolType) -> Self {
+        match ty {
+            DynSolType::Bool => Type::Bool,
+            DynSolType::Int(_) | DynSolType::Uint(_) => Type::Int,
+            DynSolType::Address => Type::Address,
+            DynSolType::Function | DynSolType::FixedBytes(_) | DynSolType::Bytes => Type::Bytes,
+            DynSolType::String => Type::String,
+            DynSolType::Array(inner) | DynSolType::FixedArray(inner, _) => Type::List(Box::new(Type::from_sol(inner))),
+            DynSolType::Tuple(_) => Type::List(Box::new(Type::Abi)),
+        }
+    }
+
+    /// Either side can stand in for the other.
+    fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Abi, _) | (_, Type::Abi) => true,
+            (Type::List(a), Type::List(b)) => a.accepts(b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Int => write!(f, "int"),
+            Type::Address => write!(f, "address"),
+            Type::Bytes => write!(f, "bytes"),
+            Type::String => write!(f, "string"),
+            Type::Abi => write!(f, "ethabi"),
+            Type::List(inner) => write!(f, "list<{}>", inner),
+            Type::Map => write!(f, "map"),
+        }
+    }
+}
+
+/// An evaluated expression. Ints are unsigned 256 bit, like wei amounts and most of what
+/// contracts return.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Int(U256),
+    Address(Address),
+    Bytes(Bytes),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Tuples and arrays become lists, fixed bytes become bytes. Negative ints have no value
+    /// here, the `*_repr` builtins still show them.
+    pub fn from_abi(value: DynSolValue) -> Result<Self, String> {
+        Ok(match value {
+            DynSolValue::Bool(b) => Value::Bool(b),
+            DynSolValue::Uint(u, _) => Value::Int(u),
+            DynSolValue::Int(i, _) if i.is_negative() => return Err(format!("negative int {} isn't supported", i)),
+            DynSolValue::Int(i, _) => Value::Int(i.into_raw()),
+            DynSolValue::Address(address) => Value::Address(address),
+            DynSolValue::Function(function) => Value::Bytes(Bytes::copy_from_slice(function.as_slice())),
+            DynSolValue::FixedBytes(word, size) => Value::Bytes(Bytes::copy_from_slice(&word[..size])),
+            DynSolValue::Bytes(bytes) => Value::Bytes(Bytes::from(bytes)),
+            DynSolValue::String(s) => Value::String(s),
+            DynSolValue::Array(values) | DynSolValue::FixedArray(values) | DynSolValue::Tuple(values) => {
+                Value::List(values.into_iter().map(Value::from_abi).collect::<Result<_, _>>()?)
+            }
+        })
+    }
+
+    pub fn ty(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Address(_) => Type::Address,
+            Value::Bytes(_) => Type::Bytes,
+            Value::String(_) => Type::String,
+            Value::List(values) => {
+                let mut types = values.iter().map(Value::ty);
+                let first = types.next().unwrap_or(Type::Abi);
+                Type::List(Box::new(if types.all(|ty| ty == first) { first } else { Type::Abi }))
+            }
+            Value::Map(_) => Type::Map,
+        }
+    }
+}
+
+/// Same syntax as expression literals, so a value can be pasted back into an expression.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Address(address) => write!(f, "{}", hex::encode_prefixed(address)),
+            Value::Bytes(bytes) => write!(f, "hex\"{}\"", hex::encode(bytes)),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// What an expression is evaluated against. State is read at `block` when there is one, `latest`
+/// otherwise, and `tx.*` / `block.*` are only bound when the transaction / block are set.
+#[derive(Clone, Copy)]
+pub struct Scope<'a> {
+    pub chain_id: u64,
+    pub api: &'a (dyn EthJsonRpc + Sync),
+    pub block: Option<&'a Block>,
+    pub transaction: Option<&'a Transaction>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(chain_id: u64, api: &'a (dyn EthJsonRpc + Sync)) -> Self {
+        Self {
+            chain_id,
+            api,
+            block: None,
+            transaction: None,
+        }
+    }
+
+    /// The block a detector is running on.
+    pub fn from_context(ctx: &BlockContext<'a>) -> Self {
+        Self::new(ctx.chain_id, ctx.api).with_block(ctx.block)
+    }
+
+    pub fn with_block(mut self, block: &'a Block) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn with_transaction(mut self, transaction: &'a Transaction) -> Self {
+        self.transaction = Some(transaction);
+        self
+    }
+
+    fn at(&self) -> BlockTag {
+        self.block.map_or(BlockTag::Latest, |block| BlockTag::Number(block.number()))
+    }
+}
+
+/// A parsed and type checked expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expr {
+    source: String,
+    node: parser::Node,
+    ty: Type,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let node = parser::parse(source)?;
+        let ty = check::check(&node)?;
+        Ok(Self {
+            source: source.to_string(),
+            node,
+            ty,
+        })
+    }
+
+    /// An expression that has to be a `bool`, what detectors use.
+    pub fn condition(source: &str) -> Result<Self, ExprError> {
+        let expr = Self::parse(source)?;
+        if !Type::Bool.accepts(&expr.ty) {
+            return Err(ExprError::Type {
+                pos: 0,
+                message: format!("condition is {}, not bool", expr.ty),
+            });
+        }
+        Ok(expr)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    pub async fn eval(&self, scope: &Scope<'_>) -> Result<Value, ExprError> {
+        eval::eval(&self.node, scope).await
+    }
+
+    /// Evaluates a condition, anything other than a bool is an error.
+    pub async fn test(&self, scope: &Scope<'_>) -> Result<bool, ExprError> {
+        match self.eval(scope).await? {
+            Value::Bool(b) => Ok(b),
+            value => Err(ExprError::Eval {
+                pos: 0,
+                message: format!("expected a bool, got {}", value.ty()),
+            }),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// `pos` is the byte offset in the expression.
+#[derive(Debug)]
+pub enum ExprError {
+    Syntax { pos: usize, message: String },
+    Type { pos: usize, message: String },
+    /// Failed at runtime, e.g. an ethabi value of the wrong type or division by zero
+    Eval { pos: usize, message: String },
+    Rpc { pos: usize, err: RpcError },
+}
+
+impl ExprError {
+    fn syntax(pos: usize, message: impl Into<String>) -> Self {
+        ExprError::Syntax {
+            pos,
+            message: message.into(),
+        }
+    }
+
+    fn ty(pos: usize, message: impl Into<String>) -> Self {
+        ExprError::Type {
+            pos,
+            message: message.into(),
+        }
+    }
+
+    fn eval(pos: usize, message: impl Into<String>) -> Self {
+        ExprError::Eval {
+            pos,
+            message: message.into(),
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        match self {
+            ExprError::Syntax { pos, .. }
+            | ExprError::Type { pos, .. }
+            | ExprError::Eval { pos, .. }
+            | ExprError::Rpc { pos, .. } => *pos,
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Syntax { pos, message } => write!(f, "Syntax error at {}: {}", pos, message),
+            ExprError::Type { pos, message } => write!(f, "Type error at {}: {}", pos, message),
+            ExprError::Eval { pos, message } => write!(f, "Error at {}: {}", pos, message),
+            ExprError::Rpc { pos, err } => write!(f, "RPC error at {}: {}", pos, err),
+        }
+    }
+}
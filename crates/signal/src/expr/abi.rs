@@ -0,0 +1,281 @@
+//! ethabi literal syntax, the format `static_call` params and the `*_repr` builtins use.
+//!
+//! Types are solidity types, comma separated when there's a list, e.g. `address,uint256[]`.
+//! Values are written against a type: decimal or `0x` hex numbers (decimals allowed with a `wei`,
+//! `gwei` or `ether` suffix), `true`/`false`, hex with or without `0x` for addresses and bytes,
+//! `[..]` arrays and `(..)` tuples. Strings are bare at the top level and quoted inside arrays and
+//! tuples, e.g. `["a","b,c"]`.
+
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{hex, Address, B256, I256, U256};
+
+use crate::units::parse_units;
+
+/// Comma separated types, empty for none.
+pub fn parse_types(types: &str) -> Result<Vec<DynSolType>, String> {
+    if types.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match DynSolType::parse(&format!("({})", types.trim())) {
+        Ok(DynSolType::Tuple(types)) => Ok(types),
+        _ => Err(format!("invalid types `{}`", types)),
+    }
+}
+
+pub fn parse_value(ty: &DynSolType, value: &str) -> Result<DynSolValue, String> {
+    let mut parser = LiteralParser { input: value, pos: 0 };
+    let value = parser.value(ty, false)?;
+    parser.end()?;
+    Ok(value)
+}
+
+/// Comma separated values for `types`, same as the tuple of them without the parens.
+pub fn parse_values(types: &[DynSolType], values: &str) -> Result<Vec<DynSolValue>, String> {
+    if types.is_empty() {
+        return match values.trim() {
+            "" | "()" => Ok(Vec::new()),
+            values => Err(format!("no types for `{}`", values)),
+        };
+    }
+    // A single top level string keeps its commas
+    if let [DynSolType::String] = types {
+        return parse_value(&types[0], values).map(|value| vec![value]);
+    }
+    let mut parser = LiteralParser { input: values, pos: 0 };
+    let values = parser.sequence(types, None)?;
+    parser.end()?;
+    Ok(values)
+}
+
+/// Inverse of [parse_value].
+pub fn format_value(value: &DynSolValue) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, false);
+    out
+}
+
+fn write_value(out: &mut String, value: &DynSolValue, nested: bool) {
+    match value {
+        DynSolValue::Address(address) => out.push_str(&hex::encode_prefixed(address)),
+        DynSolValue::Function(function) => out.push_str(&hex::encode_prefixed(function)),
+        DynSolValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        DynSolValue::Int(i, _) => out.push_str(&i.to_string()),
+        DynSolValue::Uint(u, _) => out.push_str(&u.to_string()),
+        DynSolValue::FixedBytes(word, size) => out.push_str(&hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Bytes(bytes) => out.push_str(&hex::encode_prefixed(bytes)),
+        DynSolValue::String(s) if nested => {
+            out.push('"');
+            for c in s.chars() {
+                if c == '"' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+        }
+        DynSolValue::String(s) => out.push_str(s),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => write_list(out, values, '[', ']'),
+        DynSolValue::Tuple(values) => write_list(out, values, '(', ')'),
+    }
+}
+
+fn write_list(out: &mut String, values: &[DynSolValue], open: char, close: char) {
+    out.push(open);
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_value(out, value, true);
+    }
+    out.push(close);
+}
+
+struct LiteralParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> LiteralParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(format!("expected `{}` at {} in `{}`", c, self.pos, self.input));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(format!("unexpected `{}` in `{}`", self.rest(), self.input)),
+        }
+    }
+
+    /// Values for `types` separated by commas, up to `close` if there is one.
+    fn sequence(&mut self, types: &[DynSolType], close: Option<char>) -> Result<Vec<DynSolValue>, String> {
+        let mut values = Vec::with_capacity(types.len());
+        for (i, ty) in types.iter().enumerate() {
+            if i > 0 {
+                self.expect(',')?;
+            }
+            values.push(self.value(ty, true)?);
+        }
+        if let Some(close) = close {
+            self.expect(close)?;
+        }
+        Ok(values)
+    }
+
+    fn array(&mut self, ty: &DynSolType) -> Result<Vec<DynSolValue>, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(values);
+        }
+        loop {
+            values.push(self.value(ty, true)?);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                _ => break,
+            }
+        }
+        self.expect(']')?;
+        Ok(values)
+    }
+
+    fn value(&mut self, ty: &DynSolType, nested: bool) -> Result<DynSolValue, String> {
+        match ty {
+            DynSolType::Array(inner) => Ok(DynSolValue::Array(self.array(inner)?)),
+            DynSolType::FixedArray(inner, len) => {
+                let values = self.array(inner)?;
+                if values.len() != *len {
+                    return Err(format!("expected {} values for {}, got {}", len, ty, values.len()));
+                }
+                Ok(DynSolValue::FixedArray(values))
+            }
+            DynSolType::Tuple(types) => {
+                self.expect('(')?;
+                Ok(DynSolValue::Tuple(self.sequence(types, Some(')'))?))
+            }
+            DynSolType::String if self.peek() == Some('"') => self.quoted().map(DynSolValue::String),
+            DynSolType::String if !nested => {
+                let value = self.rest().trim().to_string();
+                self.pos = self.input.len();
+                Ok(DynSolValue::String(value))
+            }
+            ty => {
+                self.skip_whitespace();
+                let len = self.rest().find([',', ')', ']']).unwrap_or(self.rest().len());
+                let atom = self.rest()[..len].trim();
+                self.pos += len;
+                scalar(ty, atom)
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err(format!("unterminated string in `{}`", self.input))
+    }
+}
+
+fn scalar(ty: &DynSolType, atom: &str) -> Result<DynSolValue, String> {
+    let invalid = || format!("invalid {} `{}`", ty, atom);
+    match ty {
+        DynSolType::Bool => match atom {
+            "true" => Ok(DynSolValue::Bool(true)),
+            "false" => Ok(DynSolValue::Bool(false)),
+            _ => Err(invalid()),
+        },
+        DynSolType::Address => {
+            let bytes = hex::decode(atom).map_err(|_| invalid())?;
+            if bytes.len() != 20 {
+                return Err(invalid());
+            }
+            Ok(DynSolValue::Address(Address::from_slice(&bytes)))
+        }
+        DynSolType::Uint(bits) => {
+            let value = parse_uint(atom).ok_or_else(invalid)?;
+            if value.bit_len() > *bits {
+                return Err(format!("{} doesn't fit {}", atom, ty));
+            }
+            Ok(DynSolValue::Uint(value, *bits))
+        }
+        DynSolType::Int(bits) => {
+            let (negative, magnitude) = match atom.strip_prefix('-') {
+                Some(magnitude) => (true, magnitude.trim_start()),
+                None => (false, atom),
+            };
+            let magnitude = parse_uint(magnitude).ok_or_else(invalid)?;
+            // -2^(bits-1) ..= 2^(bits-1) - 1
+            let limit = U256::from(1) << (bits - 1);
+            if (negative && magnitude > limit) || (!negative && magnitude >= limit) {
+                return Err(format!("{} doesn't fit {}", atom, ty));
+            }
+            let raw = if negative { magnitude.wrapping_neg() } else { magnitude };
+            Ok(DynSolValue::Int(I256::from_raw(raw), *bits))
+        }
+        DynSolType::FixedBytes(size) => {
+            let bytes = hex::decode(atom).map_err(|_| invalid())?;
+            if bytes.len() != *size {
+                return Err(invalid());
+            }
+            let mut word = B256::ZERO;
+            word[..*size].copy_from_slice(&bytes);
+            Ok(DynSolValue::FixedBytes(word, *size))
+        }
+        DynSolType::Bytes => hex::decode(atom).map(DynSolValue::Bytes).map_err(|_| invalid()),
+        DynSolType::String => Ok(DynSolValue::String(atom.to_string())),
+        _ => Err(format!("{} values aren't supported", ty)),
+    }
+}
+
+/// Decimal, `0x` hex, or a decimal with a unit e.g. `1.5 ether`.
+pub(crate) fn parse_uint(atom: &str) -> Option<U256> {
+    if let Some(hex) = atom.strip_prefix("0x") {
+        return U256::from_str_radix(hex, 16).ok();
+    }
+    let split = atom.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(atom.len());
+    let (amount, unit) = atom.split_at(split);
+    parse_units(amount, unit_decimals(unit.trim())?).ok()
+}
+
+/// Decimals for a unit suffix, none is wei.
+pub(crate) fn unit_decimals(unit: &str) -> Option<usize> {
+    match unit {
+        "" | "wei" => Some(0),
+        "gwei" => Some(9),
+        "ether" => Some(18),
+        _ => None,
+    }
+}
@@ -0,0 +1,209 @@
+use alloy_dyn_abi::{DynSolType, DynSolValue, EventExt};
+use alloy_json_abi::{Event, Param};
+use alloy_primitives::{address, hex, keccak256, Address, Bytes, Selector, B256};
+
+use super::{abi, Type};
+use crate::model::Log;
+
+/// ENS registry, same address on mainnet and the testnets.
+pub const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+/// `resolver(bytes32)` on the registry
+pub(crate) const RESOLVER_SELECTOR: Selector = Selector::new(hex!("0178b8bf"));
+/// `addr(bytes32)` on a resolver
+pub(crate) const ADDR_SELECTOR: Selector = Selector::new(hex!("3b3b57de"));
+
+/// Argument and return types, `None` for names that aren't builtins. `len` is checked on its own
+/// as it takes several types.
+pub(crate) fn signature(name: &str) -> Option<(Vec<Type>, Type)> {
+    let call = || vec![Type::Address, Type::String, Type::String, Type::String];
+    Some(match name {
+        "get_balance" => (vec![Type::Address], Type::Int),
+        "is_contract" => (vec![Type::Address], Type::Bool),
+        "resolve_name" => (vec![Type::String], Type::Address),
+        "read_slot" => (vec![Type::Address, Type::Int], Type::Int),
+        "static_call" | "static_call_hash" => (call(), Type::Abi),
+        "static_call_repr" | "static_call_hash_repr" => (call(), Type::String),
+        "get_contract_events" => (
+            vec![Type::Address, Type::String, Type::String],
+            Type::List(Box::new(Type::Map)),
+        ),
+        _ => return None,
+    })
+}
+
+/// EIP-137 namehash. Labels are only lowercased, not UTS-46 normalized.
+pub fn namehash(name: &str) -> B256 {
+    let mut node = B256::ZERO;
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.to_lowercase().rsplit('.') {
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(node.as_slice());
+        preimage[32..].copy_from_slice(keccak256(label.as_bytes()).as_slice());
+        node = keccak256(preimage);
+    }
+    node
+}
+
+/// A `static_call` prepared from its string arguments.
+///
+/// The function is a name (`static_call`) or 4 byte hex selector (`static_call_hash`), optionally
+/// followed by `-> <types>` to decode the result, e.g. `"balanceOf -> uint256"`. Without return
+/// types the result is the raw bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticCall {
+    pub selector: Selector,
+    pub params: Vec<DynSolValue>,
+    pub returns: Option<Vec<DynSolType>>,
+}
+
+impl StaticCall {
+    pub fn parse(function: &str, by_hash: bool, types: &str, values: &str) -> Result<Self, String> {
+        let (function, returns) = match function.split_once("->") {
+            Some((function, returns)) => (function.trim(), Some(abi::parse_types(returns)?)),
+            None => (function.trim(), None),
+        };
+        let types = abi::parse_types(types)?;
+        let params = abi::parse_values(&types, values)?;
+        let selector = if by_hash {
+            let bytes = hex::decode(function).ok().filter(|bytes| bytes.len() == 4);
+            Selector::from_slice(&bytes.ok_or_else(|| format!("`{}` isn't a 4 byte selector", function))?)
+        } else {
+            if function.is_empty() || !function.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+                return Err(format!("`{}` isn't a function name", function));
+            }
+            let types: Vec<String> = types.iter().map(|ty| ty.sol_type_name().into_owned()).collect();
+            let signature = format!("{}({})", function, types.join(","));
+            Selector::from_slice(&keccak256(signature.as_bytes())[..4])
+        };
+        Ok(Self { selector, params, returns })
+    }
+
+    pub fn calldata(&self) -> Bytes {
+        let mut data = self.selector.to_vec();
+        data.extend(DynSolValue::Tuple(self.params.clone()).abi_encode_params());
+        Bytes::from(data)
+    }
+
+    /// One return type is that value, several are a tuple, none is the raw bytes.
+    pub fn decode(&self, output: &[u8]) -> Result<DynSolValue, String> {
+        let Some(returns) = &self.returns else {
+            return Ok(DynSolValue::Bytes(output.to_vec()));
+        };
+        let decoded = DynSolType::Tuple(returns.clone())
+            .abi_decode_params(output)
+            .map_err(|e| format!("couldn't decode the result as ({}): {}", type_list(returns), e))?;
+        match decoded {
+            DynSolValue::Tuple(mut values) if values.len() == 1 => Ok(values.remove(0)),
+            decoded => Ok(decoded),
+        }
+    }
+
+    /// Expression type of what [StaticCall::decode] gives.
+    pub fn result_type(&self) -> Type {
+        match self.returns.as_deref() {
+            None => Type::Bytes,
+            Some([ty]) => Type::from_sol(ty),
+            Some(_) => Type::List(Box::new(Type::Abi)),
+        }
+    }
+}
+
+fn type_list(types: &[DynSolType]) -> String {
+    types.iter().map(|ty| ty.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// A `get_contract_events` event definition and topic filter.
+///
+/// Topics are an ethabi tuple with an array per indexed parameter, in order, an empty array
+/// matching anything. `"([],[0x...])"` is transfers to one address. An empty string doesn't
+/// filter.
+#[derive(Clone, Debug)]
+pub struct EventQuery {
+    pub event: Event,
+    pub topics: Vec<Option<Vec<B256>>>,
+}
+
+impl EventQuery {
+    pub fn parse(definition: &str, topics: &str) -> Result<Self, String> {
+        let definition = definition.trim();
+        let mut event = Event::parse(definition.strip_prefix("event ").unwrap_or(definition))
+            .map_err(|e| format!("invalid event `{}`: {}", definition, e))?;
+        // `uint` is hashed as `uint256` in the selector
+        for input in &mut event.inputs {
+            if input.components.is_empty() {
+                input.ty = canonical(&input.ty)?;
+            }
+            for component in &mut input.components {
+                canonical_param(component)?;
+            }
+        }
+        let indexed = event
+            .inputs
+            .iter()
+            .filter(|input| input.indexed)
+            .map(|input| DynSolType::parse(&input.selector_type()).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut filter = Vec::new();
+        if !event.anonymous {
+            filter.push(Some(vec![event.selector()]));
+        }
+        if !topics.trim().is_empty() {
+            let ty = DynSolType::Tuple(indexed.iter().cloned().map(|ty| DynSolType::Array(Box::new(ty))).collect());
+            let DynSolValue::Tuple(positions) = abi::parse_value(&ty, topics)? else {
+                unreachable!("parsed as a tuple");
+            };
+            for values in positions {
+                let values = values.as_array().unwrap_or_default();
+                if values.is_empty() {
+                    filter.push(None);
+                    continue;
+                }
+                filter.push(Some(values.iter().map(topic).collect::<Result<_, _>>()?));
+            }
+        }
+        // Trailing wildcards are the same as no filter there
+        while filter.last() == Some(&None) {
+            filter.pop();
+        }
+        Ok(Self { event, topics: filter })
+    }
+
+    /// Parameter names to values, unnamed ones by position. `None` if the log isn't this event.
+    pub fn decode(&self, log: &Log) -> Option<Vec<(String, DynSolValue)>> {
+        let decoded = self.event.decode_log_parts(log.topics.iter().copied(), &log.data, true).ok()?;
+        let (mut indexed, mut body) = (decoded.indexed.into_iter(), decoded.body.into_iter());
+        self.event
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let value = if input.indexed { indexed.next() } else { body.next() }?;
+                let name = if input.name.is_empty() { i.to_string() } else { input.name.clone() };
+                Some((name, value))
+            })
+            .collect()
+    }
+}
+
+fn canonical(ty: &str) -> Result<String, String> {
+    DynSolType::parse(ty).map(|ty| ty.sol_type_name().into_owned()).map_err(|e| e.to_string())
+}
+
+fn canonical_param(param: &mut Param) -> Result<(), String> {
+    if param.components.is_empty() {
+        param.ty = canonical(&param.ty)?;
+    }
+    param.components.iter_mut().try_for_each(canonical_param)
+}
+
+/// Topic for an indexed value, dynamic values are indexed by their hash.
+fn topic(value: &DynSolValue) -> Result<B256, String> {
+    match value {
+        DynSolValue::Bytes(bytes) => Ok(keccak256(bytes)),
+        DynSolValue::String(s) => Ok(keccak256(s.as_bytes())),
+        value => value.as_word().ok_or_else(|| format!("can't filter on {}", abi::format_value(value))),
+    }
+}
@@ -0,0 +1,120 @@
+use super::{
+    builtins::{self, EventQuery, StaticCall},
+    parser::{BinaryOp, Node, NodeKind, UnaryOp},
+    ExprError, Type, Value,
+};
+
+/// Type of a variable, `None` if there's no such variable.
+pub(crate) fn var_type(name: &str) -> Option<Type> {
+    Some(match name {
+        "tx.hash" | "tx.input" | "tx.selector" | "block.hash" => Type::Bytes,
+        "tx.from" | "tx.to" => Type::Address,
+        "tx.value" | "tx.nonce" | "tx.gas" | "block.number" | "block.timestamp" | "chain_id" => Type::Int,
+        _ => return None,
+    })
+}
+
+pub(crate) fn check(node: &Node) -> Result<Type, ExprError> {
+    let pos = node.pos;
+    match &node.kind {
+        NodeKind::Literal(value) => Ok(value.ty()),
+        NodeKind::Var(name) => var_type(name).ok_or_else(|| ExprError::ty(pos, format!("unknown variable `{}`", name))),
+        NodeKind::Unary(UnaryOp::Not, operand) => {
+            expect(operand, &Type::Bool)?;
+            Ok(Type::Bool)
+        }
+        NodeKind::Binary(op, left, right) => {
+            let (l, r) = (check(left)?, check(right)?);
+            let mismatch = || ExprError::ty(pos, format!("can't apply `{}` to {} and {}", op.symbol(), l, r));
+            match op {
+                BinaryOp::Or | BinaryOp::And => {
+                    if !Type::Bool.accepts(&l) || !Type::Bool.accepts(&r) {
+                        return Err(mismatch());
+                    }
+                    Ok(Type::Bool)
+                }
+                BinaryOp::Eq | BinaryOp::Ne => {
+                    if !l.accepts(&r) {
+                        return Err(mismatch());
+                    }
+                    Ok(Type::Bool)
+                }
+                BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                    if !Type::Int.accepts(&l) || !Type::Int.accepts(&r) {
+                        return Err(mismatch());
+                    }
+                    Ok(Type::Bool)
+                }
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+                    if !Type::Int.accepts(&l) || !Type::Int.accepts(&r) {
+                        return Err(mismatch());
+                    }
+                    Ok(Type::Int)
+                }
+            }
+        }
+        NodeKind::Index(base, index) => {
+            let (base_ty, index_ty) = (check(base)?, check(index)?);
+            match (&base_ty, &index_ty) {
+                (Type::List(inner), i) if Type::Int.accepts(i) => Ok((**inner).clone()),
+                (Type::Bytes, i) if Type::Int.accepts(i) => Ok(Type::Int),
+                (Type::Map, k) if Type::String.accepts(k) => Ok(Type::Abi),
+                (Type::Abi, Type::Int | Type::String | Type::Abi) => Ok(Type::Abi),
+                _ => Err(ExprError::ty(pos, format!("can't index {} with {}", base_ty, index_ty))),
+            }
+        }
+        NodeKind::Call(name, args) if name == "len" => {
+            let [arg] = args.as_slice() else {
+                return Err(ExprError::ty(pos, "len takes 1 argument"));
+            };
+            match check(arg)? {
+                Type::List(_) | Type::Bytes | Type::String | Type::Abi => Ok(Type::Int),
+                ty => Err(ExprError::ty(arg.pos, format!("len of {}", ty))),
+            }
+        }
+        NodeKind::Call(name, args) => {
+            let (params, ret) =
+                builtins::signature(name).ok_or_else(|| ExprError::ty(pos, format!("unknown function `{}`", name)))?;
+            if args.len() != params.len() {
+                return Err(ExprError::ty(pos, format!("{} takes {} arguments, got {}", name, params.len(), args.len())));
+            }
+            for (arg, param) in args.iter().zip(&params) {
+                expect(arg, param)?;
+            }
+            check_literal_args(name, args, ret)
+        }
+    }
+}
+
+fn expect(node: &Node, ty: &Type) -> Result<(), ExprError> {
+    let actual = check(node)?;
+    if !ty.accepts(&actual) {
+        return Err(ExprError::ty(node.pos, format!("expected {}, found {}", ty, actual)));
+    }
+    Ok(())
+}
+
+fn literal_str(node: &Node) -> Option<&str> {
+    match &node.kind {
+        NodeKind::Literal(Value::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// ethabi arguments written as literals are checked now rather than on the first call, and a
+/// static call with literal return types gets their type instead of `ethabi`.
+fn check_literal_args(name: &str, args: &[Node], ret: Type) -> Result<Type, ExprError> {
+    let strings: Vec<Option<&str>> = args.iter().map(literal_str).collect();
+    match (name, strings.as_slice()) {
+        ("static_call" | "static_call_hash" | "static_call_repr" | "static_call_hash_repr", [_, Some(function), Some(types), Some(values)]) => {
+            let call = StaticCall::parse(function, name.starts_with("static_call_hash"), types, values)
+                .map_err(|e| ExprError::ty(args[1].pos, e))?;
+            Ok(if ret == Type::Abi { call.result_type() } else { ret })
+        }
+        ("get_contract_events", [_, Some(definition), Some(topics)]) => {
+            EventQuery::parse(definition, topics).map_err(|e| ExprError::ty(args[1].pos, e))?;
+            Ok(ret)
+        }
+        _ => Ok(ret),
+    }
+}
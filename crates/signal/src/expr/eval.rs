@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::{Address, Bytes, U256};
+use futures_util::{future::BoxFuture, FutureExt};
+
+use super::{
+    abi,
+    builtins::{self, EventQuery, StaticCall},
+    parser::{BinaryOp, Node, NodeKind, UnaryOp},
+    ExprError, Scope, Value,
+};
+use crate::api::{CallRequest, LogFilter};
+
+/// Boxed so it can recurse.
+pub(crate) fn eval<'a>(node: &'a Node, scope: &'a Scope<'a>) -> BoxFuture<'a, Result<Value, ExprError>> {
+    async move {
+        let pos = node.pos;
+        match &node.kind {
+            NodeKind::Literal(value) => Ok(value.clone()),
+            NodeKind::Var(name) => var(scope, name).ok_or_else(|| ExprError::eval(pos, format!("`{}` isn't set here", name))),
+            NodeKind::Unary(UnaryOp::Not, operand) => Ok(Value::Bool(!as_bool(eval(operand, scope).await?, operand.pos)?)),
+            // Right side only when needed, it may be a call
+            NodeKind::Binary(BinaryOp::And, left, right) => {
+                Ok(Value::Bool(as_bool(eval(left, scope).await?, left.pos)? && as_bool(eval(right, scope).await?, right.pos)?))
+            }
+            NodeKind::Binary(BinaryOp::Or, left, right) => {
+                Ok(Value::Bool(as_bool(eval(left, scope).await?, left.pos)? || as_bool(eval(right, scope).await?, right.pos)?))
+            }
+            NodeKind::Binary(op, left, right) => {
+                let (l, r) = (eval(left, scope).await?, eval(right, scope).await?);
+                binary(*op, l, r, pos)
+            }
+            NodeKind::Index(base, index) => {
+                let (base, index) = (eval(base, scope).await?, eval(index, scope).await?);
+                match (base, index) {
+                    (Value::List(values), Value::Int(i)) => values
+                        .into_iter()
+                        .nth(i.saturating_to::<usize>())
+                        .ok_or_else(|| ExprError::eval(pos, format!("index {} out of range", i))),
+                    (Value::Bytes(bytes), Value::Int(i)) => bytes
+                        .get(i.saturating_to::<usize>())
+                        .map(|b| Value::Int(U256::from(*b)))
+                        .ok_or_else(|| ExprError::eval(pos, format!("index {} out of range", i))),
+                    (Value::Map(mut map), Value::String(key)) => {
+                        map.remove(&key).ok_or_else(|| ExprError::eval(pos, format!("no field `{}`", key)))
+                    }
+                    (base, index) => Err(ExprError::eval(pos, format!("can't index {} with {}", base.ty(), index.ty()))),
+                }
+            }
+            NodeKind::Call(name, args) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(eval(arg, scope).await?);
+                }
+                call(scope, name, values, pos).await
+            }
+        }
+    }
+    .boxed()
+}
+
+fn var(scope: &Scope<'_>, name: &str) -> Option<Value> {
+    if name == "chain_id" {
+        return Some(Value::Int(U256::from(scope.chain_id)));
+    }
+    if let Some(field) = name.strip_prefix("block.") {
+        let block = scope.block?;
+        return Some(match field {
+            "number" => Value::Int(U256::from(block.number())),
+            "timestamp" => Value::Int(U256::from(block.timestamp())),
+            "hash" => Value::Bytes(Bytes::copy_from_slice(block.hash().as_slice())),
+            _ => return None,
+        });
+    }
+    let tx = scope.transaction?;
+    Some(match name.strip_prefix("tx.")? {
+        "hash" => Value::Bytes(Bytes::copy_from_slice(tx.hash.as_slice())),
+        "from" => Value::Address(tx.from),
+        // Contract creations have no `to`
+        "to" => Value::Address(tx.to.unwrap_or(Address::ZERO)),
+        "value" => Value::Int(tx.value),
+        "input" => Value::Bytes(tx.input.clone()),
+        "selector" => Value::Bytes(tx.selector().map_or_else(Bytes::new, |s| Bytes::copy_from_slice(s.as_slice()))),
+        "nonce" => Value::Int(U256::from(tx.nonce)),
+        "gas" => Value::Int(U256::from(tx.gas)),
+        _ => return None,
+    })
+}
+
+fn as_bool(value: Value, pos: usize) -> Result<bool, ExprError> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        value => Err(ExprError::eval(pos, format!("expected bool, got {}", value.ty()))),
+    }
+}
+
+fn binary(op: BinaryOp, l: Value, r: Value, pos: usize) -> Result<Value, ExprError> {
+    if let BinaryOp::Eq | BinaryOp::Ne = op {
+        if l.ty() != r.ty() && !matches!((&l, &r), (Value::List(_), Value::List(_))) {
+            return Err(ExprError::eval(pos, format!("can't compare {} with {}", l.ty(), r.ty())));
+        }
+        return Ok(Value::Bool((l == r) == (op == BinaryOp::Eq)));
+    }
+    let (Value::Int(a), Value::Int(b)) = (&l, &r) else {
+        return Err(ExprError::eval(pos, format!("can't apply `{}` to {} and {}", op.symbol(), l.ty(), r.ty())));
+    };
+    let (a, b) = (*a, *b);
+    let int = |value: Option<U256>, problem: &str| {
+        value.map(Value::Int).ok_or_else(|| ExprError::eval(pos, format!("{} in {} {} {}", problem, a, op.symbol(), b)))
+    };
+    match op {
+        BinaryOp::Lt => Ok(Value::Bool(a < b)),
+        BinaryOp::Le => Ok(Value::Bool(a <= b)),
+        BinaryOp::Gt => Ok(Value::Bool(a > b)),
+        BinaryOp::Ge => Ok(Value::Bool(a >= b)),
+        BinaryOp::Add => int(a.checked_add(b), "overflow"),
+        BinaryOp::Sub => int(a.checked_sub(b), "underflow"),
+        BinaryOp::Mul => int(a.checked_mul(b), "overflow"),
+        BinaryOp::Div => int(a.checked_div(b), "division by zero"),
+        BinaryOp::Rem => int(a.checked_rem(b), "division by zero"),
+        BinaryOp::Or | BinaryOp::And | BinaryOp::Eq | BinaryOp::Ne => unreachable!("handled above"),
+    }
+}
+
+async fn call(scope: &Scope<'_>, name: &str, args: Vec<Value>, pos: usize) -> Result<Value, ExprError> {
+    let rpc = |err| ExprError::Rpc { pos, err };
+    let at = scope.at();
+    match (name, args.as_slice()) {
+        ("len", [value]) => match value {
+            Value::List(values) => Ok(Value::Int(U256::from(values.len()))),
+            Value::Bytes(bytes) => Ok(Value::Int(U256::from(bytes.len()))),
+            Value::String(s) => Ok(Value::Int(U256::from(s.len()))),
+            value => Err(ExprError::eval(pos, format!("len of {}", value.ty()))),
+        },
+        ("get_balance", [Value::Address(address)]) => Ok(Value::Int(scope.api.get_balance(*address, at).await.map_err(rpc)?)),
+        ("is_contract", [Value::Address(address)]) => {
+            Ok(Value::Bool(!scope.api.get_code(*address, at).await.map_err(rpc)?.is_empty()))
+        }
+        ("read_slot", [Value::Address(address), Value::Int(slot)]) => {
+            let word = scope.api.get_storage_at(*address, *slot, at).await.map_err(rpc)?;
+            Ok(Value::Int(word.into()))
+        }
+        ("resolve_name", [Value::String(name)]) => {
+            let node = builtins::namehash(name);
+            let mut input = builtins::RESOLVER_SELECTOR.to_vec();
+            input.extend_from_slice(node.as_slice());
+            let resolver = word_address(&scope.api.call(&CallRequest::new(builtins::ENS_REGISTRY, input.into()), at, None).await.map_err(rpc)?);
+            let resolver = resolver.filter(|r| !r.is_zero()).ok_or_else(|| ExprError::eval(pos, format!("`{}` has no resolver", name)))?;
+            let mut input = builtins::ADDR_SELECTOR.to_vec();
+            input.extend_from_slice(node.as_slice());
+            let output = scope.api.call(&CallRequest::new(resolver, input.into()), at, None).await.map_err(rpc)?;
+            word_address(&output)
+                .map(Value::Address)
+                .ok_or_else(|| ExprError::eval(pos, format!("unexpected resolver result for `{}`", name)))
+        }
+        (
+            "static_call" | "static_call_hash" | "static_call_repr" | "static_call_hash_repr",
+            [Value::Address(address), Value::String(function), Value::String(types), Value::String(values)],
+        ) => {
+            let static_call = StaticCall::parse(function, name.starts_with("static_call_hash"), types, values)
+                .map_err(|e| ExprError::eval(pos, e))?;
+            let output = scope.api.call(&CallRequest::new(*address, static_call.calldata()), at, None).await.map_err(rpc)?;
+            let value = static_call.decode(&output).map_err(|e| ExprError::eval(pos, e))?;
+            if name.ends_with("_repr") {
+                return Ok(Value::String(abi::format_value(&value)));
+            }
+            Value::from_abi(value).map_err(|e| ExprError::eval(pos, e))
+        }
+        ("get_contract_events", [Value::Address(address), Value::String(definition), Value::String(topics)]) => {
+            let query = EventQuery::parse(definition, topics).map_err(|e| ExprError::eval(pos, e))?;
+            // Logs of the scope's block, or of `latest` outside one
+            let filter = LogFilter {
+                address: vec![(*address).into()],
+                topics: query
+                    .topics
+                    .iter()
+                    .map(|topic| topic.as_ref().map(|topics| topics.iter().map(|t| t.to_string()).collect()))
+                    .collect(),
+                block_hash: scope.block.map(|block| block.hash()),
+                from_block: scope.block.is_none().then_some(at),
+                to_block: scope.block.is_none().then_some(at),
+            };
+            let mut events = Vec::new();
+            for log in scope.api.get_logs(&filter).await.map_err(rpc)? {
+                let Some(fields) = query.decode(&log) else {
+                    continue;
+                };
+                let mut event = BTreeMap::new();
+                for (name, value) in fields {
+                    event.insert(name, Value::from_abi(value).map_err(|e| ExprError::eval(pos, e))?);
+                }
+                events.push(Value::Map(event));
+            }
+            Ok(Value::List(events))
+        }
+        (name, args) => {
+            let types: Vec<String> = args.iter().map(|arg| arg.ty().to_string()).collect();
+            Err(ExprError::eval(pos, format!("can't call {} with ({})", name, types.join(", "))))
+        }
+    }
+}
+
+/// Address in the low 20 bytes of a returned word.
+fn word_address(output: &[u8]) -> Option<Address> {
+    (output.len() == 32).then(|| Address::from_slice(&output[12..]))
+}
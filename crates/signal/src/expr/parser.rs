@@ -0,0 +1,373 @@
+use alloy_primitives::{hex, Address, Bytes, U256};
+
+use super::{abi, ExprError, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum NodeKind {
+    Literal(Value),
+    /// `tx.to`, `block.number`, dots included
+    Var(String),
+    Call(String, Vec<Node>),
+    Index(Box<Node>, Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+/// `pos` is the byte offset in the source, for errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Node {
+    pub kind: NodeKind,
+    pub pos: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    /// Decimal digits, maybe with a `.`
+    Number(String),
+    /// Digits after `0x`
+    Hex(String),
+    /// `hex"..."`
+    HexBytes(Vec<u8>),
+    Str(String),
+    Punct(&'static str),
+    Eof,
+}
+
+// Longest first so `<=` isn't read as `<`
+const PUNCTUATION: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]", ",", ".",
+];
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, ExprError> {
+    let mut tokens = Vec::new();
+    let bytes = source.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        let start = pos;
+        let rest = &source[pos..];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let token = if let Some(literal) = rest.strip_prefix("hex\"") {
+            let end = literal.find('"').ok_or_else(|| ExprError::syntax(start, "unterminated hex literal"))?;
+            let bytes = hex::decode(&literal[..end]).map_err(|_| ExprError::syntax(start, "invalid hex literal"))?;
+            pos += 5 + end;
+            Token::HexBytes(bytes)
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            pos += len;
+            Token::Ident(rest[..len].to_string())
+        } else if let Some(digits) = rest.strip_prefix("0x") {
+            let len = digits.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(digits.len());
+            pos += 2 + len;
+            Token::Hex(digits[..len].to_string())
+        } else if c.is_ascii_digit() {
+            let mut len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            // `1.5` but not `1.foo`
+            if rest[len..].starts_with('.') && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                len += 1 + rest[len + 1..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - len - 1);
+            }
+            pos += len;
+            Token::Number(rest[..len].to_string())
+        } else if c == b'"' {
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, c)) => value.push(c),
+                        None => return Err(ExprError::syntax(start, "unterminated string")),
+                    },
+                    Some((_, c)) => value.push(c),
+                    None => return Err(ExprError::syntax(start, "unterminated string")),
+                }
+            };
+            pos += end + 2;
+            Token::Str(value)
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                .ok_or_else(|| ExprError::syntax(start, format!("unexpected `{}`", rest.chars().next().unwrap())))?;
+            pos += punct.len();
+            Token::Punct(punct)
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::Eof, source.len()));
+    Ok(tokens)
+}
+
+pub(crate) fn parse(source: &str) -> Result<Node, ExprError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        next: 0,
+    };
+    let node = parser.or()?;
+    match parser.peek() {
+        Token::Eof => Ok(node),
+        token => Err(ExprError::syntax(parser.pos(), format!("unexpected {}", describe(token)))),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(name) => format!("`{}`", name),
+        Token::Number(n) => format!("`{}`", n),
+        Token::Hex(h) => format!("`0x{}`", h),
+        Token::HexBytes(_) => "hex literal".to_string(),
+        Token::Str(_) => "string".to_string(),
+        Token::Punct(p) => format!("`{}`", p),
+        Token::Eof => "end of expression".to_string(),
+    }
+}
+
+/// Precedence climbing, loosest first: `||`, `&&`, `== !=`, `< <= > >=`, `+ -`, `* / %`, `!`,
+/// then calls, indexing and member access.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        if token.0 != Token::Eof {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Token::Punct(p) if *p == punct) {
+            self.next += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ExprError> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        Err(ExprError::syntax(self.pos(), format!("expected `{}`, found {}", punct, describe(self.peek()))))
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Node, ExprError>,
+    ) -> Result<Node, ExprError> {
+        let mut left = operand(self)?;
+        'outer: loop {
+            for (punct, op) in ops {
+                let pos = self.pos();
+                if self.eat(punct) {
+                    let right = operand(self)?;
+                    left = Node {
+                        kind: NodeKind::Binary(*op, Box::new(left), Box::new(right)),
+                        pos,
+                    };
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, ExprError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node, ExprError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Node, ExprError> {
+        self.binary(&[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Node, ExprError> {
+        let ops = [("<=", BinaryOp::Le), (">=", BinaryOp::Ge), ("<", BinaryOp::Lt), (">", BinaryOp::Gt)];
+        self.binary(&ops, Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Node, ExprError> {
+        self.binary(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Node, ExprError> {
+        self.binary(&[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        let pos = self.pos();
+        if self.eat("!") {
+            let operand = self.unary()?;
+            return Ok(Node {
+                kind: NodeKind::Unary(UnaryOp::Not, Box::new(operand)),
+                pos,
+            });
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.primary()?;
+        loop {
+            let pos = self.pos();
+            if self.eat("[") {
+                let index = self.or()?;
+                self.expect("]")?;
+                node = Node {
+                    kind: NodeKind::Index(Box::new(node), Box::new(index)),
+                    pos,
+                };
+            } else if self.eat(".") {
+                let (Token::Ident(field), _) = self.advance() else {
+                    return Err(ExprError::syntax(pos, "expected a field name after `.`"));
+                };
+                node = match node.kind {
+                    NodeKind::Var(name) => Node {
+                        kind: NodeKind::Var(format!("{}.{}", name, field)),
+                        pos: node.pos,
+                    },
+                    // Same as indexing a map with the name
+                    kind => Node {
+                        kind: NodeKind::Index(
+                            Box::new(Node { kind, pos: node.pos }),
+                            Box::new(Node {
+                                kind: NodeKind::Literal(Value::String(field)),
+                                pos,
+                            }),
+                        ),
+                        pos,
+                    },
+                };
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ExprError> {
+        let (token, pos) = self.advance();
+        let literal = |value| Ok(Node {
+            kind: NodeKind::Literal(value),
+            pos,
+        });
+        match token {
+            Token::Number(number) => {
+                // Optional unit, `100 ether`
+                let unit = match self.peek() {
+                    Token::Ident(unit) if abi::unit_decimals(unit).is_some() => {
+                        let unit = unit.clone();
+                        self.next += 1;
+                        unit
+                    }
+                    _ => String::new(),
+                };
+                match abi::parse_uint(&format!("{}{}", number, unit)) {
+                    Some(value) => literal(Value::Int(value)),
+                    None if unit.is_empty() => Err(ExprError::syntax(pos, format!("`{}` needs a unit", number))),
+                    None => Err(ExprError::syntax(pos, format!("`{} {}` isn't a whole number of wei", number, unit))),
+                }
+            }
+            // 20 bytes is an address, anything else a number
+            Token::Hex(digits) if digits.len() == 40 => {
+                let address = digits.parse::<Address>().map_err(|_| ExprError::syntax(pos, "invalid address"))?;
+                literal(Value::Address(address))
+            }
+            Token::Hex(digits) => match U256::from_str_radix(&digits, 16) {
+                Ok(value) if !digits.is_empty() => literal(Value::Int(value)),
+                _ => Err(ExprError::syntax(pos, format!("invalid number `0x{}`", digits))),
+            },
+            Token::HexBytes(bytes) => literal(Value::Bytes(Bytes::from(bytes))),
+            Token::Str(s) => literal(Value::String(s)),
+            Token::Ident(name) if name == "true" || name == "false" => literal(Value::Bool(name == "true")),
+            Token::Ident(name) => {
+                if !self.eat("(") {
+                    return Ok(Node {
+                        kind: NodeKind::Var(name),
+                        pos,
+                    });
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.or()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Node {
+                    kind: NodeKind::Call(name, args),
+                    pos,
+                })
+            }
+            Token::Punct("(") => {
+                let node = self.or()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            token => Err(ExprError::syntax(pos, format!("expected an expression, found {}", describe(&token)))),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::{collections::VecDeque, str::FromStr};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{BlockTag, EthJsonRpc, RpcError},
+    model::Block,
+};
+
+/// Blocks a [ChainFollower] keeps by default, enough to cover finality on mainnet.
+pub const DEFAULT_WINDOW: usize = 128;
+
+/// How far behind the head the follower runs. `Latest` is the fastest and the most likely to be
+/// reorged, `Finalized` can't be reorged but lags by roughly two epochs (~13 minutes on mainnet).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingDepth {
+    Latest,
+    /// This many blocks behind `latest`.
+    Confirmations(u64),
+    Safe,
+    Finalized,
+}
+
+impl FromStr for ProcessingDepth {
+    type Err = String;
+
+    /// `latest`, `safe`, `finalized` or a number of confirmations.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(ProcessingDepth::Latest),
+            "safe" => Ok(ProcessingDepth::Safe),
+            "finalized" => Ok(ProcessingDepth::Finalized),
+            n => n
+                .parse()
+                .map(ProcessingDepth::Confirmations)
+                .map_err(|_| format!("Unknown processing depth: {}", s)),
+        }
+    }
+}
+
+/// The parts of a block the follower needs to keep to spot a reorg.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockRef {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+}
+
+impl BlockRef {
+    fn from_block(block: &Block) -> Self {
+        Self {
+            number: block.header.number,
+            hash: block.header.hash,
+            parent_hash: block.header.parent_hash,
+        }
+    }
+}
+
+/// What changed on the canonical chain since the last poll, in the order it should be applied.
+/// Reverts always come before the blocks that replace them.
+#[derive(Debug)]
+pub enum ChainNotification {
+    BlockAdded(Box<Block>),
+    /// Block is no longer canonical, anything produced from it should be retracted.
+    BlockReverted(BlockRef),
+    /// A block we added earlier is now finalized and can't be reverted any more. Not sent when
+    /// following at [ProcessingDepth::Finalized], every added block is final already.
+    BlockFinalized(BlockRef),
+    /// A block we added earlier left the window before it was seen finalized, e.g. the node
+    /// doesn't serve the `finalized` tag or finality lags the window. Nothing more is sent for it.
+    /// Not sent at [ProcessingDepth::Finalized] either.
+    BlockEvicted(BlockRef),
+}
+
+/// Follows the canonical chain through [EthJsonRpc] so every block is seen exactly once, including
+/// blocks that arrive between polls, and reorgs are noticed.
+///
+/// New heads are walked back by `parentHash` until they join the window of recent blocks we have
+/// already emitted. Anything in the window past that point has been orphaned and is reverted.
+/// A reorg deeper than the window can't be resolved this way, the whole window is reverted and we
+/// carry on from the new head.
+///
+/// Below [ProcessingDepth::Finalized] the finalized head is checked on each poll too, so blocks
+/// still in the window get a [ChainNotification::BlockFinalized]. The window has to be longer
+/// than the finality lag for that, which the default is, blocks that leave it first get a
+/// [ChainNotification::BlockEvicted] instead.
+pub struct ChainFollower<'a, P> {
+    api: &'a P,
+    depth: ProcessingDepth,
+    window: VecDeque<BlockRef>,
+    window_size: usize,
+    next: Option<u64>,
+    // Highest block we have sent BlockFinalized for
+    finalized: u64,
+}
+
+impl<'a, P: EthJsonRpc + Sync> ChainFollower<'a, P> {
+    pub const DEFAULT_WINDOW: usize = DEFAULT_WINDOW;
+
+    /// Starts at whatever the head is on the first poll.
+    pub fn new(api: &'a P) -> Self {
+        Self {
+            api,
+            depth: ProcessingDepth::Latest,
+            window: VecDeque::new(),
+            window_size: Self::DEFAULT_WINDOW,
+            next: None,
+            finalized: 0,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: ProcessingDepth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn depth(&self) -> ProcessingDepth {
+        self.depth
+    }
+
+    /// Starts at `number` instead of the head, earlier blocks are caught up a window at a time.
+    pub fn with_start(mut self, number: u64) -> Self {
+        self.next = Some(number);
+        self
+    }
+
+    pub fn with_window(mut self, window_size: usize) -> Self {
+        self.window_size = window_size.max(1);
+        self
+    }
+
+    /// Most recent canonical block we have emitted.
+    pub fn tip(&self) -> Option<&BlockRef> {
+        self.window.back()
+    }
+
+    /// A block we asked for by number or hash has to exist, unlike the head at a tag.
+    async fn block(&self, block: BlockTag) -> Result<Block, RpcError> {
+        let method = match block {
+            BlockTag::Hash(_) => "eth_getBlockByHash",
+            _ => "eth_getBlockByNumber",
+        };
+        self.api
+            .get_block(block)
+            .await?
+            .ok_or_else(|| RpcError::NullResult(method.to_string()))
+    }
+
+    /// Head at our depth, `None` if the chain isn't that long yet.
+    async fn head(&self) -> Result<Option<Block>, RpcError> {
+        let tag = match self.depth {
+            ProcessingDepth::Latest => BlockTag::Latest,
+            ProcessingDepth::Safe => BlockTag::Safe,
+            ProcessingDepth::Finalized => BlockTag::Finalized,
+            ProcessingDepth::Confirmations(confirmations) => {
+                let latest = self.api.get_block_number().await?;
+                let Some(number) = latest.checked_sub(confirmations) else {
+                    return Ok(None);
+                };
+                BlockTag::Number(number)
+            }
+        };
+        self.block(tag).await.map(Some)
+    }
+
+    /// BlockFinalized for window blocks the finalized head has passed. Best effort, nodes without
+    /// the `finalized` tag just never upgrade.
+    async fn finalize(&mut self) -> Vec<ChainNotification> {
+        let mut notifications = Vec::new();
+        if self.depth == ProcessingDepth::Finalized {
+            return notifications;
+        }
+        let Ok(Some(finalized)) = self.api.get_block(BlockTag::Finalized).await else {
+            return notifications;
+        };
+        let number = finalized.header.number;
+        // Our copy of that block was orphaned, wait until the reorg shows up on a poll
+        let mismatch = self
+            .window
+            .iter()
+            .any(|block| block.number == number && block.hash != finalized.header.hash);
+        if mismatch {
+            return notifications;
+        }
+        for block in &self.window {
+            if block.number > self.finalized && block.number <= number {
+                notifications.push(ChainNotification::BlockFinalized(block.clone()));
+            }
+        }
+        self.finalized = self.finalized.max(number);
+        notifications
+    }
+
+    fn known(&self, hash: &B256) -> bool {
+        self.window.iter().any(|block| block.hash == *hash)
+    }
+
+    /// Fetches the head at our depth and returns what changed since the last call. Empty if the
+    /// head hasn't moved. At most one window of new blocks is returned per call, call again to
+    /// catch up.
+    pub async fn poll(&mut self) -> Result<Vec<ChainNotification>, RpcError> {
+        let Some(mut head) = self.head().await? else {
+            return Ok(Vec::new());
+        };
+        if self.window.is_empty() && self.next.is_some_and(|next| head.header.number < next) {
+            return Ok(Vec::new());
+        }
+
+        // Don't walk back further than a window in one go, go forward by number instead
+        let from = self.tip().map(|tip| tip.number + 1).or(self.next);
+        if let Some(from) = from {
+            let limit = from + self.window_size as u64 - 1;
+            if head.header.number > limit {
+                head = self.block(BlockTag::Number(limit)).await?;
+            }
+        }
+
+        // Newest first
+        let mut new_blocks = Vec::new();
+        let mut block = head;
+        loop {
+            let block_ref = BlockRef::from_block(&block);
+            if self.known(&block_ref.hash) {
+                break;
+            }
+            let oldest = self.window.front().map(|b| b.number);
+            let joined = self.known(&block_ref.parent_hash);
+            let start = match (oldest, self.next) {
+                (None, Some(next)) => block_ref.number <= next,
+                (None, None) => true,
+                _ => false,
+            };
+            let too_deep = oldest.is_some_and(|oldest| block_ref.number <= oldest);
+            new_blocks.push((block_ref, block));
+            if joined || start || too_deep {
+                break;
+            }
+            block = self.block(BlockTag::Hash(new_blocks.last().unwrap().0.parent_hash)).await?;
+        }
+
+        let mut notifications = Vec::new();
+        let Some((oldest_new, _)) = new_blocks.last() else {
+            return Ok(self.finalize().await);
+        };
+
+        // Pop everything after the fork point, if the new blocks don't join the window at all
+        // this empties it
+        let fork = oldest_new.parent_hash;
+        while let Some(tip) = self.window.back() {
+            if tip.hash == fork {
+                break;
+            }
+            notifications.push(ChainNotification::BlockReverted(self.window.pop_back().unwrap()));
+        }
+
+        for (block_ref, block) in new_blocks.into_iter().rev() {
+            self.window.push_back(block_ref);
+            notifications.push(ChainNotification::BlockAdded(Box::new(block)));
+        }
+        self.next = None;
+        // Before evicting, blocks finalized by now are upgraded rather than evicted
+        notifications.extend(self.finalize().await);
+        while self.window.len() > self.window_size {
+            let block = self.window.pop_front().unwrap();
+            if self.depth != ProcessingDepth::Finalized && block.number > self.finalized {
+                notifications.push(ChainNotification::BlockEvicted(block));
+            }
+        }
+        Ok(notifications)
+    }
+}
@@ -1,3 +1,15 @@
+pub mod address;
 pub mod api;
+pub mod chain;
+pub mod correlation;
+pub mod expr;
+pub mod follower;
+pub mod model;
+pub mod registry;
+pub mod rules;
+pub mod score;
+pub mod sink;
 pub mod sol;
+pub mod state;
 pub mod types;
+pub mod units;
@@ -0,0 +1,507 @@
+use alloy_primitives::{Address, Bloom, Bytes, Selector, B256, B64, U256};
+use serde::{Deserialize, Serialize};
+
+/// Hex quantities (`"0x1a"`) as plain integers. Nodes send every number as one of these.
+mod quantity {
+    use alloy_primitives::U128;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Copy + Into<u128>, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        U128::from((*value).into()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: TryFrom<u128>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        let value = U128::deserialize(deserializer)?;
+        T::try_from(value.to::<u128>()).map_err(|_| D::Error::custom(format!("quantity {} out of range", value)))
+    }
+
+    pub mod opt {
+        use super::*;
+
+        pub fn serialize<T: Copy + Into<u128>, S: Serializer>(
+            value: &Option<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            value.map(|value| U128::from(value.into())).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T: TryFrom<u128>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+            match Option::<U128>::deserialize(deserializer)? {
+                Some(value) => T::try_from(value.to::<u128>())
+                    .map(Some)
+                    .map_err(|_| D::Error::custom(format!("quantity {} out of range", value))),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+/// Block header fields as returned by `eth_getBlockByNumber`/`eth_getBlockByHash`.
+///
+/// Only `hash`, `parentHash`, `number` and `timestamp` are required. Other fields default to zero
+/// if the node leaves them out, and fields added by later forks are `None` on older blocks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    pub hash: B256,
+    pub parent_hash: B256,
+    #[serde(with = "quantity")]
+    pub number: u64,
+    #[serde(with = "quantity")]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub sha3_uncles: B256,
+    #[serde(default)]
+    pub miner: Address,
+    #[serde(default)]
+    pub state_root: B256,
+    #[serde(default)]
+    pub transactions_root: B256,
+    #[serde(default)]
+    pub receipts_root: B256,
+    #[serde(default)]
+    pub logs_bloom: Bloom,
+    #[serde(default)]
+    pub difficulty: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_difficulty: Option<U256>,
+    #[serde(default, with = "quantity")]
+    pub gas_limit: u64,
+    #[serde(default, with = "quantity")]
+    pub gas_used: u64,
+    #[serde(default)]
+    pub extra_data: Bytes,
+    #[serde(default)]
+    pub mix_hash: B256,
+    #[serde(default)]
+    pub nonce: B64,
+    /// London
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<u128>,
+    /// Shanghai
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals_root: Option<B256>,
+    /// Cancun
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<u64>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_beacon_block_root: Option<B256>,
+    /// Prague
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<B256>,
+}
+
+/// Validator withdrawal, `amount` is in gwei.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    #[serde(with = "quantity")]
+    pub index: u64,
+    #[serde(with = "quantity")]
+    pub validator_index: u64,
+    pub address: Address,
+    #[serde(with = "quantity")]
+    pub amount: u64,
+}
+
+/// A block fetched with full transactions (the `true` flag on `eth_getBlockBy*`), blocks with
+/// only transaction hashes don't decode.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    #[serde(flatten)]
+    pub header: Header,
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    #[serde(default)]
+    pub uncles: Vec<B256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // Not part of the block, see attach_receipts
+    #[serde(skip)]
+    receipts: Option<Vec<Receipt>>,
+}
+
+impl Block {
+    pub fn number(&self) -> u64 {
+        self.header.number
+    }
+
+    pub fn hash(&self) -> B256 {
+        self.header.hash
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.header.timestamp
+    }
+
+    /// Receipts from [crate::api::EthJsonRpc::get_block_receipts], fetched once so events can
+    /// read them without their own calls.
+    pub fn attach_receipts(&mut self, receipts: Vec<Receipt>) {
+        self.receipts = Some(receipts);
+    }
+
+    /// `None` if receipts haven't been attached.
+    pub fn receipts(&self) -> Option<&[Receipt]> {
+        self.receipts.as_deref()
+    }
+
+    pub fn receipt(&self, transaction_hash: B256) -> Option<&Receipt> {
+        self.receipts()?
+            .iter()
+            .find(|receipt| receipt.transaction_hash == transaction_hash)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<B256>,
+}
+
+/// EIP-7702 authorization, lets `authority` run `address`'s code as its own.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authorization {
+    pub chain_id: U256,
+    pub address: Address,
+    #[serde(with = "quantity")]
+    pub nonce: u64,
+    #[serde(with = "quantity")]
+    pub y_parity: u8,
+    pub r: U256,
+    pub s: U256,
+}
+
+/// Fields that only exist on some transaction types.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionKind {
+    Legacy,
+    /// EIP-2930
+    AccessList { access_list: Vec<AccessListItem> },
+    /// EIP-1559
+    DynamicFee {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+        access_list: Vec<AccessListItem>,
+    },
+    /// EIP-4844
+    Blob {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+        max_fee_per_blob_gas: u128,
+        access_list: Vec<AccessListItem>,
+        blob_versioned_hashes: Vec<B256>,
+    },
+    /// EIP-7702
+    SetCode {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+        access_list: Vec<AccessListItem>,
+        authorization_list: Vec<Authorization>,
+    },
+    /// Chain specific types, e.g. Optimism deposits (`0x7e`) or Arbitrum system transactions.
+    /// Only the common fields are decoded for these.
+    Other(u8),
+}
+
+impl TransactionKind {
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            TransactionKind::Legacy => 0,
+            TransactionKind::AccessList { .. } => 1,
+            TransactionKind::DynamicFee { .. } => 2,
+            TransactionKind::Blob { .. } => 3,
+            TransactionKind::SetCode { .. } => 4,
+            TransactionKind::Other(tx_type) => *tx_type,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub r: U256,
+    pub s: U256,
+    pub v: U256,
+    pub y_parity: Option<u8>,
+}
+
+/// A transaction as returned inside a block or by `eth_getTransactionByHash`. Block fields are
+/// `None` while it is pending.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawTransaction", into = "RawTransaction")]
+pub struct Transaction {
+    pub hash: B256,
+    pub kind: TransactionKind,
+    pub nonce: u64,
+    pub block_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub transaction_index: Option<u64>,
+    pub from: Address,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    /// Wei
+    pub value: U256,
+    pub gas: u64,
+    /// `gasPrice` as the node returns it. For mined EIP-1559 and later transactions this is the
+    /// effective price paid.
+    pub gas_price: Option<u128>,
+    pub input: Bytes,
+    pub chain_id: Option<u64>,
+    pub signature: Option<Signature>,
+}
+
+impl Transaction {
+    pub fn is_contract_creation(&self) -> bool {
+        self.to.is_none()
+    }
+
+    /// First four bytes of the input, `None` for plain transfers.
+    pub fn selector(&self) -> Option<Selector> {
+        self.input.get(..4).map(Selector::from_slice)
+    }
+}
+
+/// Every field any transaction type can have, the wire format [Transaction] goes through.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTransaction {
+    hash: B256,
+    #[serde(rename = "type", default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    tx_type: Option<u8>,
+    #[serde(with = "quantity")]
+    nonce: u64,
+    block_hash: Option<B256>,
+    #[serde(default, with = "quantity::opt")]
+    block_number: Option<u64>,
+    #[serde(default, with = "quantity::opt")]
+    transaction_index: Option<u64>,
+    from: Address,
+    to: Option<Address>,
+    value: U256,
+    #[serde(with = "quantity")]
+    gas: u64,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    gas_price: Option<u128>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    max_fee_per_gas: Option<u128>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    max_priority_fee_per_gas: Option<u128>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    max_fee_per_blob_gas: Option<u128>,
+    // Older nodes call it data
+    #[serde(alias = "data")]
+    input: Bytes,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    chain_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_list: Option<Vec<AccessListItem>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blob_versioned_hashes: Option<Vec<B256>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    authorization_list: Option<Vec<Authorization>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    v: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    r: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    s: Option<U256>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    y_parity: Option<u8>,
+}
+
+impl TryFrom<RawTransaction> for Transaction {
+    type Error = String;
+
+    fn try_from(raw: RawTransaction) -> Result<Self, Self::Error> {
+        // Pre-Berlin nodes don't send a type, those are all legacy
+        let tx_type = raw.tx_type.unwrap_or(0);
+        let missing = |field: &str| format!("type {} transaction {} has no {}", tx_type, raw.hash, field);
+        let max_fee_per_gas = || raw.max_fee_per_gas.ok_or_else(|| missing("maxFeePerGas"));
+        let max_priority_fee_per_gas = || raw.max_priority_fee_per_gas.ok_or_else(|| missing("maxPriorityFeePerGas"));
+        let access_list = raw.access_list.clone().unwrap_or_default();
+
+        let kind = match tx_type {
+            0 => TransactionKind::Legacy,
+            1 => TransactionKind::AccessList { access_list },
+            2 => TransactionKind::DynamicFee {
+                max_fee_per_gas: max_fee_per_gas()?,
+                max_priority_fee_per_gas: max_priority_fee_per_gas()?,
+                access_list,
+            },
+            3 => TransactionKind::Blob {
+                max_fee_per_gas: max_fee_per_gas()?,
+                max_priority_fee_per_gas: max_priority_fee_per_gas()?,
+                max_fee_per_blob_gas: raw.max_fee_per_blob_gas.ok_or_else(|| missing("maxFeePerBlobGas"))?,
+                access_list,
+                blob_versioned_hashes: raw.blob_versioned_hashes.clone().unwrap_or_default(),
+            },
+            4 => TransactionKind::SetCode {
+                max_fee_per_gas: max_fee_per_gas()?,
+                max_priority_fee_per_gas: max_priority_fee_per_gas()?,
+                access_list,
+                authorization_list: raw.authorization_list.clone().ok_or_else(|| missing("authorizationList"))?,
+            },
+            other => TransactionKind::Other(other),
+        };
+
+        let signature = match (raw.r, raw.s, raw.v) {
+            (Some(r), Some(s), Some(v)) => Some(Signature { r, s, v, y_parity: raw.y_parity }),
+            _ => None,
+        };
+        Ok(Self {
+            hash: raw.hash,
+            kind,
+            nonce: raw.nonce,
+            block_hash: raw.block_hash,
+            block_number: raw.block_number,
+            transaction_index: raw.transaction_index,
+            from: raw.from,
+            to: raw.to,
+            value: raw.value,
+            gas: raw.gas,
+            gas_price: raw.gas_price,
+            input: raw.input,
+            chain_id: raw.chain_id,
+            signature,
+        })
+    }
+}
+
+impl From<Transaction> for RawTransaction {
+    fn from(tx: Transaction) -> Self {
+        let mut raw = RawTransaction {
+            hash: tx.hash,
+            tx_type: Some(tx.kind.tx_type()),
+            nonce: tx.nonce,
+            block_hash: tx.block_hash,
+            block_number: tx.block_number,
+            transaction_index: tx.transaction_index,
+            from: tx.from,
+            to: tx.to,
+            value: tx.value,
+            gas: tx.gas,
+            gas_price: tx.gas_price,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_blob_gas: None,
+            input: tx.input,
+            chain_id: tx.chain_id,
+            access_list: None,
+            blob_versioned_hashes: None,
+            authorization_list: None,
+            v: tx.signature.as_ref().map(|sig| sig.v),
+            r: tx.signature.as_ref().map(|sig| sig.r),
+            s: tx.signature.as_ref().map(|sig| sig.s),
+            y_parity: tx.signature.and_then(|sig| sig.y_parity),
+        };
+        match tx.kind {
+            TransactionKind::Legacy | TransactionKind::Other(_) => {}
+            TransactionKind::AccessList { access_list } => raw.access_list = Some(access_list),
+            TransactionKind::DynamicFee { max_fee_per_gas, max_priority_fee_per_gas, access_list } => {
+                raw.max_fee_per_gas = Some(max_fee_per_gas);
+                raw.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                raw.access_list = Some(access_list);
+            }
+            TransactionKind::Blob {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                max_fee_per_blob_gas,
+                access_list,
+                blob_versioned_hashes,
+            } => {
+                raw.max_fee_per_gas = Some(max_fee_per_gas);
+                raw.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                raw.max_fee_per_blob_gas = Some(max_fee_per_blob_gas);
+                raw.access_list = Some(access_list);
+                raw.blob_versioned_hashes = Some(blob_versioned_hashes);
+            }
+            TransactionKind::SetCode { max_fee_per_gas, max_priority_fee_per_gas, access_list, authorization_list } => {
+                raw.max_fee_per_gas = Some(max_fee_per_gas);
+                raw.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                raw.access_list = Some(access_list);
+                raw.authorization_list = Some(authorization_list);
+            }
+        }
+        raw
+    }
+}
+
+/// `eth_getTransactionReceipt`/`eth_getBlockReceipts` item.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    pub transaction_hash: B256,
+    #[serde(default, with = "quantity")]
+    pub transaction_index: u64,
+    #[serde(default)]
+    pub block_hash: B256,
+    #[serde(default, with = "quantity")]
+    pub block_number: u64,
+    #[serde(default)]
+    pub from: Address,
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// Set when the transaction itself created a contract, contracts created by internal calls
+    /// only show up in traces.
+    #[serde(default)]
+    pub contract_address: Option<Address>,
+    #[serde(default, with = "quantity")]
+    pub cumulative_gas_used: u64,
+    #[serde(default, with = "quantity")]
+    pub gas_used: u64,
+    #[serde(default, with = "quantity")]
+    pub effective_gas_price: u128,
+    /// `1` success, `0` reverted. Pre-Byzantium receipts have `root` instead.
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub status: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<B256>,
+    #[serde(default)]
+    pub logs: Vec<Log>,
+    #[serde(default)]
+    pub logs_bloom: Bloom,
+    #[serde(rename = "type", default, with = "quantity")]
+    pub tx_type: u8,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<u64>,
+    #[serde(default, with = "quantity::opt", skip_serializing_if = "Option::is_none")]
+    pub blob_gas_price: Option<u128>,
+}
+
+impl Receipt {
+    /// `false` for reverted transactions, pre-Byzantium receipts have no status and count as
+    /// successful.
+    pub fn is_success(&self) -> bool {
+        self.status != Some(0)
+    }
+}
+
+/// Log from a receipt, `eth_getLogs` or a `logs` subscription. Position fields are `None` for
+/// pending logs, `removed` is set when a subscription sends a log again because of a reorg.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub address: Address,
+    #[serde(default)]
+    pub topics: Vec<B256>,
+    #[serde(default)]
+    pub data: Bytes,
+    #[serde(default)]
+    pub block_hash: Option<B256>,
+    #[serde(default, with = "quantity::opt")]
+    pub block_number: Option<u64>,
+    #[serde(default)]
+    pub transaction_hash: Option<B256>,
+    #[serde(default, with = "quantity::opt")]
+    pub transaction_index: Option<u64>,
+    #[serde(default, with = "quantity::opt")]
+    pub log_index: Option<u64>,
+    #[serde(default)]
+    pub removed: bool,
+}
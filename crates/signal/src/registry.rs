@@ -0,0 +1,257 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    api::EthJsonRpc,
+    chain::ChainConfig,
+    correlation::Pattern,
+    model::Block,
+    rules::RuleSet,
+    sink::{Match, Sink},
+    state::StateStore,
+    types::{
+        AnonymouslyFundedSmartContractTriggeredSignal, BlockContext, DetectorMeta, Event, Signal,
+        SuspiciousContractCalledEvent, SuspiciousContractCreatedEvent, TornadoCashWithdrawEvent,
+        TransferFromFixedFloatEvent,
+    },
+};
+
+type EventFactory = Arc<dyn Fn(&ChainConfig) -> Box<dyn Event> + Send + Sync>;
+type SignalFactory = Arc<dyn Fn(&ChainConfig) -> Box<dyn Signal> + Send + Sync>;
+type PatternFactory = Arc<dyn Fn(&ChainConfig) -> Pattern + Send + Sync>;
+
+/// Every detector the runner knows about, by name. Detectors are built per chain since most of
+/// them depend on the chain's addresses, [ChainConfig::detectors] picks which ones run.
+///
+/// Detectors from other crates are added with [DetectorRegistry::with_event],
+/// [DetectorRegistry::with_signal] and [DetectorRegistry::with_pattern], nothing here needs to
+/// change.
+#[derive(Clone, Default)]
+pub struct DetectorRegistry {
+    events: Vec<(String, EventFactory)>,
+    signals: Vec<(String, SignalFactory)>,
+    patterns: Vec<(String, PatternFactory)>,
+}
+
+impl DetectorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The detectors in this crate. Events that add to the [StateStore] come before the ones
+    /// reading it, see [Detectors::run].
+    pub fn builtin() -> Self {
+        Self::new()
+            .with_event("tornado_cash_withdraw", |chain| {
+                Box::new(TornadoCashWithdrawEvent::for_chain(&chain.addresses))
+            })
+            .with_event("transfer_from_fixed_float", |chain| {
+                Box::new(
+                    TransferFromFixedFloatEvent::for_chain(&chain.addresses).with_min_value(chain.min_transfer_value),
+                )
+            })
+            .with_event("suspicious_contract_created", |_| Box::new(SuspiciousContractCreatedEvent))
+            .with_event("suspicious_contract_called", |_| Box::new(SuspiciousContractCalledEvent))
+            .with_pattern("anonymously_funded_smart_contract_triggered", |_| {
+                AnonymouslyFundedSmartContractTriggeredSignal::pattern()
+            })
+    }
+
+    /// Replaces any event or signal already registered under `name`.
+    pub fn with_event(
+        mut self,
+        name: &str,
+        factory: impl Fn(&ChainConfig) -> Box<dyn Event> + Send + Sync + 'static,
+    ) -> Self {
+        self.remove(name);
+        self.events.push((name.to_string(), Arc::new(factory)));
+        self
+    }
+
+    /// Replaces any event or signal already registered under `name`.
+    pub fn with_signal(
+        mut self,
+        name: &str,
+        factory: impl Fn(&ChainConfig) -> Box<dyn Signal> + Send + Sync + 'static,
+    ) -> Self {
+        self.remove(name);
+        self.signals.push((name.to_string(), Arc::new(factory)));
+        self
+    }
+
+    /// Replaces any detector already registered under `name`.
+    pub fn with_pattern(
+        mut self,
+        name: &str,
+        factory: impl Fn(&ChainConfig) -> Pattern + Send + Sync + 'static,
+    ) -> Self {
+        self.remove(name);
+        self.patterns.push((name.to_string(), Arc::new(factory)));
+        self
+    }
+
+    /// Registers every rule as an event under its name. Funding rules go before the builtin
+    /// events so their recipients are in the state before anything reads it, and count as the
+    /// funding step of `anonymously_funded_smart_contract_triggered` if it is registered.
+    ///
+    /// A rule named like a detector that is already registered is an error unless it says
+    /// `override = true`, so a typo can't quietly replace a builtin.
+    pub fn with_rules(mut self, rules: &RuleSet) -> Result<Self, RegistryError> {
+        let mut funding = Vec::new();
+        for rule in rules.rules() {
+            if !rule.overrides() && self.names().any(|name| name == rule.name()) {
+                return Err(RegistryError::NameTaken(rule.name().to_string()));
+            }
+            self.remove(rule.name());
+            let event = rule.clone();
+            let factory: EventFactory = Arc::new(move |_| Box::new(event.clone()));
+            if rule.is_funding() {
+                self.events.insert(funding.len(), (rule.name().to_string(), factory));
+                funding.push(rule.id());
+            } else {
+                self.events.push((rule.name().to_string(), factory));
+            }
+        }
+        let pattern = "anonymously_funded_smart_contract_triggered";
+        if !funding.is_empty() && self.patterns.iter().any(|(name, _)| name == pattern) {
+            self = self.with_pattern(pattern, move |_| {
+                AnonymouslyFundedSmartContractTriggeredSignal::pattern_with_funding(&funding)
+            });
+        }
+        Ok(self)
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.events.retain(|(registered, _)| registered != name);
+        self.signals.retain(|(registered, _)| registered != name);
+        self.patterns.retain(|(registered, _)| registered != name);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        let events = self.events.iter().map(|(name, _)| name.as_str());
+        let signals = self.signals.iter().map(|(name, _)| name.as_str());
+        events.chain(signals).chain(self.patterns.iter().map(|(name, _)| name.as_str()))
+    }
+
+    /// Builds the chain's detectors, all of them unless [ChainConfig::detectors] is set.
+    ///
+    /// Every registered detector is checked for duplicate IDs, not just the enabled ones, so a
+    /// clash shows up at startup whatever the config.
+    pub fn build(&self, chain: &ChainConfig) -> Result<Detectors, RegistryError> {
+        if let Some(enabled) = &chain.detectors {
+            if let Some(unknown) = enabled.iter().find(|name| !self.names().any(|registered| registered == *name)) {
+                return Err(RegistryError::UnknownDetector(unknown.clone()));
+            }
+        }
+        let is_enabled = |name: &str| chain.detectors.as_ref().is_none_or(|enabled| enabled.iter().any(|e| e == name));
+
+        let events: Vec<(&str, Box<dyn Event>)> =
+            self.events.iter().map(|(name, factory)| (name.as_str(), factory(chain))).collect();
+        let signals: Vec<(&str, Box<dyn Signal>)> =
+            self.signals.iter().map(|(name, factory)| (name.as_str(), factory(chain))).collect();
+        let patterns: Vec<(&str, Pattern)> =
+            self.patterns.iter().map(|(name, factory)| (name.as_str(), factory(chain))).collect();
+
+        let mut ids: HashMap<u32, &str> = HashMap::new();
+        let metas = events
+            .iter()
+            .map(|(name, event)| (*name, event.meta()))
+            .chain(signals.iter().map(|(name, signal)| (*name, signal.meta())))
+            .chain(patterns.iter().map(|(name, pattern)| (*name, pattern.meta.clone())));
+        for (name, meta) in metas {
+            if let Some(first) = ids.insert(meta.id, name) {
+                return Err(RegistryError::DuplicateId {
+                    id: meta.id,
+                    first: first.to_string(),
+                    second: name.to_string(),
+                });
+            }
+        }
+
+        Ok(Detectors {
+            events: events.into_iter().filter(|(name, _)| is_enabled(name)).map(|(_, event)| event).collect(),
+            signals: signals.into_iter().filter(|(name, _)| is_enabled(name)).map(|(_, signal)| signal).collect(),
+            patterns: patterns.into_iter().filter(|(name, _)| is_enabled(name)).map(|(_, pattern)| pattern).collect(),
+        })
+    }
+}
+
+/// The detectors running on one chain.
+#[derive(Default)]
+pub struct Detectors {
+    pub events: Vec<Box<dyn Event>>,
+    pub signals: Vec<Box<dyn Signal>>,
+    pub patterns: Vec<Pattern>,
+}
+
+impl Detectors {
+    /// Runs every detector on the block, in registration order, events first, then patterns over
+    /// all of the block's event matches, then signals.
+    ///
+    /// Each event's matches go into `state` before the next detector runs, so within a block an
+    /// event sees what the events before it added and signals see everything. The order is that
+    /// of the detectors, not the transactions: a contract created earlier in the block than its
+    /// creator's funding is still caught.
+    pub async fn run(
+        &self,
+        chain_id: u64,
+        block: &Block,
+        api: &(dyn EthJsonRpc + Sync),
+        state: &mut StateStore,
+        sink: &mut (dyn Sink + Send),
+    ) {
+        let mut block_matches: Vec<Match> = Vec::new();
+        for event in &self.events {
+            let mut matches: Vec<Match> = Vec::new();
+            let ctx = BlockContext::new(chain_id, block, api)
+                .with_suspicious_addresses(state.suspicious_addresses())
+                .with_suspicious_contracts(state.suspicious_contracts());
+            event.event(&ctx, &mut matches).await;
+            for (id, value) in matches {
+                for update in event.state_updates(&value) {
+                    state.apply(block.hash(), update);
+                }
+                sink.push(id, value.clone());
+                block_matches.push((id, value));
+            }
+        }
+        for pattern in &self.patterns {
+            state.correlate(pattern, block, &block_matches, sink);
+        }
+        let ctx = BlockContext::new(chain_id, block, api)
+            .with_suspicious_addresses(state.suspicious_addresses())
+            .with_suspicious_contracts(state.suspicious_contracts());
+        for signal in &self.signals {
+            signal.signal(&ctx, sink).await;
+        }
+    }
+
+    pub fn metas(&self) -> Vec<DetectorMeta> {
+        let events = self.events.iter().map(|event| event.meta());
+        let signals = self.signals.iter().map(|signal| signal.meta());
+        events.chain(signals).chain(self.patterns.iter().map(|pattern| pattern.meta.clone())).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    DuplicateId { id: u32, first: String, second: String },
+    UnknownDetector(String),
+    /// A rule without `override = true` named like a registered detector
+    NameTaken(String),
+}
+
+impl std::error::Error for RegistryError {}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::DuplicateId { id, first, second } => {
+                write!(f, "Detectors {} and {} both use id {}", first, second, id)
+            }
+            RegistryError::UnknownDetector(name) => write!(f, "Unknown detector {}", name),
+            RegistryError::NameTaken(name) => {
+                write!(f, "Rule {} is named like a registered detector, set `override = true` to replace it", name)
+            }
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::{borrow::Cow, collections::HashSet, env, fs, io, path::Path};
+
+use alloy_primitives::{hex, Address, Selector, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    address::CanonicalAddress,
+    expr::{Expr, Scope},
+    model::{Log, Receipt, Transaction},
+    sink::Sink,
+    state::StateUpdate,
+    types::{payload_address, BlockContext, DetectorMeta, Event, Input},
+    units::{self, format_ether, parse_ether},
+};
+
+/// `Transfer(address,address,uint256)`, ERC-721 has the same topic with the id indexed too.
+pub const TRANSFER_TOPIC: B256 = B256::new(hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"));
+
+fn default_version() -> u32 {
+    1
+}
+
+/// A rules file is a list of `[[rules]]` tables, see docs/rules.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+/// One rule as written. Every predicate given has to hold for a transaction to match, a rule
+/// needs at least one. Unknown keys are an error so a typo doesn't quietly widen a rule.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub name: String,
+    pub id: u32,
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Chain ids the rule runs on, every chain if empty
+    #[serde(default)]
+    pub chains: Vec<u64>,
+    /// Recipients count as funded from an anon source, like FixedFloat or Tornado recipients
+    #[serde(default)]
+    pub funding: bool,
+    #[serde(default)]
+    pub from: Vec<CanonicalAddress>,
+    #[serde(default)]
+    pub to: Vec<CanonicalAddress>,
+    /// Native token, decimal e.g. `"0.5"`, inclusive
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+    /// 4 byte selectors, e.g. `"0xa9059cbb"`
+    #[serde(default)]
+    pub methods: Vec<Selector>,
+    pub contract_creation: Option<bool>,
+    pub log: Option<LogConfig>,
+    pub token_transfer: Option<TokenTransferConfig>,
+    /// Expression that has to be true for the transaction, see docs/expressions. Checked last as
+    /// it usually makes RPC calls
+    pub condition: Option<String>,
+    /// Replaces the detector already registered under `name`, without it that's an error
+    #[serde(default, rename = "override")]
+    pub overrides: bool,
+}
+
+/// Some log in the receipt has to match.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    #[serde(default)]
+    pub address: Vec<CanonicalAddress>,
+    /// Alternatives per position, an empty list matches anything there
+    #[serde(default)]
+    pub topics: Vec<Vec<B256>>,
+}
+
+/// Some ERC-20 `Transfer` in the receipt has to match.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenTransferConfig {
+    #[serde(default)]
+    pub token: Vec<CanonicalAddress>,
+    #[serde(default)]
+    pub from: Vec<CanonicalAddress>,
+    #[serde(default)]
+    pub to: Vec<CanonicalAddress>,
+    /// Raw token units as a decimal string, decimals differ per token
+    pub min_amount: Option<String>,
+}
+
+/// ERC-20 transfer decoded from a log.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TokenTransfer {
+    pub token: CanonicalAddress,
+    pub from: CanonicalAddress,
+    pub to: CanonicalAddress,
+    #[serde(with = "units::wei")]
+    pub amount: U256,
+}
+
+impl TokenTransfer {
+    pub fn decode(log: &Log) -> Option<Self> {
+        if log.topics.len() != 3 || log.topics[0] != TRANSFER_TOPIC || log.data.len() != 32 {
+            return None;
+        }
+        Some(Self {
+            token: log.address.into(),
+            from: Address::from_word(log.topics[1]).into(),
+            to: Address::from_word(log.topics[2]).into(),
+            amount: U256::from_be_slice(&log.data),
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RuleMatchJson {
+    rule: String,
+    from: CanonicalAddress,
+    to: Option<CanonicalAddress>,
+    /// Receiver of the first matching token transfer, else `to`, else the created contract
+    recipient: Option<CanonicalAddress>,
+    contract_address: Option<CanonicalAddress>,
+    #[serde(with = "units::wei")]
+    value: U256,
+    value_eth: String,
+    method: Option<Selector>,
+    token_transfers: Vec<TokenTransfer>,
+    block_timestamp: u64,
+    block: u64,
+    transaction_hash: String,
+}
+
+/// A validated [RuleConfig], registered as an [Event] by
+/// [crate::registry::DetectorRegistry::with_rules].
+#[derive(Clone, Debug)]
+pub struct Rule {
+    config: RuleConfig,
+    from: HashSet<CanonicalAddress>,
+    to: HashSet<CanonicalAddress>,
+    min_value: Option<U256>,
+    max_value: Option<U256>,
+    min_amount: Option<U256>,
+    condition: Option<Expr>,
+}
+
+impl Rule {
+    const OUTPUT: &'static [&'static str] = &[
+        "rule",
+        "from",
+        "to",
+        "recipient",
+        "contract_address",
+        "value",
+        "value_eth",
+        "method",
+        "token_transfers",
+        "block_timestamp",
+        "block",
+        "transaction_hash",
+    ];
+
+    pub fn new(config: RuleConfig) -> Result<Self, RuleError> {
+        let invalid = |reason: &str| RuleError::Invalid {
+            rule: config.name.clone(),
+            reason: reason.to_string(),
+        };
+        if config.name.is_empty()
+            || !config.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(invalid("name has to be lowercase letters, digits and _"));
+        }
+        let ether = |value: &Option<String>, key: &str| {
+            value
+                .as_deref()
+                .map(parse_ether)
+                .transpose()
+                .map_err(|_| invalid(&format!("{} is not a decimal amount", key)))
+        };
+        let min_value = ether(&config.min_value, "min_value")?;
+        let max_value = ether(&config.max_value, "max_value")?;
+        if let (Some(min), Some(max)) = (min_value, max_value) {
+            if min > max {
+                return Err(invalid("min_value is above max_value"));
+            }
+        }
+        if config.contract_creation == Some(true) && !config.to.is_empty() {
+            return Err(invalid("contract creations have no to address"));
+        }
+        if let Some(log) = &config.log {
+            if log.address.is_empty() && log.topics.iter().all(Vec::is_empty) {
+                return Err(invalid("log needs an address or a topic"));
+            }
+            if log.topics.len() > 4 {
+                return Err(invalid("logs have at most 4 topics"));
+            }
+        }
+        let min_amount = match config.token_transfer.as_ref().and_then(|t| t.min_amount.as_deref()) {
+            Some(amount) => Some(
+                U256::from_str_radix(amount, 10).map_err(|_| invalid("min_amount is not a whole number of token units"))?,
+            ),
+            None => None,
+        };
+        let condition = config
+            .condition
+            .as_deref()
+            .map(Expr::condition)
+            .transpose()
+            .map_err(|e| invalid(&format!("condition: {}", e)))?;
+        let has_predicate = !config.from.is_empty()
+            || !config.to.is_empty()
+            || min_value.is_some()
+            || max_value.is_some()
+            || !config.methods.is_empty()
+            || config.contract_creation.is_some()
+            || config.log.is_some()
+            || config.token_transfer.is_some()
+            || condition.is_some();
+        if !has_predicate {
+            return Err(invalid("no predicates, it would match every transaction"));
+        }
+
+        Ok(Self {
+            from: config.from.iter().copied().collect(),
+            to: config.to.iter().copied().collect(),
+            min_value,
+            max_value,
+            min_amount,
+            condition,
+            config,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn id(&self) -> u32 {
+        self.config.id
+    }
+
+    pub fn is_funding(&self) -> bool {
+        self.config.funding
+    }
+
+    pub fn overrides(&self) -> bool {
+        self.config.overrides
+    }
+
+    fn needs_receipts(&self) -> bool {
+        self.config.log.is_some() || self.config.token_transfer.is_some() || self.config.contract_creation == Some(true)
+    }
+
+    fn matches_transaction(&self, transaction: &Transaction) -> bool {
+        (self.from.is_empty() || self.from.contains(&transaction.from.into()))
+            && (self.to.is_empty() || transaction.to.is_some_and(|to| self.to.contains(&to.into())))
+            && self.min_value.is_none_or(|min| transaction.value >= min)
+            && self.max_value.is_none_or(|max| transaction.value <= max)
+            && (self.config.methods.is_empty()
+                || transaction.selector().is_some_and(|selector| self.config.methods.contains(&selector)))
+            && self.config.contract_creation.is_none_or(|creation| transaction.is_contract_creation() == creation)
+    }
+
+    fn matches_log(config: &LogConfig, log: &Log) -> bool {
+        let addresses: &[CanonicalAddress] = &config.address;
+        (addresses.is_empty() || addresses.contains(&log.address.into()))
+            && config.topics.iter().enumerate().all(|(i, alternatives)| {
+                alternatives.is_empty() || log.topics.get(i).is_some_and(|topic| alternatives.contains(topic))
+            })
+    }
+
+    fn matches_transfer(&self, config: &TokenTransferConfig, transfer: &TokenTransfer) -> bool {
+        (config.token.is_empty() || config.token.contains(&transfer.token))
+            && (config.from.is_empty() || config.from.contains(&transfer.from))
+            && (config.to.is_empty() || config.to.contains(&transfer.to))
+            && self.min_amount.is_none_or(|min| transfer.amount >= min)
+    }
+}
+
+#[async_trait::async_trait]
+impl Event for Rule {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: self.config.id,
+            name: Cow::Owned(self.config.name.clone()),
+            version: self.config.version,
+            inputs: if self.needs_receipts() || self.condition.is_some() {
+                &[Input::Block, Input::Api]
+            } else {
+                &[Input::Block]
+            },
+            output: Self::OUTPUT,
+        }
+    }
+
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        if !self.config.chains.is_empty() && !self.config.chains.contains(&ctx.chain_id) {
+            return;
+        }
+        let block = ctx.block;
+        let candidates: Vec<&Transaction> =
+            block.transactions.iter().filter(|transaction| self.matches_transaction(transaction)).collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        // Attached receipts save the round trip, otherwise one call for the block
+        let fetched: Vec<Receipt>;
+        let receipts: &[Receipt] = match block.receipts() {
+            _ if !self.needs_receipts() => &[],
+            Some(receipts) => receipts,
+            None => {
+                let Ok(receipts) = ctx.api.get_block_receipts(block).await else {
+                    return;
+                };
+                fetched = receipts;
+                &fetched
+            }
+        };
+
+        for transaction in candidates {
+            let receipt = receipts.iter().find(|receipt| receipt.transaction_hash == transaction.hash);
+            if self.needs_receipts() && receipt.is_none() {
+                continue;
+            }
+            let logs: &[Log] = receipt.map_or(&[], |receipt| &receipt.logs);
+            if let Some(log) = &self.config.log {
+                if !logs.iter().any(|l| Self::matches_log(log, l)) {
+                    continue;
+                }
+            }
+            let token_transfers: Vec<TokenTransfer> = match &self.config.token_transfer {
+                Some(config) => {
+                    let transfers: Vec<TokenTransfer> = logs
+                        .iter()
+                        .filter_map(TokenTransfer::decode)
+                        .filter(|transfer| self.matches_transfer(config, transfer))
+                        .collect();
+                    if transfers.is_empty() {
+                        continue;
+                    }
+                    transfers
+                }
+                None => Vec::new(),
+            };
+
+            if let Some(condition) = &self.condition {
+                // An error, e.g. a failed call, counts as not matching
+                let scope = Scope::from_context(ctx).with_transaction(transaction);
+                if !condition.test(&scope).await.unwrap_or(false) {
+                    continue;
+                }
+            }
+
+            let contract_address = receipt.and_then(|receipt| receipt.contract_address).map(CanonicalAddress::from);
+            let to = transaction.to.map(CanonicalAddress::from);
+            let json_resp = RuleMatchJson {
+                rule: self.config.name.clone(),
+                from: transaction.from.into(),
+                to,
+                recipient: token_transfers.first().map(|transfer| transfer.to).or(to).or(contract_address),
+                contract_address,
+                value: transaction.value,
+                value_eth: format_ether(transaction.value),
+                method: transaction.selector(),
+                token_transfers,
+                block_timestamp: block.timestamp(),
+                block: block.number(),
+                transaction_hash: transaction.hash.to_string(),
+            };
+            sink.push(self.config.id, serde_json::to_value(json_resp).unwrap());
+        }
+    }
+
+    fn state_updates(&self, payload: &serde_json::Value) -> Vec<StateUpdate> {
+        if !self.config.funding {
+            return Vec::new();
+        }
+        payload_address(payload, "recipient").map(StateUpdate::SuspiciousAddress).into_iter().collect()
+    }
+}
+
+/// Rules loaded from one or more files, names are unique across all of them.
+#[derive(Clone, Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses and validates one file's contents.
+    pub fn parse(toml: &str) -> Result<Self, RuleError> {
+        let file: RuleFile = toml::from_str(toml).map_err(|e| RuleError::Parse(e.to_string()))?;
+        let mut set = Self::new();
+        for config in file.rules {
+            set.add(Rule::new(config)?)?;
+        }
+        Ok(set)
+    }
+
+    /// A `.toml` file, or every `.toml` file in a directory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        let path = path.as_ref();
+        let io_error = |e: io::Error| RuleError::Io(path.display().to_string(), e);
+        if !path.is_dir() {
+            let toml = fs::read_to_string(path).map_err(io_error)?;
+            return Self::parse(&toml).map_err(|e| e.in_file(path));
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path).map_err(io_error)? {
+            let file = entry.map_err(io_error)?.path();
+            if file.extension().and_then(|e| e.to_str()) == Some("toml") {
+                files.push(file);
+            }
+        }
+        // Same registration order every run
+        files.sort();
+        let mut set = Self::new();
+        for file in files {
+            set.extend(Self::load(&file)?)?;
+        }
+        Ok(set)
+    }
+
+    /// `$RULES` is a comma separated list of files or directories, no rules without it.
+    pub fn from_env() -> Result<Self, RuleError> {
+        let mut set = Self::new();
+        if let Ok(paths) = env::var("RULES") {
+            for path in paths.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+                set.extend(Self::load(path)?)?;
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn extend(&mut self, other: RuleSet) -> Result<(), RuleError> {
+        for rule in other.rules {
+            self.add(rule)?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, rule: Rule) -> Result<(), RuleError> {
+        if self.rules.iter().any(|existing| existing.name() == rule.name()) {
+            return Err(RuleError::DuplicateName(rule.name().to_string()));
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    Io(String, io::Error),
+    /// Not TOML, or doesn't fit the format
+    Parse(String),
+    Invalid { rule: String, reason: String },
+    DuplicateName(String),
+    /// Any of the above, in a file
+    File(String, Box<RuleError>),
+}
+
+impl RuleError {
+    fn in_file(self, path: &Path) -> Self {
+        RuleError::File(path.display().to_string(), Box::new(self))
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::Io(path, err) => write!(f, "{}: {}", path, err),
+            RuleError::Parse(err) => write!(f, "{}", err),
+            RuleError::Invalid { rule, reason } => write!(f, "Rule {}: {}", rule, reason),
+            RuleError::DuplicateName(name) => write!(f, "Rule {} is defined twice", name),
+            RuleError::File(path, err) => write!(f, "{}: {}", path, err),
+        }
+    }
+}
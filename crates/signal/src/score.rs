@@ -0,0 +1,326 @@
+use std::{collections::BTreeMap, env, fs, io, path::Path};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    address::CanonicalAddress,
+    api::{BlockTag, EthJsonRpc},
+    model::Block,
+    sink::Match,
+    state::{StateStore, StateUpdate},
+    types::{payload_address, SuspiciousContractCreatedEvent, TornadoCashWithdrawEvent, TransferFromFixedFloatEvent},
+};
+
+/// What raises an entity's score, weighed by [ScoreConfig]. Each kind counts once per entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// A suspicious address, or a contract one of them created. Read from the [StateStore]'s
+    /// sets rather than recorded
+    AnonymousFunding,
+    TornadoOrigin,
+    FixedFloatOrigin,
+    /// Opcodes plain contracts rarely need in their deployed code, see [bytecode_red_flags]
+    BytecodeRedFlag,
+    /// A rule matched with the entity as its recipient
+    RuleHit,
+    /// Deployed by an account that had sent few transactions before
+    FreshDeployer,
+}
+
+impl FindingKind {
+    fn default_weight(self) -> u32 {
+        match self {
+            FindingKind::AnonymousFunding => 30,
+            FindingKind::TornadoOrigin => 20,
+            FindingKind::FixedFloatOrigin => 15,
+            FindingKind::BytecodeRedFlag => 20,
+            FindingKind::RuleHit => 10,
+            FindingKind::FreshDeployer => 15,
+        }
+    }
+}
+
+/// One reason an entity is suspicious, `detail` says what exactly for whoever reads the score.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub detail: String,
+}
+
+impl Finding {
+    pub fn new(kind: FindingKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A finding with the weight it added.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Reason {
+    pub kind: FindingKind,
+    pub weight: u32,
+    pub detail: String,
+}
+
+/// An entity's score at the time of a detection, heaviest reasons first.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Score {
+    pub entity: CanonicalAddress,
+    pub score: u32,
+    pub severity: Severity,
+    pub reasons: Vec<Reason>,
+}
+
+/// Weights and severity thresholds, see docs/scoring. Kinds missing from `weights` keep their
+/// default weight, 0 turns one off.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoreConfig {
+    pub weights: BTreeMap<FindingKind, u32>,
+    /// Lowest [Severity::High] score
+    pub high: u32,
+    /// Lowest [Severity::Medium] score
+    pub medium: u32,
+    /// Deployments with a lower creator nonce are [FindingKind::FreshDeployer]
+    pub fresh_deployer_nonce: u64,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        Self {
+            weights: BTreeMap::new(),
+            high: 60,
+            medium: 30,
+            fresh_deployer_nonce: 5,
+        }
+    }
+}
+
+impl ScoreConfig {
+    pub fn parse(toml: &str) -> Result<Self, ScoreError> {
+        let config: Self = toml::from_str(toml).map_err(|e| ScoreError::Parse(e.to_string()))?;
+        if config.medium > config.high {
+            return Err(ScoreError::Invalid(format!(
+                "medium ({}) is above high ({})",
+                config.medium, config.high
+            )));
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScoreError> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path).map_err(|e| ScoreError::Io(path.display().to_string(), e))?;
+        Self::parse(&toml).map_err(|e| e.in_file(path))
+    }
+
+    /// From the file in `$SCORING`, the defaults without it.
+    pub fn from_env() -> Result<Self, ScoreError> {
+        match env::var("SCORING") {
+            Ok(path) if !path.trim().is_empty() => Self::load(path.trim()),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn weight(&self, kind: FindingKind) -> u32 {
+        self.weights.get(&kind).copied().unwrap_or_else(|| kind.default_weight())
+    }
+
+    pub fn severity(&self, score: u32) -> Severity {
+        if score >= self.high {
+            Severity::High
+        } else if score >= self.medium {
+            Severity::Medium
+        } else {
+            Severity::Low
+        }
+    }
+
+    /// Records what the block's matches say about their entities in `state`, on behalf of the
+    /// block so a reorg takes it out again. Runs after [crate::registry::Detectors::run] so the
+    /// suspicious sets already have the block in them. `api` is asked for created contracts'
+    /// code.
+    pub async fn observe(
+        &self,
+        block: &Block,
+        matches: &[Match],
+        api: &(dyn EthJsonRpc + Sync),
+        state: &mut StateStore,
+    ) {
+        for (id, payload) in matches {
+            for (entity, finding) in self.findings(block, *id, payload, api, state).await {
+                state.apply(block.hash(), StateUpdate::Finding(entity, finding));
+            }
+        }
+    }
+
+    async fn findings(
+        &self,
+        block: &Block,
+        id: u32,
+        payload: &serde_json::Value,
+        api: &(dyn EthJsonRpc + Sync),
+        state: &StateStore,
+    ) -> Vec<(CanonicalAddress, Finding)> {
+        let mut findings = Vec::new();
+        match id {
+            TornadoCashWithdrawEvent::ID => {
+                if let Some(recipient) = payload_address(payload, "recipient") {
+                    let pool = payload["tornado_address_name"].as_str().unwrap_or("Unknown");
+                    let detail = format!("withdrew from Tornado Cash {}", pool);
+                    findings.push((recipient, Finding::new(FindingKind::TornadoOrigin, detail)));
+                }
+            }
+            TransferFromFixedFloatEvent::ID => {
+                if let Some(recipient) = payload_address(payload, "recipient") {
+                    let value = payload["value_eth"].as_str().unwrap_or("?");
+                    let detail = format!("received {} ETH from FixedFloat", value);
+                    findings.push((recipient, Finding::new(FindingKind::FixedFloatOrigin, detail)));
+                }
+            }
+            SuspiciousContractCreatedEvent::ID => {
+                let (Some(creator), Some(contract)) =
+                    (payload_address(payload, "creator"), payload_address(payload, "contract_address"))
+                else {
+                    return findings;
+                };
+                // Where the creator's money came from carries over to what it deploys
+                for finding in state.findings(&creator) {
+                    let detail = format!("creator {} {}", creator, finding.detail);
+                    findings.push((contract, Finding::new(finding.kind, detail)));
+                }
+                // The creation input is init code plus constructor args, whatever is in the args
+                // isn't code. What got deployed is, asked for at this block so it isn't gone yet
+                let code = api.get_code(contract.into(), BlockTag::Hash(block.hash())).await;
+                let flags = bytecode_red_flags(&code.unwrap_or_default());
+                if !flags.is_empty() {
+                    let detail = format!("uses {}", flags.join(", "));
+                    findings.push((contract, Finding::new(FindingKind::BytecodeRedFlag, detail)));
+                }
+                let hash = payload["transaction_hash"].as_str().and_then(|hash| hash.parse::<B256>().ok());
+                let transaction = block.transactions.iter().find(|transaction| Some(transaction.hash) == hash);
+                if let Some(nonce) = transaction.map(|transaction| transaction.nonce) {
+                    if nonce < self.fresh_deployer_nonce {
+                        let detail = format!("deployed by {} at nonce {}", creator, nonce);
+                        findings.push((contract, Finding::new(FindingKind::FreshDeployer, detail)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        // Rules put their name in the payload, builtin detectors don't have a `rule` field
+        if let (Some(rule), Some(recipient)) = (payload["rule"].as_str(), payload_address(payload, "recipient")) {
+            findings.push((recipient, Finding::new(FindingKind::RuleHit, format!("matched rule {}", rule))));
+        }
+        findings
+    }
+
+    /// Weighs up everything known about the entity so far.
+    pub fn score(&self, entity: CanonicalAddress, state: &StateStore) -> Score {
+        let mut findings = Vec::new();
+        if state.suspicious_addresses().contains(&entity) {
+            findings.push(Finding::new(FindingKind::AnonymousFunding, "funded from an anon source"));
+        } else if state.suspicious_contracts().contains(&entity) {
+            findings.push(Finding::new(FindingKind::AnonymousFunding, "created by an anonymously funded account"));
+        }
+        findings.extend(state.findings(&entity).iter().cloned());
+
+        let mut reasons: Vec<Reason> = findings
+            .into_iter()
+            .map(|finding| Reason {
+                kind: finding.kind,
+                weight: self.weight(finding.kind),
+                detail: finding.detail,
+            })
+            .filter(|reason| reason.weight > 0)
+            .collect();
+        reasons.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.kind.cmp(&b.kind)));
+        let score = reasons.iter().fold(0u32, |score, reason| score.saturating_add(reason.weight));
+        Score {
+            entity,
+            score,
+            severity: self.severity(score),
+            reasons,
+        }
+    }
+
+    /// Score of what a match is about: the contract for the contract detectors and the pattern,
+    /// the recipient for funding events and rules. `None` if the payload has neither.
+    pub fn score_match(&self, payload: &serde_json::Value, state: &StateStore) -> Option<Score> {
+        ["contract", "contract_address", "recipient"]
+            .iter()
+            .find_map(|field| payload_address(payload, field))
+            .map(|entity| self.score(entity, state))
+    }
+}
+
+/// `ORIGIN`, `CALLCODE`, `DELEGATECALL` and `SELFDESTRUCT` in the code, by name. Push data is
+/// skipped, the metadata at the end of the code isn't told apart so a flag can come from there.
+pub fn bytecode_red_flags(code: &[u8]) -> Vec<&'static str> {
+    const FLAGS: [(u8, &str); 4] = [(0x32, "tx.origin"), (0xf2, "callcode"), (0xf4, "delegatecall"), (0xff, "selfdestruct")];
+    let mut seen = [false; FLAGS.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        if let Some(i) = FLAGS.iter().position(|(flag, _)| *flag == op) {
+            seen[i] = true;
+        }
+        // PUSH1 to PUSH32 are followed by their data
+        pc += match op {
+            0x60..=0x7f => (op - 0x5f) as usize + 1,
+            _ => 1,
+        };
+    }
+    FLAGS.iter().zip(seen).filter(|(_, seen)| *seen).map(|((_, name), _)| *name).collect()
+}
+
+#[derive(Debug)]
+pub enum ScoreError {
+    Io(String, io::Error),
+    /// Not TOML, or doesn't fit the format
+    Parse(String),
+    Invalid(String),
+    /// Any of the above, in a file
+    File(String, Box<ScoreError>),
+}
+
+impl ScoreError {
+    fn in_file(self, path: &Path) -> Self {
+        ScoreError::File(path.display().to_string(), Box::new(self))
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+impl std::fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreError::Io(path, err) => write!(f, "{}: {}", path, err),
+            ScoreError::Parse(err) => write!(f, "{}", err),
+            ScoreError::Invalid(reason) => write!(f, "Scoring: {}", reason),
+            ScoreError::File(path, err) => write!(f, "{}: {}", path, err),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use tokio::sync::mpsc::UnboundedSender;
+
+/// One match from an [crate::types::Event] or [crate::types::Signal], its ID and payload.
+pub type Match = (u32, serde_json::Value);
+
+/// Where events and signals write their matches, every match in a block is pushed rather than
+/// just the first one.
+///
+/// Implementations decide what happens next: keep them (`Vec`), forward them to another task
+/// (channels) or collect and hand them over in batches ([BatchSink]).
+pub trait Sink {
+    fn push(&mut self, id: u32, value: serde_json::Value);
+}
+
+impl Sink for Vec<Match> {
+    fn push(&mut self, id: u32, value: serde_json::Value) {
+        Vec::push(self, (id, value));
+    }
+}
+
+/// Forwards to whatever is receiving, e.g. a task writing to the DB. Matches are dropped once the
+/// receiver is gone. There's no bounded version, a full channel would have to either block the
+/// block loop or lose matches.
+impl Sink for UnboundedSender<Match> {
+    fn push(&mut self, id: u32, value: serde_json::Value) {
+        let _ = self.send((id, value));
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn push(&mut self, id: u32, value: serde_json::Value) {
+        (**self).push(id, value);
+    }
+}
+
+/// Collects matches and passes them to `flush` `size` at a time, whatever is left when the sink
+/// is dropped is flushed then.
+pub struct BatchSink<F: FnMut(Vec<Match>)> {
+    size: usize,
+    buffer: Vec<Match>,
+    flush: F,
+}
+
+impl<F: FnMut(Vec<Match>)> BatchSink<F> {
+    pub fn new(size: usize, flush: F) -> Self {
+        Self {
+            size: size.max(1),
+            buffer: Vec::new(),
+            flush,
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            (self.flush)(std::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+impl<F: FnMut(Vec<Match>)> Sink for BatchSink<F> {
+    fn push(&mut self, id: u32, value: serde_json::Value) {
+        self.buffer.push((id, value));
+        if self.buffer.len() >= self.size {
+            self.flush();
+        }
+    }
+}
+
+impl<F: FnMut(Vec<Match>)> Drop for BatchSink<F> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    address::CanonicalAddress,
+    correlation::{self, Pattern, PartialMatch},
+    model::Block,
+    score::Finding,
+    sink::{Match, Sink},
+};
+
+/// What an [crate::types::Event] match adds to the [StateStore].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StateUpdate {
+    /// Account funded from an anon source
+    SuspiciousAddress(CanonicalAddress),
+    /// Contract created by a suspicious address
+    SuspiciousContract(CanonicalAddress),
+    /// Something [crate::score::ScoreConfig] counts against the address
+    Finding(CanonicalAddress, Finding),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct State {
+    suspicious_addresses: HashSet<CanonicalAddress>,
+    suspicious_contracts: HashSet<CanonicalAddress>,
+    /// By pattern id
+    #[serde(default)]
+    partials: HashMap<u32, Vec<PartialMatch>>,
+    /// At most one per kind
+    #[serde(default)]
+    findings: HashMap<CanonicalAddress, Vec<Finding>>,
+}
+
+/// Addresses flagged by events, read back by later detectors through
+/// [crate::types::BlockContext], the partial matches of each [Pattern] and the findings scores
+/// are made of. One per chain.
+///
+/// Entries added by a block that isn't final yet are remembered against its hash and taken out
+/// again by [StateStore::revert] if the block is reorged out. With a path the sets are written
+/// there by [StateStore::save] and loaded again on restart, pending blocks aren't, the follower
+/// doesn't see reorgs of blocks from before a restart anyway.
+#[derive(Debug, Default)]
+pub struct StateStore {
+    path: Option<PathBuf>,
+    state: State,
+    pending: HashMap<B256, Vec<StateUpdate>>,
+    // Changed since the last save
+    dirty: bool,
+}
+
+impl StateStore {
+    /// In memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the sets saved at `path` by an earlier run, starts empty if there is no file yet.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let state = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: Some(path),
+            state,
+            pending: HashMap::new(),
+            dirty: false,
+        })
+    }
+
+    pub fn suspicious_addresses(&self) -> &HashSet<CanonicalAddress> {
+        &self.state.suspicious_addresses
+    }
+
+    pub fn suspicious_contracts(&self) -> &HashSet<CanonicalAddress> {
+        &self.state.suspicious_contracts
+    }
+
+    pub fn partials(&self, pattern: u32) -> &[PartialMatch] {
+        self.state.partials.get(&pattern).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn findings(&self, address: &CanonicalAddress) -> &[Finding] {
+        self.state.findings.get(address).map_or(&[], Vec::as_slice)
+    }
+
+    /// Runs the pattern over the block's event matches, see [Pattern::process].
+    pub fn correlate(&mut self, pattern: &Pattern, block: &Block, matches: &[Match], sink: &mut (dyn Sink + Send)) {
+        let partials = self.state.partials.entry(pattern.meta.id).or_default();
+        self.dirty |= pattern.process(partials, block, matches, sink);
+    }
+
+    /// Adds the entry on behalf of `block_hash`, false if it was already there.
+    pub fn apply(&mut self, block_hash: B256, update: StateUpdate) -> bool {
+        let added = match update {
+            StateUpdate::SuspiciousAddress(address) => self.state.suspicious_addresses.insert(address),
+            StateUpdate::SuspiciousContract(address) => self.state.suspicious_contracts.insert(address),
+            StateUpdate::Finding(address, ref finding) => {
+                let findings = self.state.findings.entry(address).or_default();
+                let new = !findings.iter().any(|existing| existing.kind == finding.kind);
+                if new {
+                    findings.push(finding.clone());
+                }
+                new
+            }
+        };
+        if added {
+            self.dirty = true;
+            self.pending.entry(block_hash).or_default().push(update);
+        }
+        added
+    }
+
+    /// Removes what the block added, partial matches included, true if that was anything.
+    pub fn revert(&mut self, block_hash: B256) -> bool {
+        let updates = self.pending.remove(&block_hash).unwrap_or_default();
+        for update in &updates {
+            match update {
+                StateUpdate::SuspiciousAddress(address) => self.state.suspicious_addresses.remove(address),
+                StateUpdate::SuspiciousContract(address) => self.state.suspicious_contracts.remove(address),
+                StateUpdate::Finding(address, finding) => {
+                    if let Some(findings) = self.state.findings.get_mut(address) {
+                        findings.retain(|existing| existing != finding);
+                        if findings.is_empty() {
+                            self.state.findings.remove(address);
+                        }
+                    }
+                    true
+                }
+            };
+        }
+        let mut reverted = !updates.is_empty();
+        for partials in self.state.partials.values_mut() {
+            let before = partials.len();
+            partials.retain(|partial| partial.block_hash() != Some(block_hash));
+            reverted |= partials.len() != before;
+        }
+        self.dirty |= reverted;
+        reverted
+    }
+
+    /// The block can't be reorged out anymore, its entries stay. Also for blocks the follower
+    /// evicted without seeing them finalized, a reorg that deep wouldn't be noticed anyway.
+    /// Partials the block's replaced go now, see [Pattern].
+    pub fn finalize(&mut self, block_hash: B256) {
+        self.pending.remove(&block_hash);
+        for partials in self.state.partials.values_mut() {
+            self.dirty |= correlation::prune_replaced(partials, block_hash);
+        }
+    }
+
+    /// Blocks whose entries [StateStore::revert] could still take out.
+    pub fn pending_blocks(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Writes the sets to the path given to [StateStore::open] if they changed since the last
+    /// save, does nothing in memory only.
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.as_ref().filter(|_| self.dirty) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename so a crash mid-write doesn't lose the previous state
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(&self.state)?)?;
+        fs::rename(&tmp, path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
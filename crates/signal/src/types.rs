@@ -1,185 +1,388 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
+use alloy_primitives::{B256, U256};
 use serde::{Deserialize, Serialize};
 
-use crate::api::EthJsonRpc;
+use crate::address::CanonicalAddress;
+use crate::api::{EthJsonRpc, JsonRpcBatch};
+use crate::chain::ChainAddresses;
+use crate::correlation::{Pattern, Step};
+use crate::follower::ProcessingDepth;
+use crate::model::{Block, Receipt};
+use crate::score::Score;
+use crate::sink::{Match, Sink};
+use crate::state::StateUpdate;
+use crate::units::{self, format_ether};
+
+/// What a detector is, for the registry, the API and the docs.
+///
+/// `id` is what [Detection]s and DB rows carry so it has to be unique across every event and
+/// signal, the registry refuses to start otherwise. Bump `version` when the payload changes.
+/// `output` lists the payload fields.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DetectorMeta {
+    pub id: u32,
+    /// Rule detectors are named in their files, see [crate::rules]
+    pub name: Cow<'static, str>,
+    pub version: u32,
+    pub inputs: &'static [Input],
+    pub output: &'static [&'static str],
+}
+
+/// Parts of [BlockContext] a detector reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    Block,
+    Api,
+    SuspiciousAddresses,
+    SuspiciousContracts,
+    /// Matches from the block's events, for [Pattern]s
+    Events,
+}
 
-/// All signals should implement a signal method that returns (ID, serde_json::value). Should
-/// expect these values to be written somewhere.
-pub enum Signal {
-    AnonymouslyFundedSmartContractTriggered(AnonymouslyFundedSmartContractTriggeredSignal),
+/// Everything a detector gets for one block. New inputs go here rather than becoming another
+/// argument on every detector.
+pub struct BlockContext<'a> {
+    pub chain_id: u64,
+    pub block: &'a Block,
+    pub api: &'a (dyn EthJsonRpc + Sync),
+    /// Accounts primarily funded from anon sources
+    pub suspicious_addresses: &'a HashSet<CanonicalAddress>,
+    /// Contracts created by those accounts
+    pub suspicious_contracts: &'a HashSet<CanonicalAddress>,
 }
 
-impl Signal {
-    pub async fn signal(&self,block: &BlockJson, suspicious_contracts: &HashSet<String>) -> Option<(u32, serde_json::Value)> {
-        match self {
-            Signal::AnonymouslyFundedSmartContractTriggered(inner) => inner.signal(block, suspicious_contracts).await,
+impl<'a> BlockContext<'a> {
+    /// Empty suspicious sets, see the `with_` methods.
+    pub fn new(chain_id: u64, block: &'a Block, api: &'a (dyn EthJsonRpc + Sync)) -> Self {
+        static EMPTY: OnceLock<HashSet<CanonicalAddress>> = OnceLock::new();
+        let empty = EMPTY.get_or_init(HashSet::new);
+        Self {
+            chain_id,
+            block,
+            api,
+            suspicious_addresses: empty,
+            suspicious_contracts: empty,
         }
     }
+
+    pub fn with_suspicious_addresses(mut self, suspicious_addresses: &'a HashSet<CanonicalAddress>) -> Self {
+        self.suspicious_addresses = suspicious_addresses;
+        self
+    }
+
+    pub fn with_suspicious_contracts(mut self, suspicious_contracts: &'a HashSet<CanonicalAddress>) -> Self {
+        self.suspicious_contracts = suspicious_contracts;
+        self
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct AnonymouslyFundedSmartContractTriggeredJson {
-    contract_address: String,
-    transaction_hash: String,
-    block: u64,
+/// Address field of a match payload, if it has a valid one.
+pub(crate) fn payload_address(payload: &serde_json::Value, field: &str) -> Option<CanonicalAddress> {
+    CanonicalAddress::deserialize(&payload[field]).ok()
+}
+
+/// [Event]s are on-chain events that we wish to track for use with [Signal]s.
+///
+/// Should push (ID, serde_json::Value) to the [Sink] for every match in the block, expecting that
+/// these will be written for use in signal code later. Implement this and register it with
+/// [crate::registry::DetectorRegistry] to add one, it doesn't have to live in this crate.
+#[async_trait::async_trait]
+pub trait Event: Send + Sync {
+    fn meta(&self) -> DetectorMeta;
+
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send));
+
+    /// What one of this event's matches adds to the [crate::state::StateStore], nothing by default.
+    fn state_updates(&self, _payload: &serde_json::Value) -> Vec<StateUpdate> {
+        Vec::new()
+    }
+}
+
+/// Same as [Event], signals are what we alert on.
+#[async_trait::async_trait]
+pub trait Signal: Send + Sync {
+    fn meta(&self) -> DetectorMeta;
+
+    async fn signal(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send));
 }
 
 /// We store lists of smart contract addresses that have been created by accounts that are
 /// primarily funded using anon sources i.e. Tornado Cash. When these smart contracts are triggered
 /// we create a signal.
+///
+/// Declared as a [Pattern]: funding, then the funded account creating a contract, then a call to
+/// that contract, however long after. Registering it again with [Pattern::within] gives it a
+/// window.
 pub struct AnonymouslyFundedSmartContractTriggeredSignal;
 
 impl AnonymouslyFundedSmartContractTriggeredSignal {
     pub const ID: u32 = 0;
 
-    pub async fn signal(
-        &self,
-        block: &BlockJson,
-        suspicious_contracts: &HashSet<String>,
-    ) -> Option<(u32, serde_json::Value)> {
-        for transaction in block.get_transactions() {
-            if let Some(to) = transaction.to() {
-                if suspicious_contracts.contains(to) {
-                    let json_resp = AnonymouslyFundedSmartContractTriggeredJson {
-                        contract_address: to.to_string(),
-                        transaction_hash: transaction.hash().unwrap_or("").to_string(),
-                        block: block.number(),
-                    };
-                    return Some((Self::ID, serde_json::to_value(json_resp).unwrap()));
-                }
-            }
-        }
-        None
+    pub fn pattern() -> Pattern {
+        Self::pattern_with_funding(&[])
+    }
+
+    /// Same with more events counting as anon funding, e.g. [crate::rules] marked `funding`.
+    /// They need a `recipient` field.
+    pub fn pattern_with_funding(funding: &[u32]) -> Pattern {
+        let mut funding_events = vec![TornadoCashWithdrawEvent::ID, TransferFromFixedFloatEvent::ID];
+        funding_events.extend_from_slice(funding);
+        let meta = DetectorMeta {
+            id: Self::ID,
+            name: "anonymously_funded_smart_contract_triggered".into(),
+            version: 2,
+            inputs: &[Input::Events],
+            output: &[
+                "contract_address",
+                "caller",
+                "transaction_hash",
+                "block",
+                "block_timestamp",
+                "value",
+                "value_eth",
+                "account",
+                "contract",
+                "sequence",
+            ],
+        };
+        Pattern::new(meta)
+            .then(Step::new(&funding_events).with_key("account", "recipient"))
+            .then(
+                Step::new(&[SuspiciousContractCreatedEvent::ID])
+                    .with_key("account", "creator")
+                    .with_key("contract", "contract_address"),
+            )
+            .then(Step::new(&[SuspiciousContractCalledEvent::ID]).with_key("contract", "contract_address"))
     }
 }
 
-/// [Event]s are on-chain events that we wish to track for use with [crate::signal::Signal]s.
-///
-/// Should implement an event method that returns (i32, serde_json::Value) expecting that this
-/// value will be written for use in signal code later.
-///
-/// One option that should be considered here is to pass a writer/sink to event method because
-/// blocks will have more than one transaction that we want to check.
-pub enum Event {
-    TornadoCashWithdraw(TornadoCashWithdrawEvent),
-    TransferFromFixedFloat(TransferFromFixedFloatEvent),
-    SuspiciousContractCreated(SuspiciousContractCreatedEvent),
+/// Output of an [Event] or [Signal] as the runner reports it, for the chain the block is on.
+/// `depth` is the depth the block was
+/// processed at, early results are raised to [ProcessingDepth::Finalized] once their block
+/// finalizes so consumers can tell an early alert from a settled one. `score` is that of the
+/// match's contract or recipient when it was made, see [crate::score::ScoreConfig::score_match].
+#[derive(Clone, Debug, Serialize)]
+pub struct Detection {
+    pub chain_id: u64,
+    pub id: u32,
+    pub value: serde_json::Value,
+    pub block: u64,
+    pub block_hash: B256,
+    pub depth: ProcessingDepth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
 }
 
-impl Event {
-    pub async fn event(&self, api: &impl EthJsonRpc, block: &BlockJson, suspicious_addresses: &HashSet<String>) -> Option<(u32, serde_json::Value)> {
-        match self {
-            Event::TornadoCashWithdraw(inner) => inner.event(block).await,
-            Event::TransferFromFixedFloat(inner) => inner.event(block).await,
-            Event::SuspiciousContractCreated(inner) => inner.event(block, api, suspicious_addresses).await
+impl Detection {
+    pub fn new(
+        chain_id: u64,
+        (id, value): Match,
+        block: &Block,
+        depth: ProcessingDepth,
+    ) -> Self {
+        Self {
+            chain_id,
+            id,
+            value,
+            block: block.number(),
+            block_hash: block.hash(),
+            depth,
+            score: None,
         }
     }
+
+    pub fn with_score(mut self, score: Option<Score>) -> Self {
+        self.score = score;
+        self
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.depth == ProcessingDepth::Finalized
+    }
+
+    pub fn finalize(&mut self) {
+        self.depth = ProcessingDepth::Finalized;
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct TornadoCashWithdrawEventJson {
-    tornado_address: String,
-    recipient: String,
-    relayer: String,
+    tornado_address: CanonicalAddress,
+    recipient: CanonicalAddress,
+    relayer: CanonicalAddress,
     tornado_address_name: String,
     block_timestamp: u64,
     block: u64,
     transaction_hash: String,
 }
 
-pub struct TornadoCashWithdrawEvent;
+/// Pool names come from the chain's [ChainAddresses], `Default` is mainnet.
+pub struct TornadoCashWithdrawEvent {
+    pools: HashMap<CanonicalAddress, String>,
+}
+
+impl Default for TornadoCashWithdrawEvent {
+    fn default() -> Self {
+        Self::for_chain(&ChainAddresses::mainnet())
+    }
+}
 
 impl TornadoCashWithdrawEvent {
-    const ID: u32 = 1;
-
-    const TCETH01: &str = "0x12D66f87A04A9E220743712cE6d9bB1B5616B8Fc";
-    const TCETH1: &str = "0x47CE0C6eD5B0Ce3d3A51fdb1C52DC66a7c3c2936";
-
-    fn decode_transaction(input: &str) -> Option<(String, String, String)> {
-        let hex = alloy_primitives::hex::decode(input).unwrap();
-        if let Ok(tc) = crate::sol::TornadoCashSol::decode(&hex) {
-            return Some((
-                tc._tornado.to_string(),
-                tc._recipient.to_string(),
-                tc._relayer.to_string(),
-            ));
+    pub const ID: u32 = 1;
+
+    pub fn for_chain(addresses: &ChainAddresses) -> Self {
+        Self {
+            pools: addresses.tornado_pools.clone(),
+        }
+    }
+
+    fn decode_transaction(input: &[u8]) -> Option<(CanonicalAddress, CanonicalAddress, CanonicalAddress)> {
+        if let Ok(tc) = crate::sol::TornadoCashSol::decode(input) {
+            return Some((tc._tornado.into(), tc._recipient.into(), tc._relayer.into()));
         }
         None
     }
+}
 
-    pub async fn event(&self, block: &BlockJson) -> Option<(u32, serde_json::Value)> {
-        for transaction in block.get_transactions() {
-            if let Some(input) = transaction.input() {
-                if let Some(withdraw) = TornadoCashWithdrawEvent::decode_transaction(input) {
-                    // This will only find one signal per block, in practice we would probably write
-                    // signals to a queue or pass a writer in to every signa
-                    let mut tornado_address_name = "Unknown".to_string();
-                    if withdraw.0.eq(Self::TCETH01) {
-                        tornado_address_name = "ETH/0.1".to_string();
-                    } else if withdraw.0.eq(Self::TCETH1) {
-                        tornado_address_name = "ETH/1".to_string();
-                    }
-                    let json_resp = TornadoCashWithdrawEventJson {
-                        tornado_address: withdraw.0,
-                        recipient: withdraw.1,
-                        relayer: withdraw.2,
-                        tornado_address_name,
-                        block: block.number(),
-                        block_timestamp: block.timestamp(),
-                        transaction_hash: transaction.hash().unwrap_or("").to_string(),
-                    };
+#[async_trait::async_trait]
+impl Event for TornadoCashWithdrawEvent {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: Self::ID,
+            name: "tornado_cash_withdraw".into(),
+            version: 1,
+            inputs: &[Input::Block],
+            output: &[
+                "tornado_address",
+                "recipient",
+                "relayer",
+                "tornado_address_name",
+                "block_timestamp",
+                "block",
+                "transaction_hash",
+            ],
+        }
+    }
 
-                    // Unwrap should only fail when code here is wrong, so we need to exit
-                    return Some((Self::ID, serde_json::to_value(json_resp).unwrap()));
-                }
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        let block = ctx.block;
+        for transaction in &block.transactions {
+            if let Some(withdraw) = TornadoCashWithdrawEvent::decode_transaction(&transaction.input) {
+                let tornado_address_name = self
+                    .pools
+                    .get(&withdraw.0)
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown".to_string());
+                let json_resp = TornadoCashWithdrawEventJson {
+                    tornado_address: withdraw.0,
+                    recipient: withdraw.1,
+                    relayer: withdraw.2,
+                    tornado_address_name,
+                    block: block.number(),
+                    block_timestamp: block.timestamp(),
+                    transaction_hash: transaction.hash.to_string(),
+                };
+
+                // Unwrap should only fail when code here is wrong, so we need to exit
+                sink.push(Self::ID, serde_json::to_value(json_resp).unwrap());
             }
         }
-        None
+    }
+
+    /// Recipients count as funded from an anon source.
+    fn state_updates(&self, payload: &serde_json::Value) -> Vec<StateUpdate> {
+        payload_address(payload, "recipient").map(StateUpdate::SuspiciousAddress).into_iter().collect()
     }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct TransferFromFixedFloatJson {
-    pub recipient: String,
-    pub value: u64,
+    pub recipient: CanonicalAddress,
+    /// Wei, a decimal string in the payload
+    #[serde(with = "units::wei")]
+    pub value: U256,
+    #[serde(default)]
+    pub value_eth: String,
     pub block_timestamp: u64,
     pub block: u64,
     pub transaction_hash: String,
 }
 
-pub struct TransferFromFixedFloatEvent;
+/// Wallets come from the chain's [ChainAddresses], `Default` is mainnet. Transfers below
+/// `min_value` wei are ignored, by default every transfer is reported.
+pub struct TransferFromFixedFloatEvent {
+    addresses: HashSet<CanonicalAddress>,
+    min_value: U256,
+}
+
+impl Default for TransferFromFixedFloatEvent {
+    fn default() -> Self {
+        Self::for_chain(&ChainAddresses::mainnet())
+    }
+}
 
 impl TransferFromFixedFloatEvent {
     pub const ID: u32 = 2;
-    const FIXED_FLOAT_ADDRESS: &str = "0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f";
-
-    pub async fn event(&self, block: &BlockJson) -> Option<(u32, serde_json::Value)> {
-        for transaction in block.get_transactions() {
-            if let Some(from_address) = transaction.from() {
-                if from_address.eq(Self::FIXED_FLOAT_ADDRESS) {
-                    if let Some(to_address) = transaction.to() {
-                        let json_resp = TransferFromFixedFloatJson {
-                            recipient: to_address.to_string(),
-                            block: block.number(),
-                            value: transaction.value().unwrap_or(0),
-                            block_timestamp: block.timestamp(),
-                            transaction_hash: transaction.hash().unwrap_or("").to_string(),
-                        };
-                        return Some((Self::ID, serde_json::to_value(json_resp).unwrap()));
-                    }
+
+    pub fn for_chain(addresses: &ChainAddresses) -> Self {
+        Self {
+            addresses: addresses.fixed_float.clone(),
+            min_value: U256::ZERO,
+        }
+    }
+
+    pub fn with_min_value(mut self, min_value: U256) -> Self {
+        self.min_value = min_value;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Event for TransferFromFixedFloatEvent {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: Self::ID,
+            name: "transfer_from_fixed_float".into(),
+            version: 1,
+            inputs: &[Input::Block],
+            output: &["recipient", "value", "value_eth", "block_timestamp", "block", "transaction_hash"],
+        }
+    }
+
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        let block = ctx.block;
+        for transaction in &block.transactions {
+            if transaction.value >= self.min_value && self.addresses.contains(&transaction.from.into()) {
+                if let Some(to_address) = transaction.to {
+                    let json_resp = TransferFromFixedFloatJson {
+                        recipient: to_address.into(),
+                        block: block.number(),
+                        value: transaction.value,
+                        value_eth: format_ether(transaction.value),
+                        block_timestamp: block.timestamp(),
+                        transaction_hash: transaction.hash.to_string(),
+                    };
+                    sink.push(Self::ID, serde_json::to_value(json_resp).unwrap());
                 }
             }
         }
-        None
+    }
+
+    /// Recipients count as funded from an anon source.
+    fn state_updates(&self, payload: &serde_json::Value) -> Vec<StateUpdate> {
+        payload_address(payload, "recipient").map(StateUpdate::SuspiciousAddress).into_iter().collect()
     }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct SuspiciousContractCreatedJson {
-    creator: String,
+    creator: CanonicalAddress,
     contract_code: String,
-    contract_address: String,
+    contract_address: CanonicalAddress,
     block_timestamp: u64,
     block: u64,
     transaction_hash: String,
@@ -191,141 +394,124 @@ pub struct SuspiciousContractCreatedJson {
 pub struct SuspiciousContractCreatedEvent;
 
 impl SuspiciousContractCreatedEvent {
-    const ID: u32 = 3;
-
-    pub async fn event(
-        &self,
-        block: &BlockJson,
-        api: &impl EthJsonRpc,
-        suspicious_addresses: &HashSet<String>,
-    ) -> Option<(u32, serde_json::Value)> {
-        for transaction in block.get_transactions() {
-            if let Some(from_address) = transaction.from() {
-                // Possible bug with is_contract_creation, not 100% sure whether this check is correct
-                if suspicious_addresses.contains(from_address) && transaction.is_contract_creation() {
-                    if let Some(hash) = transaction.hash() {
-                        if let Ok(receipt) = api.get_transaction_receipt(hash).await {
-                            let transaction_receipt = TransactionReceiptJson::new(receipt);
-                            let json_resp = SuspiciousContractCreatedJson {
-                                creator: from_address.to_string(),
-                                contract_code: transaction.input().unwrap_or("x0").to_string(),
-                                contract_address: transaction_receipt.contract_address().unwrap().to_string(),
-                                block_timestamp: block.timestamp(),
-                                block: block.number(),
-                                transaction_hash: hash.to_string(),
-                            };
-                            return Some((Self::ID, serde_json::to_value(json_resp).unwrap()));
-                        }
-                    }
-                }
-            }
-        }
-        None
-    }
-}
-
-fn convert_i64_from_hex(str: &str) -> i64 {
-    i64::from_str_radix(str.trim_start_matches("0x"), 16).unwrap()
+    pub const ID: u32 = 3;
 }
 
-/// Thin logic around a block in the chain. Should contain little logic itself other than
-/// getters. Used as input to algo.
-///
-/// In this case, it is just a wrapper around serde_json so we don't have to think about serializing
-/// and deserializing/go through eth API in depth. In practice, the underlying representation would
-/// be completely irrelevant to callers and there would be complete separation i.e. you would have
-/// a completely standard [Block] struct rather than [BlockJson].
-#[derive(Debug)]
-pub struct BlockJson {
-    value: serde_json::Value,
-}
-
-impl BlockJson {
-    pub fn new(value: serde_json::Value) -> Self {
-        Self { value }
-    }
-
-    pub fn timestamp(&self) -> u64 {
-        // Every block should have a timestamp
-        let ts = self.value["result"]["timestamp"].as_str().unwrap();
-        convert_i64_from_hex(ts) as u64
+#[async_trait::async_trait]
+impl Event for SuspiciousContractCreatedEvent {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: Self::ID,
+            name: "suspicious_contract_created".into(),
+            version: 1,
+            inputs: &[Input::Block, Input::Api, Input::SuspiciousAddresses],
+            output: &["creator", "contract_code", "contract_address", "block_timestamp", "block", "transaction_hash"],
+        }
     }
 
-    pub fn number(&self) -> u64 {
-        // Every block should have a number
-        let number = self.value["result"]["number"].as_str().unwrap();
-        convert_i64_from_hex(number) as u64
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        let block = ctx.block;
+        // Receipts for every candidate are fetched in one batch rather than one call each, or
+        // read from the block if they were attached
+        let candidates: Vec<_> = block
+            .transactions
+            .iter()
+            .filter(|transaction| {
+                transaction.is_contract_creation() && ctx.suspicious_addresses.contains(&transaction.from.into())
+            })
+            .collect();
+
+        // Attached receipts save the round trip, otherwise fetch them here
+        let fetched: Vec<Option<Receipt>>;
+        let receipts: Vec<Option<&Receipt>> = if block.receipts().is_some() {
+            candidates.iter().map(|transaction| block.receipt(transaction.hash)).collect()
+        } else {
+            let batch = candidates
+                .iter()
+                .fold(JsonRpcBatch::new(), |batch, transaction| batch.get_receipt(transaction.hash));
+            let Ok(results) = ctx.api.batch(batch).await else {
+                return;
+            };
+            // A NullResult here means the receipt isn't available yet rather than the provider
+            // being down, either way there is nothing to report for this transaction
+            fetched = results
+                .into_iter()
+                .map(|res| res.ok().and_then(|mut value| serde_json::from_value(value["result"].take()).ok()))
+                .collect();
+            fetched.iter().map(Option::as_ref).collect()
+        };
+
+        for (transaction, receipt) in candidates.into_iter().zip(receipts) {
+            let Some(contract_address) = receipt.and_then(|receipt| receipt.contract_address) else {
+                continue;
+            };
+            let json_resp = SuspiciousContractCreatedJson {
+                creator: transaction.from.into(),
+                contract_code: transaction.input.to_string(),
+                contract_address: contract_address.into(),
+                block_timestamp: block.timestamp(),
+                block: block.number(),
+                transaction_hash: transaction.hash.to_string(),
+            };
+            sink.push(Self::ID, serde_json::to_value(json_resp).unwrap());
+        }
     }
 
-    pub fn get_transactions(&self) -> Vec<TransactionJson> {
-        let mut res = Vec::new();
-        if let Some(transactions) = self.value["result"]["transactions"].as_array() {
-            //This only makes sense because we don't have an internal repr that isn't related to
-            //json. Once this is gone we just return proper values
-            for transaction in transactions {
-                res.push(TransactionJson {
-                    value: transaction.clone(),
-                });
-            }
-        }
-        //If we don't have any transactions on result then this will fail silently, this shouldn't
-        //happen without errors in client code
-        res
+    /// Contracts created by suspicious addresses are what the triggered signal watches.
+    fn state_updates(&self, payload: &serde_json::Value) -> Vec<StateUpdate> {
+        payload_address(payload, "contract_address").map(StateUpdate::SuspiciousContract).into_iter().collect()
     }
 }
 
-// Thin logic around transaction. Same as above. Would also need to be moved away from wrapper
-// around serde_json.
-#[derive(Debug)]
-pub struct TransactionJson {
-    value: serde_json::Value,
+#[derive(Deserialize, Serialize)]
+pub struct SuspiciousContractCalledJson {
+    contract_address: CanonicalAddress,
+    caller: CanonicalAddress,
+    transaction_hash: String,
+    block_timestamp: u64,
+    block: u64,
+    /// Wei sent with the call
+    #[serde(with = "units::wei")]
+    value: U256,
+    value_eth: String,
 }
 
-impl TransactionJson {
-    pub fn hash(&self) -> Option<&str> {
-        self.value["hash"].as_str()
-    }
-
-    pub fn input(&self) -> Option<&str> {
-        self.value["input"].as_str()
-    }
-
-    pub fn from(&self) -> Option<&str> {
-        self.value["from"].as_str()
-    }
-
-    pub fn to(&self) -> Option<&str> {
-        self.value["to"].as_str()
-    }
-
-    pub fn value(&self) -> Option<u64> {
-        self.value["value"].as_u64()
-    }
+/// Transactions to contracts created by suspicious addresses.
+pub struct SuspiciousContractCalledEvent;
 
-    pub fn timestamp(&self) -> Option<i64> {
-        self.value["timestamp"].as_i64()
-    }
-
-    pub fn is_contract_creation(&self) -> bool {
-        if let Some(_val) = self.value["to"].as_null() {
-            return true;
-        }
-        false
-    }
+impl SuspiciousContractCalledEvent {
+    pub const ID: u32 = 4;
 }
 
-#[derive(Debug)]
-pub struct TransactionReceiptJson {
-    value: serde_json::Value,
-}
-
-impl TransactionReceiptJson {
-    pub fn new(value: serde_json::Value) -> Self {
-        Self { value }
+#[async_trait::async_trait]
+impl Event for SuspiciousContractCalledEvent {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: Self::ID,
+            name: "suspicious_contract_called".into(),
+            version: 1,
+            inputs: &[Input::Block, Input::SuspiciousContracts],
+            output: &["contract_address", "caller", "transaction_hash", "block_timestamp", "block", "value", "value_eth"],
+        }
     }
 
-    pub fn contract_address(&self) -> Option<&str> {
-        self.value["result"]["contractAddress"].as_str()
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        let block = ctx.block;
+        for transaction in &block.transactions {
+            if let Some(to) = transaction.to.map(CanonicalAddress::from) {
+                if ctx.suspicious_contracts.contains(&to) {
+                    let json_resp = SuspiciousContractCalledJson {
+                        contract_address: to,
+                        caller: transaction.from.into(),
+                        transaction_hash: transaction.hash.to_string(),
+                        block_timestamp: block.timestamp(),
+                        block: block.number(),
+                        value: transaction.value,
+                        value_eth: format_ether(transaction.value),
+                    };
+                    sink.push(Self::ID, serde_json::to_value(json_resp).unwrap());
+                }
+            }
+        }
     }
 }
-
@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use alloy_primitives::U256;
+
+/// 10^18
+pub const WEI_PER_ETH: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+const ETH_DECIMALS: usize = 18;
+
+/// Wei as a decimal ETH string, e.g. `1500000000000000000` is `"1.5"`. Exact, trailing zeros are
+/// dropped.
+pub fn format_ether(wei: U256) -> String {
+    let whole = wei / WEI_PER_ETH;
+    let fraction = wei % WEI_PER_ETH;
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0>width$}", fraction.to_string(), width = ETH_DECIMALS);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Decimal ETH to wei, the inverse of [format_ether]. Anything below 1 wei is an error rather than
+/// being rounded.
+pub fn parse_ether(eth: &str) -> Result<U256, ParseEtherError> {
+    parse_units(eth, ETH_DECIMALS)
+}
+
+/// Decimal amount in a unit with `decimals` places to its smallest unit, e.g. `"1.5"` gwei with 9
+/// is `1500000000`. Same rules as [parse_ether].
+pub fn parse_units(amount: &str, decimals: usize) -> Result<U256, ParseEtherError> {
+    let err = || ParseEtherError(amount.to_string());
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > decimals
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(err());
+    }
+    let whole = match whole {
+        "" => U256::ZERO,
+        whole => U256::from_str(whole).map_err(|_| err())?,
+    };
+    let fraction = match fraction {
+        "" => U256::ZERO,
+        fraction => U256::from_str(&format!("{:0<width$}", fraction, width = decimals)).map_err(|_| err())?,
+    };
+    whole
+        .checked_mul(U256::from(10).pow(U256::from(decimals)))
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(err)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseEtherError(String);
+
+impl std::error::Error for ParseEtherError {}
+
+impl std::fmt::Display for ParseEtherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid ETH amount: {}", self.0)
+    }
+}
+
+/// Wei in payloads as a decimal string. JSON numbers stop being exact at 2^53 which is only
+/// ~0.009 ETH, so neither the DB nor javascript consumers can take them as numbers. Numbers are
+/// still accepted when reading, older payloads stored `value` as one.
+pub mod wei {
+    use alloy_primitives::U256;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        String(String),
+        Number(u64),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::String(value) => value.parse().map_err(|_| D::Error::custom(format!("invalid wei amount {}", value))),
+            Repr::Number(value) => Ok(U256::from(value)),
+        }
+    }
+}
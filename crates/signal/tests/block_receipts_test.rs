@@ -0,0 +1,136 @@
+use std::{collections::HashSet, fs::File, io::BufReader};
+
+use alloy_primitives::{address, B256};
+use insolvent_detect_signal::{
+    api::{EthJsonRpc, HttpJsonRpc, ProviderConfig},
+    model::{Block, Receipt},
+    sink::Match,
+    types::{BlockContext, Event, SuspiciousContractCreatedEvent},
+};
+use serde_json::{json, Value};
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const TX_A: &str = "0x000000000000000000000000000000000000000000000000000000000000000a";
+const TX_B: &str = "0x000000000000000000000000000000000000000000000000000000000000000b";
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+fn receipt(hash: &Value) -> Value {
+    json!({"transactionHash": hash, "status": "0x1", "contractAddress": null, "logs": [{"address": ZERO_ADDRESS, "topics": [], "data": "0x"}]})
+}
+
+/// Node with two transactions in every block. Without `eth_getBlockReceipts` it answers like geth
+/// does for an unknown method.
+struct Node {
+    block_receipts: bool,
+}
+
+impl Respond for Node {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        if let Value::Array(requests) = body {
+            let responses: Vec<Value> = requests
+                .iter()
+                .map(|req| json!({"jsonrpc": "2.0", "id": req["id"], "result": receipt(&req["params"][0])}))
+                .collect();
+            return ResponseTemplate::new(200).set_body_json(responses);
+        }
+        let response = if self.block_receipts {
+            json!({"jsonrpc": "2.0", "id": body["id"], "result": [receipt(&json!(TX_A)), receipt(&json!(TX_B))]})
+        } else {
+            json!({"jsonrpc": "2.0", "id": body["id"], "error": {"code": -32601, "message": "the method eth_getBlockReceipts does not exist/is not available"}})
+        };
+        ResponseTemplate::new(200).set_body_json(response)
+    }
+}
+
+fn transaction(hash: &str) -> Value {
+    json!({"hash": hash, "type": "0x0", "nonce": "0x0", "from": ZERO_ADDRESS, "to": ZERO_ADDRESS, "value": "0x0", "gas": "0x5208", "gasPrice": "0x1", "input": "0x"})
+}
+
+fn block() -> Block {
+    serde_json::from_value(json!({
+        "number": "0x10",
+        "hash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "parentHash": "0x1111111111111111111111111111111111111111111111111111111111111110",
+        "timestamp": "0x1",
+        "transactions": [transaction(TX_A), transaction(TX_B)],
+    }))
+    .unwrap()
+}
+
+async fn sent(server: &MockServer) -> Vec<Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|req| serde_json::from_slice(&req.body).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn block_receipts_native_test() {
+    // One eth_getBlockReceipts call for the whole block
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(Node { block_receipts: true }).mount(&server).await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+
+    let mut block = block();
+    assert!(block.receipts().is_none());
+    let receipts = api.get_block_receipts(&block).await.unwrap();
+    assert_eq!(receipts.len(), 2);
+    assert!(receipts[0].is_success());
+    assert_eq!(receipts[0].logs.len(), 1);
+
+    block.attach_receipts(receipts);
+    let tx_b: B256 = TX_B.parse().unwrap();
+    assert_eq!(block.receipt(tx_b).unwrap().transaction_hash, tx_b);
+    assert!(block.receipt(B256::ZERO).is_none());
+
+    let sent = sent(&server).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["method"], "eth_getBlockReceipts");
+    assert_eq!(sent[0]["params"][0], "0x1111111111111111111111111111111111111111111111111111111111111111");
+}
+
+#[tokio::test]
+async fn block_receipts_fallback_test() {
+    // Node doesn't have the method, receipts come from a single batch in block order instead
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(Node { block_receipts: false }).mount(&server).await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+
+    let receipts = api.get_block_receipts(&block()).await.unwrap();
+    let hashes: Vec<_> = receipts.iter().map(|r| r.transaction_hash.to_string()).collect();
+    assert_eq!(hashes, vec![TX_A, TX_B]);
+
+    let sent = sent(&server).await;
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0]["method"], "eth_getBlockReceipts");
+    assert_eq!(sent[1].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn block_receipts_attached_test() {
+    // Events read attached receipts instead of calling the provider
+    let server = MockServer::start().await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+
+    let file = File::open("tests/__data__/suspicious_contract_created_response.json").unwrap();
+    let value: Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let mut block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    block.attach_receipts(vec![Receipt {
+        transaction_hash: "0xc727091f212aa24561e1ab7693b752b584013c3e914b177a2675d108d487738f".parse().unwrap(),
+        contract_address: Some(address!("03e7b13bcd9b8383f403696c1494845560607eca")),
+        ..Default::default()
+    }]);
+
+    let mut suspicious_addresses = HashSet::new();
+    suspicious_addresses.insert("0x864e656c57a5a119f332c47326a35422294db5c9".parse().unwrap());
+    let event = SuspiciousContractCreatedEvent;
+    let mut matches: Vec<Match> = Vec::new();
+    event.event(&BlockContext::new(1, &block, &api).with_suspicious_addresses(&suspicious_addresses), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0, 3);
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
@@ -0,0 +1,175 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use alloy_primitives::{address, b256};
+use insolvent_detect_signal::{
+    api::{BatchResponse, BlockTag, CacheConfig, CachedJsonRpc, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, RpcError},
+    model::Block,
+};
+use serde_json::{json, Value};
+
+/// Node at block 1000 that records the methods it is asked for, a batch is one entry.
+#[derive(Clone, Default)]
+struct CountingNode {
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl CountingNode {
+    fn response(req: &JsonRpcRequest) -> Value {
+        let params = serde_json::to_value(&req.params).unwrap();
+        let result = match req.method.as_str() {
+            "eth_blockNumber" => json!("0x3e8"),
+            "eth_getBlockByNumber" => json!({"number": params[0], "hash": "0xb", "timestamp": "0x1", "transactions": []}),
+            "eth_getTransactionReceipt" => json!({"transactionHash": params[0], "blockNumber": "0x64"}),
+            "eth_getCode" => json!("0x6080"),
+            "eth_getBlockReceipts" => json!([{"transactionHash": format!("0x{:064x}", 10), "blockNumber": "0x64"}]),
+            _ => Value::Null,
+        };
+        json!({"jsonrpc": "2.0", "id": req.id, "result": result})
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn reset(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for CountingNode {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        self.calls.lock().unwrap().push(req.method.clone());
+        Ok(Self::response(req))
+    }
+
+    async fn batch(&self, batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        self.calls.lock().unwrap().push(format!("batch of {}", batch.len()));
+        Ok(batch.requests().iter().map(|req| Ok(Self::response(req))).collect())
+    }
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("insolvent-cache-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn cache_backfill_test() {
+    // Second pass over a finalized range should never reach the node, also after a restart
+    let dir = cache_dir("backfill");
+    let node = CountingNode::default();
+    let api = CachedJsonRpc::new(node.clone(), CacheConfig::new(&dir)).unwrap();
+
+    for number in 100..110 {
+        api.get_block_by_number_hash(number).await.unwrap();
+    }
+    let receipts = JsonRpcBatch::new().get_transaction_receipt("0xa").get_transaction_receipt("0xb");
+    api.batch(receipts).await.unwrap();
+    assert_eq!(api.stats().entries, 12);
+
+    node.reset();
+    for number in 100..110 {
+        let block = api.get_block_by_number_hash(number).await.unwrap();
+        assert_eq!(block["result"]["number"], format!("0x{:x}", number));
+    }
+    let receipts = JsonRpcBatch::new().get_transaction_receipt("0xa").get_transaction_receipt("0xb");
+    let results = api.batch(receipts).await.unwrap();
+    assert_eq!(results[1].as_ref().unwrap()["result"]["transactionHash"], "0xb");
+    assert!(node.calls().is_empty());
+    assert_eq!(api.stats().hits, 12);
+
+    // Entries are picked up from disk by a new instance
+    let api = CachedJsonRpc::new(node.clone(), CacheConfig::new(&dir)).unwrap();
+    api.get_block_by_number_hash(105).await.unwrap();
+    assert!(node.calls().is_empty());
+
+    // Partly cached batch only sends the misses, plus a head lookup since this instance hasn't
+    // seen one yet
+    let mixed = JsonRpcBatch::new().get_transaction_receipt("0xa").get_transaction_receipt("0xc");
+    let results = api.batch(mixed).await.unwrap();
+    assert_eq!(results[0].as_ref().unwrap()["result"]["transactionHash"], "0xa");
+    assert_eq!(results[1].as_ref().unwrap()["result"]["transactionHash"], "0xc");
+    assert_eq!(node.calls(), vec!["batch of 1", "eth_blockNumber"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn cache_block_receipts_test() {
+    // Receipts are asked for by block hash so they never change
+    let dir = cache_dir("receipts");
+    let node = CountingNode::default();
+    let api = CachedJsonRpc::new(node.clone(), CacheConfig::new(&dir)).unwrap();
+    for _ in 0..2 {
+        let receipts = api.get_block_receipts(&Block::default()).await.unwrap();
+        assert_eq!(receipts[0].block_number, 100);
+    }
+    assert_eq!(node.calls(), vec!["eth_getBlockReceipts"]);
+    assert_eq!(api.stats().hits, 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn cache_never_stores_mutable_test() {
+    // latest, recent blocks and code at a tag must always go to the node
+    let dir = cache_dir("mutable");
+    let node = CountingNode::default();
+    let api = CachedJsonRpc::new(node.clone(), CacheConfig::new(&dir)).unwrap();
+    let contract = address!("03e7b13bcd9b8383f403696c1494845560607eca");
+
+    for _ in 0..2 {
+        api.get_block_by_number_latest().await.unwrap();
+        // Within 64 blocks of the head
+        api.get_block_by_number_hash(990).await.unwrap();
+        api.get_code(contract, BlockTag::Latest).await.unwrap();
+        api.get_code(contract, BlockTag::Pending).await.unwrap();
+    }
+    assert_eq!(api.stats().entries, 0);
+    let calls = node.calls();
+    assert_eq!(calls.iter().filter(|m| *m == "eth_getBlockByNumber").count(), 4);
+    assert_eq!(calls.iter().filter(|m| *m == "eth_getCode").count(), 4);
+
+    // Code at a final block or a block hash is fine
+    node.reset();
+    let hash = b256!("c727091f212aa24561e1ab7693b752b584013c3e914b177a2675d108d487738f");
+    for _ in 0..2 {
+        api.get_code(contract, BlockTag::Number(100)).await.unwrap();
+        api.get_code(contract, BlockTag::Hash(hash)).await.unwrap();
+    }
+    assert_eq!(node.calls().iter().filter(|m| *m == "eth_getCode").count(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn cache_eviction_test() {
+    // Least recently used blocks go first once over the limit
+    let dir = cache_dir("eviction");
+    let node = CountingNode::default();
+    // Each block is ~66 bytes, room for three
+    let config = CacheConfig::new(&dir).with_max_bytes(250);
+    let api = CachedJsonRpc::new(node.clone(), config).unwrap();
+
+    for number in 100..103 {
+        api.get_block_by_number_hash(number).await.unwrap();
+    }
+    let stats = api.stats();
+    assert!(stats.bytes <= 250);
+    assert_eq!(stats.entries, 3);
+
+    // Use 100 so 101 is the oldest, then push it out
+    api.get_block_by_number_hash(100).await.unwrap();
+    api.get_block_by_number_hash(103).await.unwrap();
+    assert_eq!(api.stats().entries, 3);
+
+    node.reset();
+    api.get_block_by_number_hash(100).await.unwrap();
+    api.get_block_by_number_hash(103).await.unwrap();
+    assert!(node.calls().is_empty());
+    api.get_block_by_number_hash(101).await.unwrap();
+    assert_eq!(node.calls(), vec!["eth_getBlockByNumber"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
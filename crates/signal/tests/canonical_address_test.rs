@@ -0,0 +1,57 @@
+use std::{collections::HashSet, fs::File, io::BufReader};
+
+use alloy_primitives::address;
+use insolvent_detect_signal::{
+    address::{AddressParseError, CanonicalAddress},
+    api::{HttpJsonRpc, ProviderConfig},
+    model::Block,
+    sink::Match,
+    types::{BlockContext, Event, TornadoCashWithdrawEvent},
+};
+
+const LOWER: &str = "0x47ce0c6ed5b0ce3d3a51fdb1c52dc66a7c3c2936";
+const CHECKSUM: &str = "0x47CE0C6eD5B0Ce3d3A51fdb1C52DC66a7c3c2936";
+
+#[test]
+fn canonical_address_parse_test() {
+    let lower: CanonicalAddress = LOWER.parse().unwrap();
+    let upper: CanonicalAddress = "0x47CE0C6ED5B0CE3D3A51FDB1C52DC66A7C3C2936".parse().unwrap();
+    let checksum: CanonicalAddress = CHECKSUM.parse().unwrap();
+    let unprefixed: CanonicalAddress = LOWER[2..].parse().unwrap();
+    assert_eq!(lower, upper);
+    assert_eq!(lower, checksum);
+    assert_eq!(lower, unprefixed);
+    assert_eq!(lower, address!("47ce0c6ed5b0ce3d3a51fdb1c52dc66a7c3c2936").into());
+
+    // One letter flipped
+    let typo = "0x47cE0C6eD5B0Ce3d3A51fdb1C52DC66a7c3c2936";
+    assert_eq!(typo.parse::<CanonicalAddress>(), Err(AddressParseError::Checksum(typo.to_string())));
+    for invalid in ["", "0x", "0x47ce", "0x47ce0c6ed5b0ce3d3a51fdb1c52dc66a7c3c29zz"] {
+        assert!(matches!(invalid.parse::<CanonicalAddress>(), Err(AddressParseError::Invalid(_))), "{}", invalid);
+    }
+
+    // Always written out lowercase, whatever it was parsed from
+    assert_eq!(checksum.to_string(), LOWER);
+    assert_eq!(serde_json::to_value(checksum).unwrap(), LOWER);
+    assert_eq!(checksum.to_checksum(), CHECKSUM);
+    let set: HashSet<CanonicalAddress> = [lower, upper, checksum].into();
+    assert_eq!(set.len(), 1);
+}
+
+#[tokio::test]
+async fn canonical_address_tornado_test() {
+    // Decoded pool address has to match the configured one however either is formatted
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
+    let file = File::open("tests/__data__/tornado_cash_block_response.json").unwrap();
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+
+    let mut matches: Vec<Match> = Vec::new();
+    TornadoCashWithdrawEvent::default()
+        .event(&BlockContext::new(1, &block, &api), &mut matches)
+        .await;
+    let (_, payload) = &matches[0];
+    assert_ne!(payload["tornado_address_name"], "Unknown");
+    let tornado_address = payload["tornado_address"].as_str().unwrap();
+    assert_eq!(tornado_address, tornado_address.to_lowercase());
+}
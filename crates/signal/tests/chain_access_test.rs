@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{address, b256, Bytes, U256};
+use insolvent_detect_signal::api::{
+    AccountOverride, BlockTag, CallRequest, EthJsonRpc, HttpJsonRpc, LogFilter, ProviderConfig,
+};
+use serde_json::{json, Value};
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Minimal node, answers based on the method and checks the params we send look right.
+struct FakeNode;
+
+impl Respond for FakeNode {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let req: Value = serde_json::from_slice(&request.body).unwrap();
+        let params = &req["params"];
+        let result = match req["method"].as_str().unwrap() {
+            "eth_getCode" => {
+                assert_eq!(params[0], "0x03e7b13bcd9b8383f403696c1494845560607eca");
+                assert_eq!(params[1], "latest");
+                json!("0x6080")
+            }
+            "eth_getBalance" => {
+                assert_eq!(params[1], "0x1146ab6");
+                json!("0xde0b6b3a7640000")
+            }
+            "eth_getStorageAt" => {
+                assert_eq!(params[1], "0x1");
+                assert_eq!(params[2], "finalized");
+                json!("0x0000000000000000000000000000000000000000000000000000000000000001")
+            }
+            "eth_getTransactionCount" => json!("0x1a"),
+            "eth_call" => {
+                assert_eq!(params[0]["to"], "0x03e7b13bcd9b8383f403696c1494845560607eca");
+                assert_eq!(params[0]["input"], "0x06fdde03");
+                assert_eq!(params[1], "safe");
+                assert_eq!(
+                    params[2]["0x864e656c57a5a119f332c47326a35422294db5c9"]["balance"],
+                    "0x1"
+                );
+                json!("0x01")
+            }
+            "eth_getLogs" => {
+                assert_eq!(params[0]["fromBlock"], "0x1");
+                assert_eq!(params[0]["toBlock"], "latest");
+                json!([{"address": "0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f", "topics": ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"], "data": "0x", "blockNumber": "0x10"}])
+            }
+            "eth_getTransactionByHash" => Value::Null,
+            "eth_getBlockByHash" => json!({
+                "number": "0x1146ab6",
+                "hash": params[0],
+                "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "timestamp": "0x1",
+                "transactions": [],
+            }),
+            "eth_chainId" => json!("0x1"),
+            _ => return ResponseTemplate::new(200).set_body_json(json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": -32601, "message": "method not found"}})),
+        };
+        ResponseTemplate::new(200).set_body_json(json!({"jsonrpc": "2.0", "id": req["id"], "result": result}))
+    }
+}
+
+async fn api() -> (MockServer, HttpJsonRpc) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(FakeNode).mount(&server).await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+    (server, api)
+}
+
+#[tokio::test]
+async fn chain_access_state_test() {
+    // State methods should send the right params and decode hex results into typed values
+    let (_server, api) = api().await;
+    let contract = address!("03e7b13bcd9b8383f403696c1494845560607eca");
+
+    let code = api.get_code(contract, BlockTag::Latest).await.unwrap();
+    assert_eq!(code, Bytes::from(vec![0x60, 0x80]));
+
+    let balance = api.get_balance(contract, BlockTag::Number(18115254)).await.unwrap();
+    assert_eq!(balance, U256::from(1_000_000_000_000_000_000u128));
+
+    let slot = api.get_storage_at(contract, U256::from(1), BlockTag::Finalized).await.unwrap();
+    assert_eq!(slot, b256!("0000000000000000000000000000000000000000000000000000000000000001"));
+
+    assert_eq!(api.get_transaction_count(contract, BlockTag::Latest).await.unwrap(), 26);
+    assert_eq!(api.get_chain_id().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn chain_access_call_test() {
+    // eth_call with a block tag and a state override on the caller
+    let (_server, api) = api().await;
+    let contract = address!("03e7b13bcd9b8383f403696c1494845560607eca");
+    let caller = address!("864e656c57a5a119f332c47326a35422294db5c9");
+
+    let mut overrides = HashMap::new();
+    overrides.insert(
+        caller,
+        AccountOverride {
+            balance: Some(U256::from(1)),
+            ..Default::default()
+        },
+    );
+    let call = CallRequest::new(contract, Bytes::from(vec![0x06, 0xfd, 0xde, 0x03]));
+    let res = api.call(&call, BlockTag::Safe, Some(&overrides)).await.unwrap();
+    assert_eq!(res, Bytes::from(vec![0x01]));
+}
+
+#[tokio::test]
+async fn chain_access_lookup_test() {
+    // Logs, blocks and transactions are typed, unknown hashes come back as None
+    let (_server, api) = api().await;
+
+    let filter = LogFilter {
+        from_block: Some(BlockTag::Number(1)),
+        to_block: Some(BlockTag::Latest),
+        ..Default::default()
+    };
+    let logs = api.get_logs(&filter).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].address, address!("4e5b2e1dc63f6b91cb6cd759936495434c7e972f"));
+    assert_eq!(logs[0].block_number, Some(16));
+
+    let hash = b256!("c727091f212aa24561e1ab7693b752b584013c3e914b177a2675d108d487738f");
+    assert!(api.get_transaction_by_hash(hash).await.unwrap().is_none());
+
+    let block = api.get_block_by_hash(hash).await.unwrap().unwrap();
+    assert_eq!(block.number(), 18115254);
+    assert_eq!(block.hash(), hash);
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use insolvent_detect_signal::{
+    api::{BatchResponse, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, RpcError},
+    follower::{ChainFollower, ChainNotification, ProcessingDepth},
+};
+use serde_json::{json, Value};
+
+fn hash(branch: u8, number: u64) -> String {
+    format!("0x{:02x}{:062x}", branch, number)
+}
+
+/// Chain the test can extend and reorg between polls. Blocks are kept by hash so orphaned
+/// blocks can still be fetched, like on a real node.
+#[derive(Default)]
+struct Chain {
+    blocks: HashMap<String, Value>,
+    canonical: Vec<String>,
+    // Nodes without these answer with null
+    safe: Option<usize>,
+    finalized: Option<usize>,
+}
+
+impl Chain {
+    /// Replaces everything from `from` onwards with blocks on `branch` up to `to`.
+    fn build(&mut self, branch: u8, from: u64, to: u64) {
+        self.canonical.truncate(from as usize);
+        for number in from..=to {
+            let parent = match number {
+                0 => hash(0, 0),
+                _ => self.canonical[number as usize - 1].clone(),
+            };
+            let block_hash = hash(branch, number);
+            self.blocks.insert(
+                block_hash.clone(),
+                json!({"number": format!("0x{:x}", number), "hash": block_hash, "parentHash": parent, "timestamp": "0x1", "transactions": []}),
+            );
+            self.canonical.push(block_hash);
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct FakeNode {
+    chain: Arc<Mutex<Chain>>,
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for FakeNode {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        let params = serde_json::to_value(&req.params).unwrap();
+        let chain = self.chain.lock().unwrap();
+        if req.method == "eth_blockNumber" {
+            let head = format!("0x{:x}", chain.canonical.len() - 1);
+            return Ok(json!({"jsonrpc": "2.0", "id": req.id, "result": head}));
+        }
+        let block_hash = match (req.method.as_str(), params[0].as_str().unwrap()) {
+            ("eth_getBlockByNumber", "latest") => chain.canonical.last().cloned(),
+            ("eth_getBlockByNumber", "safe") => chain.safe.map(|n| chain.canonical[n].clone()),
+            ("eth_getBlockByNumber", "finalized") => chain.finalized.map(|n| chain.canonical[n].clone()),
+            ("eth_getBlockByNumber", number) => {
+                let number = usize::from_str_radix(number.trim_start_matches("0x"), 16).unwrap();
+                chain.canonical.get(number).cloned()
+            }
+            (_, block_hash) => Some(block_hash.to_string()),
+        };
+        let result = block_hash.and_then(|h| chain.blocks.get(&h).cloned()).unwrap_or(Value::Null);
+        Ok(json!({"jsonrpc": "2.0", "id": req.id, "result": result}))
+    }
+
+    async fn batch(&self, _batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        unimplemented!()
+    }
+}
+
+/// `+n` for an added block, `-n` for a reverted one, with the branch for added blocks, `fn` for
+/// finalized and `en` for evicted ones.
+fn summary(notifications: &[ChainNotification]) -> Vec<String> {
+    notifications
+        .iter()
+        .map(|n| match n {
+            ChainNotification::BlockAdded(block) => format!("+{}:{}", block.number(), &block.hash().to_string()[2..4]),
+            ChainNotification::BlockReverted(block) => format!("-{}", block.number),
+            ChainNotification::BlockFinalized(block) => format!("f{}", block.number),
+            ChainNotification::BlockEvicted(block) => format!("e{}", block.number),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn follower_gap_and_repeat_test() {
+    // Unchanged head gives nothing, several new blocks between polls all come through in order
+    let node = FakeNode::default();
+    node.chain.lock().unwrap().build(1, 0, 10);
+    let mut follower = ChainFollower::new(&node);
+
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+10:01"]);
+    assert!(follower.poll().await.unwrap().is_empty());
+
+    node.chain.lock().unwrap().build(1, 11, 13);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+11:01", "+12:01", "+13:01"]);
+    assert_eq!(follower.tip().unwrap().number, 13);
+}
+
+#[tokio::test]
+async fn follower_reorg_test() {
+    // Last two blocks replaced by a longer branch, both reverted before the new ones are added
+    let node = FakeNode::default();
+    node.chain.lock().unwrap().build(1, 0, 10);
+    let mut follower = ChainFollower::new(&node).with_start(8);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+8:01", "+9:01", "+10:01"]);
+
+    node.chain.lock().unwrap().build(2, 9, 11);
+    assert_eq!(
+        summary(&follower.poll().await.unwrap()),
+        vec!["-10", "-9", "+9:02", "+10:02", "+11:02"]
+    );
+
+    // Reorg deeper than the window, everything we know about is reverted
+    let mut follower = ChainFollower::new(&node).with_start(9).with_window(2);
+    follower.poll().await.unwrap();
+    follower.poll().await.unwrap();
+    assert_eq!(follower.tip().unwrap().number, 11);
+    node.chain.lock().unwrap().build(3, 5, 12);
+    let notifications = summary(&follower.poll().await.unwrap());
+    assert_eq!(notifications, vec!["-11", "-10", "+10:03", "+11:03", "+12:03", "e10"]);
+}
+
+#[tokio::test]
+async fn follower_evicted_test() {
+    // Finality lagging past the window: blocks leaving it unfinalized are evicted, once
+    let node = FakeNode::default();
+    node.chain.lock().unwrap().build(1, 0, 10);
+    let mut follower = ChainFollower::new(&node).with_start(8).with_window(3);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+8:01", "+9:01", "+10:01"]);
+
+    node.chain.lock().unwrap().build(1, 11, 12);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+11:01", "+12:01", "e8", "e9"]);
+
+    // Finalized in the same poll it would leave the window, that wins
+    {
+        let mut chain = node.chain.lock().unwrap();
+        chain.build(1, 13, 13);
+        chain.finalized = Some(10);
+    }
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+13:01", "f10"]);
+
+    // Already final blocks leave quietly
+    node.chain.lock().unwrap().build(1, 14, 14);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+14:01", "e11"]);
+}
+
+#[tokio::test]
+async fn follower_catch_up_test() {
+    // Starting far behind the head is done a window at a time
+    let node = FakeNode::default();
+    node.chain.lock().unwrap().build(1, 0, 20);
+    let mut follower = ChainFollower::new(&node).with_start(5).with_window(4);
+
+    let mut seen = Vec::new();
+    for _ in 0..5 {
+        for notification in follower.poll().await.unwrap() {
+            match notification {
+                ChainNotification::BlockAdded(block) => seen.push(block.number()),
+                ChainNotification::BlockReverted(_) => panic!("nothing was reorged"),
+                ChainNotification::BlockFinalized(_) | ChainNotification::BlockEvicted(_) => {}
+            }
+        }
+    }
+    assert_eq!(seen, (5..=20).collect::<Vec<u64>>());
+}
+
+#[tokio::test]
+async fn follower_depth_test() {
+    // Confirmations and tags follow a head behind latest
+    let node = FakeNode::default();
+    {
+        let mut chain = node.chain.lock().unwrap();
+        chain.build(1, 0, 20);
+        chain.safe = Some(15);
+        chain.finalized = Some(12);
+    }
+
+    let mut follower = ChainFollower::new(&node).with_depth(ProcessingDepth::Confirmations(3));
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+17:01"]);
+    let mut follower = ChainFollower::new(&node).with_depth(ProcessingDepth::Safe);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+15:01"]);
+    // Already final so no upgrade notifications
+    let mut follower = ChainFollower::new(&node).with_depth(ProcessingDepth::Finalized);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+12:01"]);
+    node.chain.lock().unwrap().finalized = Some(14);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+13:01", "+14:01"]);
+
+    assert_eq!("finalized".parse::<ProcessingDepth>().unwrap(), ProcessingDepth::Finalized);
+    assert_eq!("12".parse::<ProcessingDepth>().unwrap(), ProcessingDepth::Confirmations(12));
+    assert!("soon".parse::<ProcessingDepth>().is_err());
+}
+
+#[tokio::test]
+async fn follower_finalized_upgrade_test() {
+    // Following latest, blocks are upgraded once the finalized head passes them
+    let node = FakeNode::default();
+    node.chain.lock().unwrap().build(1, 0, 10);
+    let mut follower = ChainFollower::new(&node).with_start(8);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+8:01", "+9:01", "+10:01"]);
+
+    node.chain.lock().unwrap().finalized = Some(9);
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["f8", "f9"]);
+    // Only once
+    assert!(follower.poll().await.unwrap().is_empty());
+
+    {
+        let mut chain = node.chain.lock().unwrap();
+        chain.build(1, 11, 11);
+        chain.finalized = Some(11);
+    }
+    assert_eq!(summary(&follower.poll().await.unwrap()), vec!["+11:01", "f10", "f11"]);
+}
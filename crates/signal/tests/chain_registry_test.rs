@@ -0,0 +1,73 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{block, offline_api};
+use insolvent_detect_signal::{
+    address::CanonicalAddress,
+    api::ProviderConfig,
+    chain::{ChainAddresses, ChainConfig, ChainRegistry},
+    follower::ProcessingDepth,
+    sink::Match,
+    types::{BlockContext, Detection, Event, TornadoCashWithdrawEvent, TransferFromFixedFloatEvent},
+};
+
+#[test]
+fn chain_registry_known_test() {
+    let provider = ProviderConfig::new("http://localhost:8545");
+    let arbitrum = ChainConfig::known(ChainConfig::ARBITRUM, provider.clone()).unwrap();
+    assert_eq!(arbitrum.name, "arbitrum");
+    assert_eq!(arbitrum.provider.chain_id, 42161);
+    assert_eq!(arbitrum.block_time, Duration::from_millis(250));
+    // Finality lag is thousands of blocks at 250ms
+    assert!(arbitrum.window > 1000);
+    assert!(arbitrum.addresses.tornado_pools.is_empty());
+
+    let mainnet = ChainConfig::known(ChainConfig::MAINNET, provider.clone()).unwrap();
+    let pool: CanonicalAddress = "0x47CE0C6eD5B0Ce3d3A51fdb1C52DC66a7c3c2936".parse().unwrap();
+    assert_eq!(mainnet.addresses.tornado_pool_name(pool), Some("ETH/1"));
+    let fixed_float: CanonicalAddress = "0x4E5B2E1DC63F6B91CB6CD759936495434C7E972F".parse().unwrap();
+    assert!(mainnet.addresses.is_fixed_float(fixed_float));
+
+    assert!(ChainConfig::known(5, provider.clone()).is_none());
+
+    let registry = ChainRegistry::new()
+        .with_chain(mainnet)
+        .with_chain(arbitrum)
+        .with_chain(ChainConfig::new(ChainConfig::MAINNET, "mainnet", provider).with_depth(ProcessingDepth::Finalized));
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.get(1).unwrap().depth, ProcessingDepth::Finalized);
+    let ids: Vec<u64> = registry.chains().map(|chain| chain.chain_id).collect();
+    assert_eq!(ids, vec![1, 42161]);
+}
+
+#[tokio::test]
+async fn chain_registry_addresses_test() {
+    // Same blocks, detectors only know the addresses of the chain they were built for
+    let api = offline_api();
+    let other_chain = ChainAddresses::default();
+
+    let tornado = block("tornado_cash_block_response.json");
+    let mut matches: Vec<Match> = Vec::new();
+    TornadoCashWithdrawEvent::default()
+        .event(&BlockContext::new(1, &tornado, &api), &mut matches)
+        .await;
+    TornadoCashWithdrawEvent::for_chain(&other_chain)
+        .event(&BlockContext::new(1, &tornado, &api), &mut matches)
+        .await;
+    assert_eq!(matches.len(), 2);
+    assert_ne!(matches[0].1["tornado_address_name"], "Unknown");
+    assert_eq!(matches[1].1["tornado_address_name"], "Unknown");
+
+    let fixed_float = block("fixed_float_deposit_response.json");
+    let mut matches: Vec<Match> = Vec::new();
+    let event = TransferFromFixedFloatEvent::for_chain(&other_chain);
+    event.event(&BlockContext::new(1, &fixed_float, &api), &mut matches).await;
+    assert!(matches.is_empty());
+
+    let event = TransferFromFixedFloatEvent::default();
+    event.event(&BlockContext::new(1, &fixed_float, &api), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+    let detection = Detection::new(ChainConfig::MAINNET, matches.remove(0), &fixed_float, ProcessingDepth::Latest);
+    assert_eq!(serde_json::to_value(&detection).unwrap()["chain_id"], 1);
+}
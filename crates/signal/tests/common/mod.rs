@@ -0,0 +1,58 @@
+//! Fixtures shared by the integration tests, pulled in with `mod common;`. Not every test uses
+//! all of them.
+#![allow(dead_code)]
+
+use std::{fs::File, io::BufReader};
+
+use alloy_primitives::address;
+use insolvent_detect_signal::{
+    address::CanonicalAddress,
+    api::{HttpJsonRpc, ProviderConfig},
+    model::{Block, Receipt},
+};
+
+/// Funded by FixedFloat in [funding_block], creates [CONTRACT] in [creation_block]
+pub const CREATOR: &str = "0x864e656c57a5a119f332c47326a35422294db5c9";
+/// GROK
+pub const CONTRACT: &str = "0x03e7b13bcd9b8383f403696c1494845560607eca";
+pub const CREATION_HASH: &str = "0xc727091f212aa24561e1ab7693b752b584013c3e914b177a2675d108d487738f";
+
+/// `result` of a saved response in `tests/__data__`.
+pub fn block(file: &str) -> Block {
+    let file = File::open(format!("tests/__data__/{}", file)).unwrap();
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    serde_json::from_value(value["result"].clone()).unwrap()
+}
+
+/// For detectors that don't need the node, nothing is listening.
+pub fn offline_api() -> HttpJsonRpc {
+    HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap()
+}
+
+/// FixedFloat block with its transfer going to [CREATOR]
+pub fn funding_block() -> Block {
+    let mut block = block("fixed_float_deposit_response.json");
+    for transaction in &mut block.transactions {
+        if transaction.from == address!("4e5b2e1dc63f6b91cb6cd759936495434c7e972f") {
+            transaction.to = Some(CREATOR.parse::<CanonicalAddress>().unwrap().into());
+        }
+    }
+    block
+}
+
+/// The GROK deployment with its receipt, made the creator's first transaction
+pub fn creation_block() -> Block {
+    let hash = CREATION_HASH.parse().unwrap();
+    let mut block = block("suspicious_contract_created_response.json");
+    for transaction in &mut block.transactions {
+        if transaction.hash == hash {
+            transaction.nonce = 0;
+        }
+    }
+    block.attach_receipts(vec![Receipt {
+        transaction_hash: hash,
+        contract_address: Some(CONTRACT.parse::<CanonicalAddress>().unwrap().into()),
+        ..Default::default()
+    }]);
+    block
+}
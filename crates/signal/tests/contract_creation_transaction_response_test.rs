@@ -1,6 +1,7 @@
 use std::{fs::File, io::BufReader};
 
-use insolvent_detect_signal::types::TransactionReceiptJson;
+use alloy_primitives::address;
+use insolvent_detect_signal::model::Receipt;
 
 #[test]
 fn contract_creation_transaction_response_test() {
@@ -8,9 +9,10 @@ fn contract_creation_transaction_response_test() {
     let file = File::open("tests/__data__/contract_creation_transaction_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_transaction_receipt = TransactionReceiptJson::new(value);
+    let receipt: Receipt = serde_json::from_value(value["result"].clone()).unwrap();
     assert_eq!(
-        json_transaction_receipt.contract_address().unwrap(),
-        "0x03e7b13bcd9b8383f403696c1494845560607eca"
+        receipt.contract_address.unwrap(),
+        address!("03e7b13bcd9b8383f403696c1494845560607eca")
     );
+    assert!(receipt.is_success());
 }
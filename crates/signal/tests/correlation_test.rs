@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use alloy_primitives::B256;
+use insolvent_detect_signal::{
+    correlation::{PartialMatch, Pattern, Step},
+    model::Block,
+    sink::Match,
+    state::StateStore,
+    types::{
+        AnonymouslyFundedSmartContractTriggeredSignal, DetectorMeta, Input, SuspiciousContractCalledEvent,
+        SuspiciousContractCreatedEvent, TornadoCashWithdrawEvent, TransferFromFixedFloatEvent,
+    },
+};
+use serde_json::{json, Value};
+
+const ACCOUNT: &str = "0x864e656c57a5a119f332c47326a35422294db5c9";
+const OTHER: &str = "0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f";
+const CONTRACT: &str = "0x03e7b13bcd9b8383f403696c1494845560607eca";
+
+/// Header is all the engine reads
+fn block(number: u64, timestamp: u64) -> Block {
+    let mut block = Block::default();
+    block.header.number = number;
+    block.header.timestamp = timestamp;
+    block.header.hash = B256::with_last_byte(number as u8);
+    block
+}
+
+fn funded(account: &str) -> Match {
+    (TransferFromFixedFloatEvent::ID, json!({"recipient": account, "transaction_hash": "0x01"}))
+}
+
+fn created(account: &str, contract: &str) -> Match {
+    (
+        SuspiciousContractCreatedEvent::ID,
+        json!({"creator": account, "contract_address": contract, "transaction_hash": "0x02"}),
+    )
+}
+
+fn called(contract: &str) -> Match {
+    (
+        SuspiciousContractCalledEvent::ID,
+        json!({"contract_address": contract, "caller": OTHER, "value": "0", "transaction_hash": "0x03"}),
+    )
+}
+
+/// Signals fired for the block
+fn process(pattern: &Pattern, partials: &mut Vec<PartialMatch>, block: &Block, matches: &[Match]) -> Vec<Value> {
+    let mut signals: Vec<Match> = Vec::new();
+    pattern.process(partials, block, matches, &mut signals);
+    signals.into_iter().map(|(_, value)| value).collect()
+}
+
+#[test]
+fn correlation_sequence_test() {
+    let pattern = AnonymouslyFundedSmartContractTriggeredSignal::pattern();
+    let mut partials = Vec::new();
+
+    // Out of order does nothing
+    assert!(process(&pattern, &mut partials, &block(1, 0), &[called(CONTRACT), created(ACCOUNT, CONTRACT)]).is_empty());
+    assert!(partials.is_empty());
+
+    assert!(process(&pattern, &mut partials, &block(2, 12), &[funded(ACCOUNT)]).is_empty());
+    // Creator has to be the funded account
+    assert!(process(&pattern, &mut partials, &block(3, 24), &[created(OTHER, CONTRACT)]).is_empty());
+    assert!(process(&pattern, &mut partials, &block(4, 36), &[created(ACCOUNT, CONTRACT)]).is_empty());
+    assert_eq!(partials.len(), 2);
+
+    // And the call has to be to the contract it created
+    assert!(process(&pattern, &mut partials, &block(5, 48), &[called(OTHER)]).is_empty());
+    let signals = process(&pattern, &mut partials, &block(6, 60), &[called(CONTRACT)]);
+    assert_eq!(signals.len(), 1);
+    assert_eq!(signals[0]["account"], ACCOUNT);
+    assert_eq!(signals[0]["contract"], CONTRACT);
+    assert_eq!(signals[0]["caller"], OTHER);
+    let sequence: Vec<u64> = signals[0]["sequence"].as_array().unwrap().iter().map(|s| s["block"].as_u64().unwrap()).collect();
+    assert_eq!(sequence, vec![2, 4, 6]);
+
+    // Every later call fires again
+    assert_eq!(process(&pattern, &mut partials, &block(7, 72), &[called(CONTRACT)]).len(), 1);
+
+    // However long after the funding, the builtin has no window
+    let year = 365 * 24 * 60 * 60;
+    assert_eq!(process(&pattern, &mut partials, &block(200, year), &[called(CONTRACT)]).len(), 1);
+}
+
+#[test]
+fn correlation_same_block_test() {
+    // Steps are taken in the order the block's events found them
+    let pattern = AnonymouslyFundedSmartContractTriggeredSignal::pattern();
+    let mut partials = Vec::new();
+    let matches = [funded(ACCOUNT), created(ACCOUNT, CONTRACT), called(CONTRACT)];
+    assert_eq!(process(&pattern, &mut partials, &block(1, 0), &matches).len(), 1);
+}
+
+#[test]
+fn correlation_window_test() {
+    let meta = DetectorMeta {
+        id: 100,
+        name: "funded_then_created".into(),
+        version: 1,
+        inputs: &[Input::Events],
+        output: &[],
+    };
+    let pattern = Pattern::new(meta)
+        .then(Step::new(&[TornadoCashWithdrawEvent::ID, TransferFromFixedFloatEvent::ID]).with_key("account", "recipient"))
+        .then(Step::new(&[SuspiciousContractCreatedEvent::ID]).with_key("account", "creator"))
+        .within_blocks(10)
+        .within(Duration::from_secs(60));
+    let mut partials = Vec::new();
+
+    process(&pattern, &mut partials, &block(1, 0), &[funded(ACCOUNT)]);
+    assert_eq!(process(&pattern, &mut partials, &block(11, 60), &[created(ACCOUNT, CONTRACT)]).len(), 1);
+    // Expired on blocks
+    assert!(process(&pattern, &mut partials, &block(12, 61), &[created(ACCOUNT, CONTRACT)]).is_empty());
+    assert!(partials.is_empty());
+
+    // Expired on time
+    process(&pattern, &mut partials, &block(20, 100), &[funded(ACCOUNT)]);
+    assert!(process(&pattern, &mut partials, &block(21, 161), &[created(ACCOUNT, CONTRACT)]).is_empty());
+
+    // Funded again restarts the window, the older partial is kept until then and expires first
+    process(&pattern, &mut partials, &block(30, 200), &[funded(ACCOUNT)]);
+    process(&pattern, &mut partials, &block(35, 250), &[funded(ACCOUNT)]);
+    assert_eq!(partials.len(), 2);
+    assert_eq!(process(&pattern, &mut partials, &block(42, 300), &[created(ACCOUNT, CONTRACT)]).len(), 1);
+}
+
+#[test]
+fn correlation_state_test() {
+    // Partials live in the state store, a reorg takes back what the block advanced
+    let pattern = AnonymouslyFundedSmartContractTriggeredSignal::pattern();
+    let mut state = StateStore::new();
+    let mut signals: Vec<Match> = Vec::new();
+    state.correlate(&pattern, &block(1, 0), &[funded(ACCOUNT)], &mut signals);
+    state.correlate(&pattern, &block(2, 12), &[created(ACCOUNT, CONTRACT)], &mut signals);
+    assert_eq!(state.partials(pattern.meta.id).len(), 2);
+
+    assert!(state.revert(block(2, 12).hash()));
+    assert_eq!(state.partials(pattern.meta.id).len(), 1);
+    state.correlate(&pattern, &block(3, 24), &[called(CONTRACT)], &mut signals);
+    assert!(signals.is_empty());
+}
+
+#[test]
+fn correlation_replaced_reorg_test() {
+    // Funded again in a block that's then reorged out, the first funding still counts
+    let pattern = AnonymouslyFundedSmartContractTriggeredSignal::pattern();
+    let mut state = StateStore::new();
+    let mut signals: Vec<Match> = Vec::new();
+    state.correlate(&pattern, &block(1, 0), &[funded(ACCOUNT)], &mut signals);
+    state.finalize(block(1, 0).hash());
+    state.correlate(&pattern, &block(2, 12), &[funded(ACCOUNT)], &mut signals);
+    assert_eq!(state.partials(pattern.meta.id).len(), 2);
+    assert!(state.revert(block(2, 12).hash()));
+    state.correlate(&pattern, &block(3, 24), &[created(ACCOUNT, CONTRACT)], &mut signals);
+
+    // Both being there fires once, from the latest start
+    state.correlate(&pattern, &block(4, 36), &[funded(ACCOUNT)], &mut signals);
+    state.correlate(&pattern, &block(5, 48), &[created(ACCOUNT, CONTRACT)], &mut signals);
+    state.correlate(&pattern, &block(6, 60), &[called(CONTRACT)], &mut signals);
+    assert_eq!(signals.len(), 1);
+    let sequence: Vec<u64> = signals[0].1["sequence"].as_array().unwrap().iter().map(|s| s["block"].as_u64().unwrap()).collect();
+    assert_eq!(sequence, vec![4, 5, 6]);
+
+    // Once the replacing blocks are final the older ones go
+    for number in 3..=6 {
+        state.finalize(block(number, 0).hash());
+    }
+    let partials = state.partials(pattern.meta.id);
+    assert_eq!(partials.len(), 2);
+    assert!(partials.iter().all(|partial| partial.steps[0].block == 4));
+}
@@ -0,0 +1,121 @@
+use std::{fs::File, io::BufReader};
+
+use async_trait::async_trait;
+use insolvent_detect_signal::{
+    api::{HttpJsonRpc, ProviderConfig},
+    chain::ChainConfig,
+    model::Block,
+    registry::{DetectorRegistry, RegistryError},
+    sink::{Match, Sink},
+    types::{
+        AnonymouslyFundedSmartContractTriggeredSignal, BlockContext, DetectorMeta, Event, Input,
+        TornadoCashWithdrawEvent, TransferFromFixedFloatEvent,
+    },
+};
+use serde_json::json;
+
+/// Stands in for a detector living in another crate
+struct TxCountEvent {
+    id: u32,
+}
+
+#[async_trait]
+impl Event for TxCountEvent {
+    fn meta(&self) -> DetectorMeta {
+        DetectorMeta {
+            id: self.id,
+            name: "tx_count".into(),
+            version: 1,
+            inputs: &[Input::Block],
+            output: &["tx_count"],
+        }
+    }
+
+    async fn event(&self, ctx: &BlockContext<'_>, sink: &mut (dyn Sink + Send)) {
+        sink.push(self.id, json!({"tx_count": ctx.block.transactions.len()}));
+    }
+}
+
+fn chain() -> ChainConfig {
+    ChainConfig::known(ChainConfig::MAINNET, ProviderConfig::new("http://localhost:8545")).unwrap()
+}
+
+#[test]
+fn detector_registry_builtin_test() {
+    let registry = DetectorRegistry::builtin();
+    let detectors = registry.build(&chain()).unwrap();
+    // Everything runs unless the chain says otherwise
+    let mut ids: Vec<u32> = detectors.metas().iter().map(|meta| meta.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+    let names: Vec<&str> = registry.names().collect();
+    for meta in detectors.metas() {
+        assert!(names.contains(&meta.name.as_ref()), "{}", meta.name);
+    }
+
+    let chain = chain().with_detectors(vec![
+        "tornado_cash_withdraw".to_string(),
+        "anonymously_funded_smart_contract_triggered".to_string(),
+    ]);
+    let detectors = registry.build(&chain).unwrap();
+    assert_eq!(detectors.events.len(), 1);
+    assert_eq!(detectors.events[0].meta().id, TornadoCashWithdrawEvent::ID);
+    assert!(detectors.signals.is_empty());
+    assert_eq!(detectors.patterns.len(), 1);
+    assert_eq!(detectors.patterns[0].meta.id, AnonymouslyFundedSmartContractTriggeredSignal::ID);
+
+    let chain = chain.with_detectors(vec!["tornado_cash".to_string()]);
+    assert_eq!(
+        registry.build(&chain).err(),
+        Some(RegistryError::UnknownDetector("tornado_cash".to_string()))
+    );
+}
+
+#[test]
+fn detector_registry_duplicate_id_test() {
+    let registry = DetectorRegistry::builtin().with_event("tx_count", |_| {
+        Box::new(TxCountEvent {
+            id: TransferFromFixedFloatEvent::ID,
+        })
+    });
+    assert_eq!(
+        registry.build(&chain()).err(),
+        Some(RegistryError::DuplicateId {
+            id: TransferFromFixedFloatEvent::ID,
+            first: "transfer_from_fixed_float".to_string(),
+            second: "tx_count".to_string(),
+        })
+    );
+    // Checked even when one of them is disabled
+    let chain = chain().with_detectors(vec!["tx_count".to_string()]);
+    assert!(matches!(registry.build(&chain), Err(RegistryError::DuplicateId { .. })));
+
+    // Registering under the same name replaces it
+    let registry = registry.with_event("tx_count", |_| Box::new(TxCountEvent { id: 100 }));
+    assert!(registry.build(&chain).is_ok());
+}
+
+#[tokio::test]
+async fn detector_registry_custom_event_test() {
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
+    let file = File::open("tests/__data__/tornado_cash_block_response.json").unwrap();
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+
+    let registry = DetectorRegistry::builtin().with_event("tx_count", |_| Box::new(TxCountEvent { id: 100 }));
+    let chain = chain().with_detectors(vec!["tx_count".to_string(), "tornado_cash_withdraw".to_string()]);
+    let detectors = registry.build(&chain).unwrap();
+
+    let mut matches: Vec<Match> = Vec::new();
+    let ctx = BlockContext::new(1, &block, &api);
+    for event in &detectors.events {
+        event.event(&ctx, &mut matches).await;
+    }
+    let ids: Vec<u32> = matches.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![TornadoCashWithdrawEvent::ID, 100]);
+    assert_eq!(matches[1].1["tx_count"], block.transactions.len());
+
+    // Metas are what the API lists
+    let meta = serde_json::to_value(detectors.metas()).unwrap();
+    assert_eq!(meta[1], json!({"id": 100, "name": "tx_count", "version": 1, "inputs": ["block"], "output": ["tx_count"]}));
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{address, hex, Address, Bytes, B256, U256};
+use insolvent_detect_signal::{
+    api::{HttpJsonRpc, ProviderConfig},
+    expr::{
+        abi,
+        builtins::{namehash, StaticCall, ENS_REGISTRY},
+        Expr, ExprError, Scope, Type, Value,
+    },
+    model::Block,
+    rules::{RuleSet, TRANSFER_TOPIC},
+    sink::Match,
+    types::{BlockContext, Event},
+};
+use serde_json::{json, Value as Json};
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const RICH: Address = address!("864e656c57a5a119f332c47326a35422294db5c9");
+const TOKEN: Address = address!("dac17f958d2ee523a2206206994597c13d831ec7");
+const RESOLVER: Address = address!("4976fb03c32e5b8cfe2b6ccb31c09ba78ebaba41");
+const VITALIK: Address = address!("d8da6bf26964af9d7eed9e10e9d0dc2e9b8c8e1f");
+
+/// Right aligned in 32 bytes, like a returned value.
+fn word(bytes: &[u8]) -> String {
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    hex::encode_prefixed(word)
+}
+
+/// Answers the few calls the expressions below make.
+struct FakeNode;
+
+impl Respond for FakeNode {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let req: Json = serde_json::from_slice(&request.body).unwrap();
+        let params = &req["params"];
+        let result = match req["method"].as_str().unwrap() {
+            "eth_getBalance" if params[0] == RICH.to_string().to_lowercase() => json!("0xad78ebc5ac6200000"),
+            "eth_getBalance" => json!("0xde0b6b3a7640000"),
+            "eth_getCode" if params[0] == TOKEN.to_string().to_lowercase() => json!("0x6080"),
+            "eth_getCode" => json!("0x"),
+            "eth_getStorageAt" => json!(word(&[0x2a])),
+            "eth_call" => {
+                let to: Address = params[0]["to"].as_str().unwrap().parse().unwrap();
+                let input = hex::decode(params[0]["input"].as_str().unwrap()).unwrap();
+                let node = namehash("vitalik.eth");
+                match (to, hex::encode(&input[..4]).as_str()) {
+                    (ENS_REGISTRY, "0178b8bf") if input[4..] == node[..] => json!(word(RESOLVER.as_slice())),
+                    (ENS_REGISTRY, "0178b8bf") => json!(word(&[])),
+                    (RESOLVER, "3b3b57de") => json!(word(VITALIK.as_slice())),
+                    // decimals()
+                    (TOKEN, "313ce567") => json!(word(&[6])),
+                    // balanceOf(address)
+                    (TOKEN, "70a08231") => {
+                        assert_eq!(&input[16..], RICH.as_slice());
+                        json!(word(&[0x03, 0xe8]))
+                    }
+                    // name()
+                    (TOKEN, "06fdde03") => {
+                        json!(hex::encode_prefixed(DynSolValue::Tuple(vec![DynSolValue::String("Tether USD".into())]).abi_encode_params()))
+                    }
+                    call => panic!("unexpected call {:?}", call),
+                }
+            }
+            "eth_getLogs" => {
+                assert_eq!(params[0]["topics"][0][0], TRANSFER_TOPIC.to_string());
+                // Only the `to` position is filtered
+                assert!(params[0]["topics"][1].is_null());
+                assert_eq!(params[0]["topics"][2][0], word(RICH.as_slice()));
+                json!([{
+                    "address": TOKEN.to_string().to_lowercase(),
+                    "topics": [TRANSFER_TOPIC.to_string(), word(VITALIK.as_slice()), word(RICH.as_slice())],
+                    "data": word(&[0x05]),
+                }])
+            }
+            method => panic!("unexpected {}", method),
+        };
+        ResponseTemplate::new(200).set_body_json(json!({"jsonrpc": "2.0", "id": req["id"], "result": result}))
+    }
+}
+
+async fn api() -> (MockServer, HttpJsonRpc) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(FakeNode).mount(&server).await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+    (server, api)
+}
+
+fn block() -> Block {
+    common::block("fixed_float_deposit_response.json")
+}
+
+async fn eval(api: &HttpJsonRpc, source: &str) -> Value {
+    Expr::parse(source).unwrap().eval(&Scope::new(1, api)).await.unwrap()
+}
+
+async fn eval_error(api: &HttpJsonRpc, source: &str) -> String {
+    Expr::parse(source).unwrap().eval(&Scope::new(1, api)).await.unwrap_err().to_string()
+}
+
+#[test]
+fn expr_type_check_test() {
+    let ty = |source: &str| Expr::parse(source).unwrap().ty().clone();
+    assert_eq!(ty("get_balance(tx.to) > 100 ether && !is_contract(tx.from)"), Type::Bool);
+    assert_eq!(ty("1.5 gwei + 0x10 * 2 - tx.value % 3"), Type::Int);
+    assert_eq!(ty("resolve_name(\"vitalik.eth\")"), Type::Address);
+    assert_eq!(ty("get_contract_events(tx.to, \"event Transfer(address indexed from, address indexed to, uint value)\", \"\")[0].value"), Type::Abi);
+    // Literal return types give the call a type
+    assert_eq!(ty("static_call(tx.to, \"decimals -> uint8\", \"\", \"\")"), Type::Int);
+    assert_eq!(ty("static_call(tx.to, \"name\", \"\", \"\")"), Type::Bytes);
+    assert_eq!(ty("static_call_hash_repr(tx.to, \"0x06fdde03 -> string\", \"\", \"\")"), Type::String);
+
+    let error = |source: &str| Expr::parse(source).unwrap_err();
+    assert!(matches!(error("get_balance(1)"), ExprError::Type { pos: 12, .. }));
+    assert!(matches!(error("1 + true"), ExprError::Type { .. }));
+    assert!(matches!(error("tx.sender"), ExprError::Type { .. }));
+    assert!(matches!(error("get_balances(tx.to)"), ExprError::Type { .. }));
+    assert!(matches!(error("is_contract(tx.to, tx.from)"), ExprError::Type { .. }));
+    assert!(matches!(error("1.5 > 1"), ExprError::Syntax { pos: 0, .. }));
+    assert!(matches!(error("0.1 wei"), ExprError::Syntax { .. }));
+    assert!(matches!(error("tx.value >"), ExprError::Syntax { pos: 10, .. }));
+    assert!(matches!(error("\"a"), ExprError::Syntax { .. }));
+    // ethabi literals are checked up front
+    assert!(error("static_call(tx.to, \"balanceOf\", \"address\", \"0x1234\")").to_string().contains("invalid address"));
+    assert!(error("static_call_hash(tx.to, \"balanceOf\", \"\", \"\")").to_string().contains("selector"));
+    assert!(error("get_contract_events(tx.to, \"event Transfer(address indexed from)\", \"([],[])\")").to_string().contains("expected"));
+    assert!(Expr::condition("tx.value + 1").is_err());
+}
+
+#[test]
+fn expr_abi_literal_test() {
+    let types = abi::parse_types("address, uint256[], (bool,string), bytes4, int8").unwrap();
+    let values = abi::parse_values(
+        &types,
+        "0x864e656c57a5a119f332c47326a35422294db5c9, [1, 0x10, 1.5 gwei], (true, \"a, \\\"b\\\"\"), a9059cbb, -128",
+    )
+    .unwrap();
+    assert_eq!(values[0], DynSolValue::Address(RICH));
+    assert_eq!(
+        values[1],
+        DynSolValue::Array(vec![
+            DynSolValue::Uint(U256::from(1), 256),
+            DynSolValue::Uint(U256::from(16), 256),
+            DynSolValue::Uint(U256::from(1_500_000_000u64), 256),
+        ])
+    );
+    let formatted: Vec<String> = values.iter().map(abi::format_value).collect();
+    assert_eq!(
+        formatted,
+        vec!["0x864e656c57a5a119f332c47326a35422294db5c9", "[1,16,1500000000]", "(true,\"a, \\\"b\\\"\")", "0xa9059cbb", "-128"]
+    );
+    // Formatting gives back what parses
+    for (ty, value) in types.iter().zip(&values) {
+        assert_eq!(&abi::parse_value(ty, &abi::format_value(value)).unwrap(), value);
+    }
+
+    // Top level strings are bare
+    assert_eq!(abi::parse_values(&[DynSolType::String], "a, b").unwrap(), vec![DynSolValue::String("a, b".into())]);
+    assert!(abi::parse_value(&DynSolType::Int(8), "128").is_err());
+    assert!(abi::parse_value(&DynSolType::Uint(8), "256").is_err());
+    assert!(abi::parse_value(&DynSolType::FixedArray(Box::new(DynSolType::Bool), 2), "[true]").is_err());
+    assert!(abi::parse_values(&[DynSolType::Bool, DynSolType::Bool], "true").is_err());
+
+    let call = StaticCall::parse("balanceOf -> uint256", false, "address", "864e656c57a5a119f332c47326a35422294db5c9").unwrap();
+    assert_eq!(&call.calldata()[..4], hex!("70a08231"));
+    assert_eq!(call.calldata().len(), 36);
+    assert_eq!(call.decode(&B256::with_last_byte(7)[..]).unwrap(), DynSolValue::Uint(U256::from(7), 256));
+
+    assert_eq!(namehash(""), B256::ZERO);
+    assert_eq!(namehash("eth"), B256::new(hex!("93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")));
+    assert_eq!(namehash("vitalik.eth"), B256::new(hex!("ee6c4522aab0003e8d14cd40a6af439055fd2577951148c14b6cea9a53475835")));
+}
+
+#[tokio::test]
+async fn expr_chain_access_test() {
+    let (server, api) = api().await;
+    assert_eq!(eval(&api, "get_balance(0x864e656c57a5a119f332c47326a35422294db5c9) > 100 ether").await, Value::Bool(true));
+    assert_eq!(eval(&api, "get_balance(0x4976fb03c32e5b8cfe2b6ccb31c09ba78ebaba41)").await, Value::Int(U256::from(10).pow(U256::from(18))));
+    assert_eq!(eval(&api, "is_contract(0xdac17f958d2ee523a2206206994597c13d831ec7)").await, Value::Bool(true));
+    assert_eq!(eval(&api, "read_slot(0xdac17f958d2ee523a2206206994597c13d831ec7, 0)").await, Value::Int(U256::from(42)));
+    assert_eq!(eval(&api, "resolve_name(\"Vitalik.eth\")").await, Value::Address(VITALIK));
+    assert!(eval_error(&api, "resolve_name(\"nobody.eth\")").await.contains("no resolver"));
+
+    let token = "0xdac17f958d2ee523a2206206994597c13d831ec7";
+    let balance = format!(
+        "static_call({}, \"balanceOf -> uint256\", \"address\", \"0x864e656c57a5a119f332c47326a35422294db5c9\")",
+        token
+    );
+    assert_eq!(eval(&api, &balance).await, Value::Int(U256::from(1000)));
+    let decimals = format!("static_call_hash({}, \"313ce567 -> uint8\", \"\", \"\")", token);
+    assert_eq!(eval(&api, &decimals).await, Value::Int(U256::from(6)));
+    let decimals = format!("static_call_hash_repr({}, \"0x313ce567 -> uint8\", \"\", \"\")", token);
+    assert_eq!(eval(&api, &decimals).await, Value::String("6".into()));
+    assert_eq!(eval(&api, &format!("static_call_repr({}, \"name -> string\", \"\", \"\")", token)).await, Value::String("Tether USD".into()));
+    // No return types is the raw output
+    let Value::Bytes(raw) = eval(&api, &format!("static_call({}, \"decimals\", \"\", \"\")", token)).await else {
+        panic!("expected bytes");
+    };
+    assert_eq!(raw, Bytes::from(B256::with_last_byte(6).to_vec()));
+
+    let events = format!(
+        "get_contract_events({}, \"event Transfer(address indexed from, address indexed to, uint value)\", \"([],[0x864e656c57a5a119f332c47326a35422294db5c9])\")",
+        token
+    );
+    assert_eq!(eval(&api, &format!("len({})", events)).await, Value::Int(U256::from(1)));
+    assert_eq!(eval(&api, &format!("{}[0].from", events)).await, Value::Address(VITALIK));
+    assert_eq!(eval(&api, &format!("{}[0][\"value\"] == 5", events)).await, Value::Bool(true));
+
+    // Short circuits, the right side would have asked the node
+    let before = server.received_requests().await.unwrap().len();
+    assert_eq!(eval(&api, "false && get_balance(0x864e656c57a5a119f332c47326a35422294db5c9) > 0").await, Value::Bool(false));
+    assert_eq!(eval(&api, "true || is_contract(0x864e656c57a5a119f332c47326a35422294db5c9)").await, Value::Bool(true));
+    assert_eq!(server.received_requests().await.unwrap().len(), before);
+
+    assert!(eval_error(&api, "1 / 0").await.contains("division by zero"));
+    assert!(eval_error(&api, "1 - 2").await.contains("underflow"));
+    // Not bound outside a transaction
+    assert!(eval_error(&api, "tx.value > 0").await.contains("isn't set"));
+}
+
+#[tokio::test]
+async fn expr_transaction_scope_test() {
+    let (_server, api) = api().await;
+    let block = block();
+    let transaction = &block.transactions[0];
+    let scope = Scope::new(1, &api).with_block(&block).with_transaction(transaction);
+    let expr = Expr::condition("tx.hash == tx.hash && block.number > 0 && chain_id == 1 && len(tx.input) >= 0").unwrap();
+    assert!(expr.test(&scope).await.unwrap());
+    let value = Expr::parse("tx.value").unwrap().eval(&scope).await.unwrap();
+    assert_eq!(value, Value::Int(transaction.value));
+    assert_eq!(value.to_string(), transaction.value.to_string());
+}
+
+#[tokio::test]
+async fn expr_rule_condition_test() {
+    let (_server, api) = api().await;
+    let block = block();
+    let rule = |condition: &str| {
+        let toml = format!(
+            "[[rules]]\nname = \"fresh_recipient\"\nid = 100\nfrom = [\"0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f\"]\ncondition = '{}'",
+            condition
+        );
+        RuleSet::parse(&toml).unwrap().rules().next().unwrap().clone()
+    };
+
+    // The fixture's FixedFloat transfer goes to an account with 1 ETH
+    let mut matches: Vec<Match> = Vec::new();
+    let ctx = BlockContext::new(1, &block, &api);
+    rule("get_balance(tx.to) > 100 ether").event(&ctx, &mut matches).await;
+    assert!(matches.is_empty());
+    rule("get_balance(tx.to) < 100 ether && !is_contract(tx.to)").event(&ctx, &mut matches).await;
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].1["to"], "0x3695a1a579e89f0fc8142ed701564ca32aa61ac2");
+    rule("tx.value > 2.5 ether").event(&ctx, &mut matches).await;
+    assert_eq!(matches.len(), 1);
+
+    // Conditions are type checked when the rule loads
+    let invalid = RuleSet::parse("[[rules]]\nname = \"a\"\nid = 100\ncondition = 'tx.value'").unwrap_err();
+    assert!(invalid.to_string().contains("condition"));
+    // A condition on its own is a predicate
+    assert!(RuleSet::parse("[[rules]]\nname = \"a\"\nid = 100\ncondition = 'tx.value > 1 ether'").is_ok());
+}
@@ -1,22 +1,30 @@
-use std::{fs::File, io::BufReader, collections::HashSet};
+use std::{fs::File, io::BufReader};
 
-use insolvent_detect_signal::{types::{BlockJson, Event, TransferFromFixedFloatEvent}, api::InfuraAPIHttp};
+use alloy_primitives::B256;
+use insolvent_detect_signal::{model::Block, sink::Match, types::{BlockContext, Event, TransferFromFixedFloatEvent}, api::{HttpJsonRpc, ProviderConfig}};
 
 #[tokio::test]
 async fn fixed_float_deposit_response_test() {
     // Load block from file that has a transaction that deposits from fixed float, this
     // function shoould detect that
-    let mut event_id = u32::MAX;
+    let mut matches: Vec<Match> = Vec::new();
 
-    let api = InfuraAPIHttp;
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
     let file = File::open("tests/__data__/fixed_float_deposit_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_block = BlockJson::new(value);
-    let transfer_from_fixed_float = Event::TransferFromFixedFloat(TransferFromFixedFloatEvent);
-    if let Some(event) = transfer_from_fixed_float.event(&api, &json_block, &HashSet::new()).await {
-        // Fixed float deposit has id of 2
-        event_id = event.0;
-    }
-    assert_eq!(event_id, 2);
+    let mut json_block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    let transfer_from_fixed_float = TransferFromFixedFloatEvent::default();
+    transfer_from_fixed_float.event(&BlockContext::new(1, &json_block, &api), &mut matches).await;
+    // One transfer in the block, fixed float deposit has id of 2
+    let ids: Vec<u32> = matches.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![2]);
+
+    // Every transfer out of the wallet is reported, not just the first
+    let hash: B256 = matches[0].1["transaction_hash"].as_str().unwrap().parse().unwrap();
+    let transfer = json_block.transactions.iter().find(|tx| tx.hash == hash).unwrap().clone();
+    json_block.transactions.extend([transfer.clone(), transfer]);
+    let mut matches: Vec<Match> = Vec::new();
+    transfer_from_fixed_float.event(&BlockContext::new(1, &json_block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 3);
 }
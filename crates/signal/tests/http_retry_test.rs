@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use insolvent_detect_signal::api::{EthJsonRpc, HttpJsonRpc, ProviderConfig, RateLimit, RetryPolicy};
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+fn fast_retry(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        multiplier: 2,
+        jitter: true,
+    }
+}
+
+fn receipt_response() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "result": { "contractAddress": "0x03e7b13bcd9b8383f403696c1494845560607eca" }
+    }))
+}
+
+#[tokio::test]
+async fn http_retry_server_error_test() {
+    // Two 5xx responses then success, the request should succeed on the third attempt
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).respond_with(receipt_response()).mount(&server).await;
+
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri()).with_retry(fast_retry(3))).unwrap();
+    let receipt = api.get_transaction_receipt("0x1").await.unwrap();
+    assert_eq!(
+        receipt["result"]["contractAddress"].as_str().unwrap(),
+        "0x03e7b13bcd9b8383f403696c1494845560607eca"
+    );
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn http_retry_exhausted_test() {
+    // 429 on every attempt, we should give up after max_retries and return an error
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri()).with_retry(fast_retry(2))).unwrap();
+    assert!(api.get_transaction_receipt("0x1").await.is_err());
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn http_retry_limit_exceeded_test() {
+    // JSON-RPC -32005 comes back with a 200 status but should still be retried
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "error": { "code": -32005, "message": "limit exceeded" }
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).respond_with(receipt_response()).mount(&server).await;
+
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri()).with_retry(fast_retry(3))).unwrap();
+    assert!(api.get_transaction_receipt("0x1").await.is_ok());
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn http_retry_timeout_test() {
+    // Slow response hits the client timeout and is retried
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(receipt_response().set_delay(Duration::from_millis(500)))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST")).respond_with(receipt_response()).mount(&server).await;
+
+    let config = ProviderConfig::new(&server.uri())
+        .with_timeout(Duration::from_millis(100))
+        .with_retry(fast_retry(3));
+    let api = HttpJsonRpc::new(config).unwrap();
+    assert!(api.get_transaction_receipt("0x1").await.is_ok());
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn http_no_retry_client_error_test() {
+    // 4xx other than 429 isn't transient so we shouldn't retry
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(ResponseTemplate::new(401)).mount(&server).await;
+
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri()).with_retry(fast_retry(3))).unwrap();
+    assert!(api.get_transaction_receipt("0x1").await.is_err());
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn http_rate_limit_test() {
+    // Burst of two then 10 per second, so six requests need at least ~400ms
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(receipt_response()).mount(&server).await;
+
+    let config = ProviderConfig::new(&server.uri()).with_rate_limit(RateLimit::per_second(10.0).with_burst(2));
+    let api = HttpJsonRpc::new(config).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..6 {
+        api.get_transaction_receipt("0x1").await.unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(350));
+    assert_eq!(server.received_requests().await.unwrap().len(), 6);
+}
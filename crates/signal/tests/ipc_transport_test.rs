@@ -0,0 +1,150 @@
+#![cfg(unix)]
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use insolvent_detect_signal::api::{
+    EthJsonRpc, EthPubSub, IpcJsonRpc, JsonRpcBatch, ProviderConfig, RetryPolicy,
+};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+type ServerSocket = BufReader<UnixStream>;
+
+/// Fresh socket path per test so parallel tests don't fight over it.
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("insolvent-{}-{}.ipc", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn config(path: &Path) -> ProviderConfig {
+    ProviderConfig::new(path.to_str().unwrap()).with_retry(RetryPolicy {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        multiplier: 2,
+        jitter: false,
+    })
+}
+
+async fn accept(listener: &UnixListener) -> ServerSocket {
+    let (stream, _) = listener.accept().await.unwrap();
+    BufReader::new(stream)
+}
+
+async fn next_request(socket: &mut ServerSocket) -> Value {
+    let mut line = String::new();
+    socket.read_line(&mut line).await.unwrap();
+    serde_json::from_str(&line).unwrap()
+}
+
+async fn reply(socket: &mut ServerSocket, value: Value) {
+    socket.get_mut().write_all(value.to_string().as_bytes()).await.unwrap();
+}
+
+/// Keeps the server side open until the client hangs up.
+async fn wait_closed(socket: &mut ServerSocket) {
+    let mut line = String::new();
+    while socket.read_line(&mut line).await.unwrap_or(0) > 0 {
+        line.clear();
+    }
+}
+
+#[tokio::test]
+async fn ipc_config_test() {
+    // Plain paths are IPC, everything with a scheme goes elsewhere
+    assert!(ProviderConfig::new("/var/run/reth/reth.ipc").is_ipc());
+    assert!(!ProviderConfig::new("http://127.0.0.1:8545").is_ipc());
+    assert!(!ProviderConfig::new("ws://127.0.0.1:8546").is_ipc());
+    assert!(IpcJsonRpc::connect(ProviderConfig::new("http://127.0.0.1:8545")).await.is_err());
+}
+
+#[tokio::test]
+async fn ipc_call_and_batch_test() {
+    // Responses are written without newlines and split across writes, the client has to find the
+    // value boundaries itself
+    let path = socket_path("call");
+    let listener = UnixListener::bind(&path).unwrap();
+
+    tokio::spawn(async move {
+        let mut socket = accept(&listener).await;
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_getTransactionReceipt");
+        let response = json!({"jsonrpc": "2.0", "id": req["id"], "result": {"transactionHash": "0xa"}}).to_string();
+        let (head, tail) = response.split_at(10);
+        socket.get_mut().write_all(head.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        socket.get_mut().write_all(tail.as_bytes()).await.unwrap();
+
+        let req = next_request(&mut socket).await;
+        let items = req.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        let responses: Vec<Value> = items
+            .iter()
+            .rev()
+            .map(|item| json!({"jsonrpc": "2.0", "id": item["id"], "result": {"transactionHash": item["params"][0]}}))
+            .collect();
+        reply(&mut socket, Value::Array(responses)).await;
+        wait_closed(&mut socket).await;
+    });
+
+    let api = IpcJsonRpc::connect(config(&path)).await.unwrap();
+    let receipt = api.get_transaction_receipt("0xa").await.unwrap();
+    assert_eq!(receipt["result"]["transactionHash"], "0xa");
+
+    let batch = JsonRpcBatch::new().get_transaction_receipt("0xb").get_transaction_receipt("0xc");
+    let results = api.batch(batch).await.unwrap();
+    assert_eq!(results[0].as_ref().unwrap()["result"]["transactionHash"], "0xb");
+    assert_eq!(results[1].as_ref().unwrap()["result"]["transactionHash"], "0xc");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn ipc_new_heads_resubscribe_test() {
+    // Node restarts after one head, the stream should carry on once the socket is back
+    let path = socket_path("heads");
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let server = tokio::spawn(async move {
+        let mut socket = accept(&listener).await;
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_subscribe");
+        assert_eq!(req["params"][0], "newHeads");
+        // Both in one write, the client has to split them
+        let ack = json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xa"});
+        let head = json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0xa", "result": {"number": "0x1"}}});
+        socket.get_mut().write_all(format!("{}\n{}\n", ack, head).as_bytes()).await.unwrap();
+        drop(socket);
+
+        let mut socket = accept(&listener).await;
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_subscribe");
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xb"})).await;
+        reply(
+            &mut socket,
+            json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0xb", "result": {"number": "0x2"}}}),
+        )
+        .await;
+        wait_closed(&mut socket).await;
+    });
+
+    let api = IpcJsonRpc::connect(config(&path)).await.unwrap();
+    let mut heads = api.subscribe_new_heads().await.unwrap();
+
+    let first = tokio::time::timeout(Duration::from_secs(5), heads.next()).await.unwrap().unwrap();
+    assert_eq!(first["number"], "0x1");
+    let second = tokio::time::timeout(Duration::from_secs(5), heads.next()).await.unwrap().unwrap();
+    assert_eq!(second["number"], "0x2");
+
+    drop(heads);
+    drop(api);
+    tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use insolvent_detect_signal::api::{EthJsonRpc, HttpJsonRpc, JsonRpcBatch, ProviderConfig};
+use serde_json::{json, Value};
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Answers a batch in reverse order, with an error for the second request and nothing for the
+/// third.
+struct ReversedBatchResponder;
+
+impl Respond for ReversedBatchResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let requests: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+        let mut responses = Vec::new();
+        for (i, req) in requests.iter().enumerate().rev() {
+            match i {
+                1 => responses.push(json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": -32000, "message": "not found"}})),
+                2 => {}
+                _ => responses.push(json!({"jsonrpc": "2.0", "id": req["id"], "result": {"transactionHash": req["params"][0]}})),
+            }
+        }
+        ResponseTemplate::new(200).set_body_json(Value::Array(responses))
+    }
+}
+
+#[tokio::test]
+async fn json_rpc_batch_correlation_test() {
+    // Results should come back in request order with per-item errors, whatever order the node
+    // answers in
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(ReversedBatchResponder).mount(&server).await;
+
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+    let batch = JsonRpcBatch::new()
+        .get_transaction_receipt("0xa")
+        .get_transaction_receipt("0xb")
+        .get_transaction_receipt("0xc")
+        .get_transaction_receipt("0xd");
+    let results = api.batch(batch).await.unwrap();
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap()["result"]["transactionHash"], "0xa");
+    assert_eq!(results[1].as_ref().unwrap_err().code(), Some(-32000));
+    assert!(results[2].is_err());
+    assert_eq!(results[3].as_ref().unwrap()["result"]["transactionHash"], "0xd");
+
+    // One round trip, every request in the array has its own id
+    let received = server.received_requests().await.unwrap();
+    assert_eq!(received.len(), 1);
+    let sent: Vec<Value> = serde_json::from_slice(&received[0].body).unwrap();
+    let ids: HashSet<String> = sent.iter().map(|req| req["id"].to_string()).collect();
+    assert_eq!(ids.len(), 4);
+}
+
+#[tokio::test]
+async fn json_rpc_batch_empty_test() {
+    // Nothing to send so we shouldn't hit the provider
+    let server = MockServer::start().await;
+    let api = HttpJsonRpc::new(ProviderConfig::new(&server.uri())).unwrap();
+    assert!(api.batch(JsonRpcBatch::new()).await.unwrap().is_empty());
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
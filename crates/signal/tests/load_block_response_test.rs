@@ -1,6 +1,6 @@
 use std::{fs::File, io::BufReader};
 
-use insolvent_detect_signal::types::BlockJson;
+use insolvent_detect_signal::model::Block;
 
 #[test]
 fn load_block_response_test() {
@@ -10,9 +10,9 @@ fn load_block_response_test() {
     let file = File::open("tests/__data__/random_block_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_block = BlockJson::new(value);
-    for transaction in json_block.get_transactions() {
-        from_res.push(transaction.from().unwrap().to_string());
+    let json_block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    for transaction in &json_block.transactions {
+        from_res.push(transaction.from.to_string());
     }
-    assert!(from_res.len() > 0);
+    assert!(!from_res.is_empty());
 }
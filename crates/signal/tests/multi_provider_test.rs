@@ -0,0 +1,207 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use insolvent_detect_signal::api::{
+    BatchResponse, BlockTag, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, MultiJsonRpc, MultiMode, ProviderConfigError, RpcError,
+};
+use serde_json::{json, Value};
+
+/// Provider stand-in with a fixed head, block hash and latency.
+struct FakeProvider {
+    name: &'static str,
+    head: u64,
+    hash: &'static str,
+    fail: bool,
+    delay: Duration,
+    calls: Arc<AtomicUsize>,
+}
+
+impl FakeProvider {
+    fn boxed(name: &'static str, head: u64, hash: &'static str) -> (Box<dyn EthJsonRpc + Send + Sync>, Arc<AtomicUsize>) {
+        Self::boxed_with(name, head, hash, false, Duration::ZERO)
+    }
+
+    fn boxed_with(
+        name: &'static str,
+        head: u64,
+        hash: &'static str,
+        fail: bool,
+        delay: Duration,
+    ) -> (Box<dyn EthJsonRpc + Send + Sync>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = FakeProvider {
+            name,
+            head,
+            hash,
+            fail,
+            delay,
+            calls: calls.clone(),
+        };
+        (Box::new(provider), calls)
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for FakeProvider {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        if self.fail {
+            return Err(RpcError::Transport(format!("{} is down", self.name)));
+        }
+        let result = match req.method.as_str() {
+            "eth_blockNumber" => json!(format!("0x{:x}", self.head)),
+            // Empty hash is a provider that doesn't have the block yet
+            "eth_getBlockByNumber" if self.hash.is_empty() => Value::Null,
+            "eth_getBlockByNumber" => json!({"number": format!("0x{:x}", self.head), "hash": self.hash, "timestamp": "0x1", "transactions": []}),
+            _ => json!(self.name),
+        };
+        Ok(json!({"jsonrpc": "2.0", "id": req.id, "result": result}))
+    }
+
+    async fn batch(&self, _batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        if self.fail {
+            return Err(RpcError::Transport(format!("{} is down", self.name)));
+        }
+        Ok(vec![Ok(json!({"result": self.name}))])
+    }
+}
+
+/// Provider that answers everything with a JSON-RPC error.
+struct ErrorProvider {
+    code: i64,
+    message: &'static str,
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for ErrorProvider {
+    async fn request(&self, _req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        Err(RpcError::JsonRpc {
+            code: self.code,
+            message: self.message.to_string(),
+            data: None,
+        })
+    }
+
+    async fn batch(&self, _batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn multi_failover_error_test() {
+    // First provider is down, second should answer and the failure should show in health
+    let (down, _) = FakeProvider::boxed_with("down", 100, "0xa", true, Duration::ZERO);
+    let (up, _) = FakeProvider::boxed("up", 100, "0xa");
+    let api = MultiJsonRpc::new(vec![down, up], MultiMode::Failover { max_lag: 5 }).unwrap();
+
+    let res = api.get_transaction_receipt("0x1").await.unwrap();
+    assert_eq!(res["result"], "up");
+
+    let batch = api.batch(JsonRpcBatch::new().get_transaction_receipt("0x1")).await.unwrap();
+    assert_eq!(batch[0].as_ref().unwrap()["result"], "up");
+
+    let health = api.health();
+    assert_eq!(health[0].failures, 2);
+    assert_eq!(health[1].successes, 2);
+    assert!(health[0].score() < health[1].score());
+}
+
+#[tokio::test]
+async fn multi_failover_method_not_found_test() {
+    // A node without the method doesn't stop the others from being asked
+    let missing = Box::new(ErrorProvider {
+        code: RpcError::METHOD_NOT_FOUND_CODE,
+        message: "the method eth_getBlockReceipts does not exist/is not available",
+    });
+    let (up, calls) = FakeProvider::boxed("up", 100, "0xa");
+    let api = MultiJsonRpc::new(vec![missing, up], MultiMode::Failover { max_lag: 5 }).unwrap();
+    assert_eq!(api.get_transaction_receipt("0x1").await.unwrap()["result"], "up");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Invalid params would be the same anywhere, the first answer stands
+    let invalid = Box::new(ErrorProvider {
+        code: -32602,
+        message: "invalid argument 0",
+    });
+    let (up, calls) = FakeProvider::boxed("up", 100, "0xa");
+    let api = MultiJsonRpc::new(vec![invalid, up], MultiMode::Failover { max_lag: 5 }).unwrap();
+    assert!(matches!(
+        api.get_transaction_receipt("0x1").await,
+        Err(RpcError::JsonRpc { code: -32602, .. })
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn multi_failover_lag_test() {
+    // First provider is 10 blocks behind, once we know that it should be skipped
+    let (behind, behind_calls) = FakeProvider::boxed("behind", 100, "0xa");
+    let (synced, _) = FakeProvider::boxed("synced", 110, "0xb");
+    let api = MultiJsonRpc::new(vec![behind, synced], MultiMode::Failover { max_lag: 5 }).unwrap();
+
+    api.refresh_heads().await;
+    assert_eq!(api.health()[0].head, Some(100));
+    assert_eq!(api.health()[1].head, Some(110));
+
+    let before = behind_calls.load(Ordering::SeqCst);
+    let block = api.get_block_by_number_latest().await.unwrap();
+    assert_eq!(block["result"]["hash"], "0xb");
+    assert_eq!(behind_calls.load(Ordering::SeqCst), before);
+}
+
+#[tokio::test]
+async fn multi_fastest_test() {
+    let (slow, _) = FakeProvider::boxed_with("slow", 100, "0xa", false, Duration::from_millis(300));
+    let (fast, _) = FakeProvider::boxed("fast", 100, "0xa");
+    let api = MultiJsonRpc::new(vec![slow, fast], MultiMode::Fastest).unwrap();
+
+    let res = api.get_transaction_receipt("0x1").await.unwrap();
+    assert_eq!(res["result"], "fast");
+}
+
+#[tokio::test]
+async fn multi_quorum_test() {
+    // Two of three agree on the block hash so the block is returned
+    let (a, _) = FakeProvider::boxed("a", 100, "0xa");
+    let (b, _) = FakeProvider::boxed("b", 100, "0xa");
+    let (c, _) = FakeProvider::boxed("c", 100, "0xc");
+    let api = MultiJsonRpc::new(vec![a, b, c], MultiMode::Quorum { required: 2 }).unwrap();
+    let block = api.get_block_by_number_hash(100).await.unwrap();
+    assert_eq!(block["result"]["hash"], "0xa");
+
+    // Nobody agrees, an event shouldn't see the block
+    let (a, _) = FakeProvider::boxed("a", 100, "0xa");
+    let (b, _) = FakeProvider::boxed("b", 100, "0xb");
+    let (c, _) = FakeProvider::boxed_with("c", 100, "0xa", true, Duration::ZERO);
+    let api = MultiJsonRpc::new(vec![a, b, c], MultiMode::Quorum { required: 2 }).unwrap();
+    let err = api.get_block_by_number_hash(100).await.unwrap_err();
+    assert_eq!(err, RpcError::NoQuorum { required: 2, agreeing: 1 });
+
+    // Two lagging providers without the block don't agree on null
+    let (a, _) = FakeProvider::boxed("a", 99, "");
+    let (b, _) = FakeProvider::boxed("b", 99, "");
+    let (c, _) = FakeProvider::boxed("c", 100, "0xc");
+    let api = MultiJsonRpc::new(vec![a, b, c], MultiMode::Quorum { required: 2 }).unwrap();
+    let err = api.get_block_by_number_hash(100).await.unwrap_err();
+    assert_eq!(err, RpcError::NoQuorum { required: 2, agreeing: 1 });
+
+    // Nobody has it yet, that's no block rather than an error
+    let providers = (0..3).map(|_| FakeProvider::boxed("a", 99, "").0).collect();
+    let api = MultiJsonRpc::new(providers, MultiMode::Quorum { required: 2 }).unwrap();
+    assert!(api.get_block(BlockTag::Number(100)).await.unwrap().is_none());
+
+    // A quorum nothing can meet is refused up front
+    for required in [0, 4] {
+        let providers = (0..3).map(|_| FakeProvider::boxed("a", 100, "0xa").0).collect();
+        assert!(matches!(
+            MultiJsonRpc::new(providers, MultiMode::Quorum { required }),
+            Err(ProviderConfigError::InvalidQuorum { .. })
+        ));
+    }
+}
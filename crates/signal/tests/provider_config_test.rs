@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use insolvent_detect_signal::api::{HttpJsonRpc, ProviderAuth, ProviderConfig, ProviderConfigError};
+
+#[test]
+fn provider_config_endpoint_test() {
+    // Path tokens are appended to the url, other auth types leave the url alone
+    let infura = ProviderConfig::infura("abc");
+    assert_eq!(infura.endpoint().unwrap().as_str(), "https://mainnet.infura.io/v3/abc");
+
+    let alchemy = ProviderConfig::new("https://eth-mainnet.g.alchemy.com/v2")
+        .with_auth(ProviderAuth::PathToken("abc".to_string()));
+    assert_eq!(alchemy.endpoint().unwrap().as_str(), "https://eth-mainnet.g.alchemy.com/v2/abc");
+
+    let reth = ProviderConfig::new("http://127.0.0.1:8545").with_auth(ProviderAuth::Header {
+        name: "Authorization".to_string(),
+        value: "Bearer abc".to_string(),
+    });
+    assert_eq!(reth.endpoint().unwrap().as_str(), "http://127.0.0.1:8545/");
+
+    let api = HttpJsonRpc::new(reth.with_chain_id(31337).with_timeout(Duration::from_secs(5))).unwrap();
+    assert_eq!(api.chain_id(), 31337);
+    assert_eq!(api.config().timeout, Duration::from_secs(5));
+}
+
+#[test]
+fn provider_config_error_test() {
+    // Misconfiguration should be returned as an error when the provider is built, not panic
+    let bad_url = HttpJsonRpc::new(ProviderConfig::new("not a url"));
+    assert!(matches!(bad_url, Err(ProviderConfigError::InvalidUrl(_))));
+
+    let bad_scheme = HttpJsonRpc::new(ProviderConfig::new("ftp://127.0.0.1"));
+    assert!(matches!(bad_scheme, Err(ProviderConfigError::InvalidUrl(_))));
+
+    let ws_over_http = HttpJsonRpc::new(ProviderConfig::new("ws://127.0.0.1:8546"));
+    assert!(matches!(ws_over_http, Err(ProviderConfigError::InvalidUrl(_))));
+
+    let empty_token = HttpJsonRpc::new(ProviderConfig::infura(""));
+    assert!(matches!(empty_token, Err(ProviderConfigError::EmptyToken)));
+
+    let bad_header = HttpJsonRpc::new(ProviderConfig::new("http://127.0.0.1:8545").with_auth(ProviderAuth::Header {
+        name: "Bad Header".to_string(),
+        value: "abc".to_string(),
+    }));
+    assert!(matches!(bad_header, Err(ProviderConfigError::InvalidAuthHeader(_))));
+
+    let bad_chain = HttpJsonRpc::new(ProviderConfig::new("http://127.0.0.1:8545").with_chain_id(0));
+    assert!(matches!(bad_chain, Err(ProviderConfigError::InvalidChainId)));
+}
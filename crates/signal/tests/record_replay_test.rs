@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use insolvent_detect_signal::api::{
+    EthJsonRpc, HttpJsonRpc, JsonRpcBatch, ProviderConfig, RecordingJsonRpc, ReplayJsonRpc, RetryPolicy,
+};
+use serde_json::{json, Value};
+use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Receipt for `0xa`, nothing for `0xb` and an error for anything else.
+struct ReceiptNode;
+
+impl ReceiptNode {
+    fn answer(req: &Value) -> Value {
+        match req["params"][0].as_str() {
+            Some("0xa") => json!({"jsonrpc": "2.0", "id": req["id"], "result": {"transactionHash": "0xa"}}),
+            Some("0xb") => json!({"jsonrpc": "2.0", "id": req["id"], "result": null}),
+            _ => json!({"jsonrpc": "2.0", "id": req["id"], "error": {"code": -32000, "message": "bad hash"}}),
+        }
+    }
+}
+
+impl Respond for ReceiptNode {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body = match serde_json::from_slice::<Value>(&request.body).unwrap() {
+            Value::Array(reqs) => Value::Array(reqs.iter().map(Self::answer).collect()),
+            req => Self::answer(&req),
+        };
+        ResponseTemplate::new(200).set_body_json(body)
+    }
+}
+
+fn fixture(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("insolvent-replay-{}-{}.json", name, std::process::id()))
+}
+
+/// Node behind the recorder
+fn live(server: &MockServer) -> HttpJsonRpc {
+    HttpJsonRpc::new(ProviderConfig::new(&server.uri()).with_retry(RetryPolicy::none())).unwrap()
+}
+
+#[tokio::test]
+async fn record_then_replay_test() {
+    // Whatever the live node said, including nulls and errors, should come back the same offline
+    let path = fixture("roundtrip");
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(ReceiptNode).mount(&server).await;
+
+    let recorder = RecordingJsonRpc::new(live(&server), &path);
+    let single = recorder.get_transaction_receipt("0xa").await.unwrap();
+    let batch = JsonRpcBatch::new()
+        .get_transaction_receipt("0xa")
+        .get_transaction_receipt("0xb")
+        .get_transaction_receipt("0xc");
+    let recorded = recorder.batch(batch).await.unwrap();
+    recorder.save().unwrap();
+    drop(recorder);
+
+    // Nothing is written without a save
+    let unsaved = fixture("unsaved");
+    let recorder = RecordingJsonRpc::new(live(&server), &unsaved);
+    recorder.get_transaction_receipt("0xa").await.unwrap();
+    drop(recorder);
+    assert!(!unsaved.exists());
+    drop(server);
+
+    let replay = ReplayJsonRpc::new(&path).unwrap();
+    assert_eq!(replay.get_transaction_receipt("0xa").await.unwrap()["result"], single["result"]);
+
+    // Items can be replayed in a different batch than they were recorded in
+    let batch = JsonRpcBatch::new()
+        .get_transaction_receipt("0xc")
+        .get_transaction_receipt("0xb")
+        .get_transaction_receipt("0xa");
+    let replayed = replay.batch(batch).await.unwrap();
+    assert_eq!(replayed[0].as_ref().unwrap_err(), recorded[2].as_ref().unwrap_err());
+    assert!(replayed[1].as_ref().unwrap_err().is_null_result());
+    assert_eq!(replayed[2].as_ref().unwrap()["result"], recorded[0].as_ref().unwrap()["result"]);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+#[should_panic(expected = "No recorded response for eth_getTransactionReceipt")]
+async fn replay_unrecorded_test() {
+    let path = fixture("unrecorded");
+    std::fs::write(&path, "[]").unwrap();
+    let replay = ReplayJsonRpc::new(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let _ = replay.get_transaction_receipt("0xa").await;
+}
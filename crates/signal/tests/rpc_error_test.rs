@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use insolvent_detect_signal::api::{EthJsonRpc, HttpJsonRpc, ProviderConfig, RetryPolicy, RpcError};
+use serde_json::json;
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+async fn api_with(response: ResponseTemplate) -> (MockServer, HttpJsonRpc) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(response).mount(&server).await;
+    let config = ProviderConfig::new(&server.uri())
+        .with_retry(RetryPolicy::none())
+        .with_timeout(Duration::from_millis(200));
+    let api = HttpJsonRpc::new(config).unwrap();
+    (server, api)
+}
+
+#[tokio::test]
+async fn rpc_error_json_rpc_test() {
+    // Error object in the body used to come back as a successful block and panic in BlockJson
+    let (_server, api) = api_with(ResponseTemplate::new(200).set_body_json(json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "error": {"code": -32602, "message": "invalid argument", "data": "0x"}
+    })))
+    .await;
+
+    let err = api.get_block_by_number_latest().await.unwrap_err();
+    assert_eq!(
+        err,
+        RpcError::JsonRpc {
+            code: -32602,
+            message: "invalid argument".to_string(),
+            data: Some(json!("0x")),
+        }
+    );
+    assert!(!err.is_retryable());
+}
+
+#[tokio::test]
+async fn rpc_error_null_result_test() {
+    // Receipt for a transaction that isn't mined yet, not the same as the provider being down
+    let (_server, api) = api_with(ResponseTemplate::new(200).set_body_json(json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "result": null
+    })))
+    .await;
+
+    let err = api.get_transaction_receipt("0x1").await.unwrap_err();
+    assert_eq!(err, RpcError::NullResult("eth_getTransactionReceipt".to_string()));
+    assert!(err.is_null_result());
+}
+
+#[tokio::test]
+async fn rpc_error_http_test() {
+    let (_server, api) = api_with(ResponseTemplate::new(404)).await;
+    assert_eq!(api.get_transaction_receipt("0x1").await.unwrap_err(), RpcError::HttpStatus(404));
+
+    let (_server, api) = api_with(ResponseTemplate::new(200).set_body_string("not json")).await;
+    assert!(matches!(api.get_transaction_receipt("0x1").await.unwrap_err(), RpcError::Decode(_)));
+
+    let (_server, api) =
+        api_with(ResponseTemplate::new(200).set_body_json(json!({"result": {}})).set_delay(Duration::from_secs(1))).await;
+    assert_eq!(api.get_transaction_receipt("0x1").await.unwrap_err(), RpcError::Timeout);
+}
+
+#[tokio::test]
+async fn rpc_error_transport_test() {
+    // Nothing listening on this port
+    let config = ProviderConfig::new("http://127.0.0.1:1").with_retry(RetryPolicy::none());
+    let api = HttpJsonRpc::new(config).unwrap();
+    let err = api.get_transaction_receipt("0x1").await.unwrap_err();
+    assert!(matches!(err, RpcError::Transport(_)));
+    assert!(err.is_retryable());
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use alloy_primitives::{address, Address, Bytes, B256, U256};
+use common::offline_api;
+use insolvent_detect_signal::{
+    api::ProviderConfig,
+    chain::ChainConfig,
+    model::{Block, Log, Receipt},
+    registry::{DetectorRegistry, RegistryError},
+    rules::{RuleError, RuleSet, TokenTransfer, TRANSFER_TOPIC},
+    sink::Match,
+    state::StateStore,
+    types::{AnonymouslyFundedSmartContractTriggeredSignal, BlockContext, Event},
+};
+
+const FIXED_FLOAT: &str = "0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f";
+const RECIPIENT: &str = "0x864e656c57a5a119f332c47326a35422294db5c9";
+
+fn block() -> Block {
+    common::block("fixed_float_deposit_response.json")
+}
+
+fn chain() -> ChainConfig {
+    ChainConfig::known(ChainConfig::MAINNET, ProviderConfig::new("http://localhost:8545")).unwrap()
+}
+
+fn invalid(toml: &str) -> String {
+    match RuleSet::parse(toml) {
+        Ok(_) => panic!("{}", toml),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn rules_example_test() {
+    // The documented example has to stay valid
+    let rules = RuleSet::load("../../docs/rules/example.toml").unwrap();
+    assert_eq!(rules.len(), 5);
+    let detectors = DetectorRegistry::builtin().with_rules(&rules).unwrap().build(&chain()).unwrap();
+    assert_eq!(detectors.events.len(), 9);
+    // Funding rule goes first so the builtin events see what it adds
+    assert_eq!(detectors.events[0].meta().name, "usdt_from_fixed_float");
+}
+
+#[test]
+fn rules_validation_test() {
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\nfrom = []\nfrm = []").contains("unknown field `frm`"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100").contains("no predicates"));
+    assert!(invalid("[[rules]]\nname = \"A b\"\nid = 100\nmin_value = \"1\"").contains("lowercase"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\nmin_value = \"2\"\nmax_value = \"1\"").contains("above"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\nmin_value = \"1 eth\"").contains("min_value"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\nmethods = [\"0xa9059c\"]").contains("methods"));
+    // Bad checksum
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\nfrom = [\"0x4E5B2E1DC63F6B91CB6CD759936495434C7E972f\"]").contains("from"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\ncontract_creation = true\nto = [\"0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f\"]").contains("to address"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\n[rules.log]\ntopics = [[]]").contains("log needs"));
+    assert!(invalid("[[rules]]\nname = \"a\"\nid = 100\n[rules.token_transfer]\nmin_amount = \"1.5\"").contains("min_amount"));
+    let twice = "[[rules]]\nname = \"a\"\nid = 100\nmin_value = \"1\"\n[[rules]]\nname = \"a\"\nid = 101\nmin_value = \"2\"";
+    assert!(matches!(RuleSet::parse(twice), Err(RuleError::DuplicateName(name)) if name == "a"));
+
+    // Ids are checked with everything else when the registry builds
+    let clash = RuleSet::parse("[[rules]]\nname = \"a\"\nid = 2\nmin_value = \"1\"").unwrap();
+    assert!(DetectorRegistry::builtin().with_rules(&clash).unwrap().build(&chain()).is_err());
+}
+
+#[test]
+fn rules_override_test() {
+    // A builtin's name is only taken over when the rule says so
+    let toml = "[[rules]]\nname = \"tornado_cash_withdraw\"\nid = 100\nmin_value = \"1\"";
+    let rules = RuleSet::parse(toml).unwrap();
+    assert_eq!(
+        DetectorRegistry::builtin().with_rules(&rules).err(),
+        Some(RegistryError::NameTaken("tornado_cash_withdraw".to_string()))
+    );
+
+    let rules = RuleSet::parse(&format!("{}\noverride = true", toml)).unwrap();
+    let detectors = DetectorRegistry::builtin().with_rules(&rules).unwrap().build(&chain()).unwrap();
+    let events: Vec<_> = detectors.events.iter().map(|event| event.meta()).collect();
+    let tornado: Vec<_> = events.iter().filter(|meta| meta.name == "tornado_cash_withdraw").collect();
+    assert_eq!(tornado.len(), 1);
+    assert_eq!(tornado[0].id, 100);
+}
+
+#[tokio::test]
+async fn rules_transaction_test() {
+    // Same as the FixedFloat event, written as a rule
+    let toml = format!(
+        "[[rules]]\nname = \"fixed_float\"\nid = 100\nfrom = [\"{}\"]\nmin_value = \"1\"\nchains = [1]",
+        FIXED_FLOAT
+    );
+    let rules = RuleSet::parse(&toml).unwrap();
+    let rule = rules.rules().next().unwrap();
+    let block = block();
+    let api = offline_api();
+
+    let mut matches: Vec<Match> = Vec::new();
+    rule.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+    let (id, payload) = &matches[0];
+    assert_eq!(*id, 100);
+    assert_eq!(payload["rule"], "fixed_float");
+    assert_eq!(payload["from"], FIXED_FLOAT);
+    assert_eq!(payload["recipient"], payload["to"]);
+    assert_eq!(payload["value_eth"], "2.4590284");
+
+    // Other chain
+    rule.event(&BlockContext::new(10, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+
+    let toml = format!("[[rules]]\nname = \"fixed_float\"\nid = 100\nfrom = [\"{}\"]\nmin_value = \"2.5\"", FIXED_FLOAT);
+    let rules = RuleSet::parse(&toml).unwrap();
+    rules.rules().next().unwrap().event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+}
+
+#[tokio::test]
+async fn rules_token_transfer_test() {
+    let token = address!("dac17f958d2ee523a2206206994597c13d831ec7");
+    let recipient: Address = RECIPIENT.parse().unwrap();
+    let transfer = |to: Address, amount: u64| Log {
+        address: token,
+        topics: vec![TRANSFER_TOPIC, FIXED_FLOAT.parse::<Address>().unwrap().into_word(), to.into_word()],
+        data: Bytes::from(U256::from(amount).to_be_bytes::<32>().to_vec()),
+        ..Default::default()
+    };
+
+    let mut block = block();
+    let receipts = block
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| Receipt {
+            transaction_hash: tx.hash,
+            // One big and one small transfer in the first transaction only
+            logs: if i == 0 { vec![transfer(Address::ZERO, 5), transfer(recipient, 20_000_000_000)] } else { vec![] },
+            ..Default::default()
+        })
+        .collect();
+    block.attach_receipts(receipts);
+
+    let toml = format!(
+        "[[rules]]\nname = \"usdt_from_fixed_float\"\nid = 101\nfunding = true\n[rules.token_transfer]\ntoken = [\"{}\"]\nfrom = [\"{}\"]\nmin_amount = \"10000000000\"",
+        token, FIXED_FLOAT
+    );
+    let rules = RuleSet::parse(&toml).unwrap();
+    let rule = rules.rules().next().unwrap();
+    let api = offline_api();
+    let mut matches: Vec<Match> = Vec::new();
+    rule.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 1);
+    let payload = &matches[0].1;
+    assert_eq!(payload["transaction_hash"], block.transactions[0].hash.to_string());
+    assert_eq!(payload["recipient"], RECIPIENT);
+    let transfers: Vec<TokenTransfer> = serde_json::from_value(payload["token_transfers"].clone()).unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].amount, U256::from(20_000_000_000u64));
+
+    // Funding rule feeds the state and starts the anon funded pattern
+    let detectors = DetectorRegistry::builtin().with_rules(&rules).unwrap().build(&chain()).unwrap();
+    let pattern = &detectors.patterns[0];
+    assert_eq!(pattern.meta.id, AnonymouslyFundedSmartContractTriggeredSignal::ID);
+    assert!(pattern.steps[0].events.contains(&101));
+    let mut state = StateStore::new();
+    let mut matches: Vec<Match> = Vec::new();
+    detectors.run(1, &block, &api, &mut state, &mut matches).await;
+    assert!(state.suspicious_addresses().contains(&RECIPIENT.parse().unwrap()));
+    assert_eq!(state.partials(pattern.meta.id).len(), 2);
+}
+
+#[tokio::test]
+async fn rules_log_test() {
+    let mut block = block();
+    let watched = RECIPIENT.parse::<Address>().unwrap().into_word();
+    let hash = block.transactions[3].hash;
+    block.attach_receipts(vec![Receipt {
+        transaction_hash: hash,
+        logs: vec![Log {
+            address: address!("dac17f958d2ee523a2206206994597c13d831ec7"),
+            topics: vec![TRANSFER_TOPIC, B256::ZERO, watched],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }]);
+
+    let rules = RuleSet::load("../../docs/rules/example.toml").unwrap();
+    let rule = rules.rules().find(|rule| rule.name() == "usdt_to_watched").unwrap();
+    let api = offline_api();
+    let mut matches: Vec<Match> = Vec::new();
+    rule.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    let hashes: Vec<&str> = matches.iter().map(|(_, payload)| payload["transaction_hash"].as_str().unwrap()).collect();
+    assert_eq!(hashes, vec![hash.to_string()]);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use alloy_primitives::{hex, Bytes, B256};
+use common::{creation_block, funding_block, CONTRACT, CREATION_HASH, CREATOR};
+use insolvent_detect_signal::{
+    address::CanonicalAddress,
+    api::{BatchResponse, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, ProviderConfig, RpcError},
+    chain::ChainConfig,
+    model::Block,
+    registry::DetectorRegistry,
+    score::{bytecode_red_flags, FindingKind, ScoreConfig, Severity},
+    sink::Match,
+    state::StateStore,
+};
+use serde_json::{json, Value};
+
+/// Node that only knows the code of the contract it was made with.
+struct CodeNode {
+    code: Bytes,
+}
+
+impl CodeNode {
+    fn clean() -> Self {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP
+        Self {
+            code: Bytes::from_static(&hex!("608060405200")),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for CodeNode {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        let result = match req.method.as_str() {
+            "eth_getCode" => json!(self.code),
+            _ => Value::Null,
+        };
+        Ok(json!({"jsonrpc": "2.0", "id": req.id, "result": result}))
+    }
+
+    async fn batch(&self, _batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        Err(RpcError::Transport("not served".to_string()))
+    }
+}
+
+/// Runs the detectors then scoring on the block, the way the runner does.
+async fn run(block: &Block, scoring: &ScoreConfig, state: &mut StateStore) -> Vec<Match> {
+    run_with(block, &CodeNode::clean(), scoring, state).await
+}
+
+async fn run_with(block: &Block, api: &CodeNode, scoring: &ScoreConfig, state: &mut StateStore) -> Vec<Match> {
+    let chain = ChainConfig::known(ChainConfig::MAINNET, ProviderConfig::new("http://localhost:8545")).unwrap();
+    let detectors = DetectorRegistry::builtin().build(&chain).unwrap();
+    let mut matches: Vec<Match> = Vec::new();
+    detectors.run(1, block, api, state, &mut matches).await;
+    scoring.observe(block, &matches, api, state).await;
+    matches
+}
+
+#[test]
+fn bytecode_red_flags_test() {
+    // PUSH1 0xff, ORIGIN, PUSH2 0xf4f4, DELEGATECALL
+    assert_eq!(bytecode_red_flags(&hex!("60ff3261f4f4f4")), vec!["tx.origin", "delegatecall"]);
+    // Opcodes inside push data don't count, a truncated push is fine
+    assert!(bytecode_red_flags(&hex!("7fff")).is_empty());
+    assert_eq!(bytecode_red_flags(&hex!("00ff")), vec!["selfdestruct"]);
+}
+
+#[test]
+fn score_config_test() {
+    let config = ScoreConfig::parse("").unwrap();
+    assert_eq!(config, ScoreConfig::default());
+    assert_eq!(config.severity(config.high), Severity::High);
+    assert_eq!(config.severity(config.medium), Severity::Medium);
+    assert_eq!(config.severity(config.medium - 1), Severity::Low);
+
+    let config = ScoreConfig::parse(
+        r#"
+        high = 50
+        medium = 20
+        [weights]
+        rule_hit = 25
+        fresh_deployer = 0
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.weight(FindingKind::RuleHit), 25);
+    assert_eq!(config.weight(FindingKind::FreshDeployer), 0);
+    assert_eq!(config.weight(FindingKind::TornadoOrigin), ScoreConfig::default().weight(FindingKind::TornadoOrigin));
+
+    let example = ScoreConfig::load("../../docs/scoring/example.toml").unwrap();
+    assert_eq!(example.weight(FindingKind::RuleHit), 0);
+
+    assert!(ScoreConfig::parse("[weights]\nrug_pull = 10").is_err());
+    assert!(ScoreConfig::parse("hgih = 10").is_err());
+    assert_eq!(
+        ScoreConfig::parse("high = 10\nmedium = 20").unwrap_err().to_string(),
+        "Scoring: medium (20) is above high (10)"
+    );
+}
+
+#[tokio::test]
+async fn score_funded_contract_test() {
+    let scoring = ScoreConfig::default();
+    let mut state = StateStore::new();
+    let creator: CanonicalAddress = CREATOR.parse().unwrap();
+    let contract: CanonicalAddress = CONTRACT.parse().unwrap();
+
+    run(&funding_block(), &scoring, &mut state).await;
+    let score = scoring.score(creator, &state);
+    let kinds: Vec<_> = score.reasons.iter().map(|reason| reason.kind).collect();
+    assert_eq!(kinds, vec![FindingKind::AnonymousFunding, FindingKind::FixedFloatOrigin]);
+    assert_eq!(score.score, 45);
+    assert_eq!(score.severity, Severity::Medium);
+
+    // The contract inherits its creator's origin and adds the fresh deployer
+    let creation = creation_block();
+    let matches = run(&creation, &scoring, &mut state).await;
+    let (_, created) = matches.iter().find(|(id, _)| *id == 3).unwrap();
+    let score = scoring.score_match(created, &state).unwrap();
+    assert_eq!(score.entity, contract);
+    let kinds: Vec<_> = score.reasons.iter().map(|reason| reason.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![FindingKind::AnonymousFunding, FindingKind::FixedFloatOrigin, FindingKind::FreshDeployer]
+    );
+    assert_eq!(score.score, 60);
+    assert_eq!(score.severity, Severity::High);
+    assert!(score.reasons[1].detail.starts_with(&format!("creator {} received", creator)));
+
+    // A reorg takes the block's findings out with it
+    assert!(state.revert(creation.hash()));
+    assert!(scoring.score(contract, &state).reasons.is_empty());
+    assert_eq!(scoring.score(creator, &state).score, 45);
+}
+
+#[tokio::test]
+async fn score_weights_test() {
+    // Same history weighed differently
+    let scoring = ScoreConfig::parse("medium = 70\nhigh = 100\n[weights]\nanonymous_funding = 0").unwrap();
+    let mut state = StateStore::new();
+    run(&funding_block(), &scoring, &mut state).await;
+    run(&creation_block(), &scoring, &mut state).await;
+
+    let score = scoring.score(CONTRACT.parse().unwrap(), &state);
+    assert_eq!(score.score, 30);
+    assert_eq!(score.severity, Severity::Low);
+    assert!(score.reasons.iter().all(|reason| reason.kind != FindingKind::AnonymousFunding));
+}
+
+#[tokio::test]
+async fn score_runtime_code_test() {
+    // Constructor args with ORIGIN, DELEGATECALL and SELFDESTRUCT bytes in them aren't code
+    let mut creation = creation_block();
+    let hash: B256 = CREATION_HASH.parse().unwrap();
+    let transaction = creation.transactions.iter_mut().find(|transaction| transaction.hash == hash).unwrap();
+    let mut input = transaction.input.to_vec();
+    input.extend_from_slice(&[0; 29]);
+    input.extend_from_slice(&hex!("32f4ff"));
+    transaction.input = input.into();
+    assert_eq!(bytecode_red_flags(&transaction.input), vec!["tx.origin", "delegatecall", "selfdestruct"]);
+
+    let scoring = ScoreConfig::default();
+    let mut state = StateStore::new();
+    run(&funding_block(), &scoring, &mut state).await;
+    run(&creation, &scoring, &mut state).await;
+    let score = scoring.score(CONTRACT.parse().unwrap(), &state);
+    assert!(score.reasons.iter().all(|reason| reason.kind != FindingKind::BytecodeRedFlag));
+
+    // The deployed code is what counts
+    let proxy = CodeNode {
+        code: Bytes::from_static(&hex!("6080604052f400")),
+    };
+    let mut state = StateStore::new();
+    run(&funding_block(), &scoring, &mut state).await;
+    run_with(&creation, &proxy, &scoring, &mut state).await;
+    let score = scoring.score(CONTRACT.parse().unwrap(), &state);
+    let flag = score.reasons.iter().find(|reason| reason.kind == FindingKind::BytecodeRedFlag).unwrap();
+    assert_eq!(flag.detail, "uses delegatecall");
+}
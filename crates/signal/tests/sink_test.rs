@@ -0,0 +1,54 @@
+mod common;
+
+use std::cell::RefCell;
+
+use alloy_primitives::address;
+use insolvent_detect_signal::{
+    model::Block,
+    sink::{BatchSink, Match, Sink},
+    types::{BlockContext, Event, TransferFromFixedFloatEvent},
+};
+use serde_json::json;
+
+fn block() -> Block {
+    let mut block = common::block("fixed_float_deposit_response.json");
+    // Five transfers out of the wallet
+    let transfer = block
+        .transactions
+        .iter()
+        .find(|tx| tx.from == address!("4e5b2e1dc63f6b91cb6cd759936495434c7e972f"))
+        .unwrap()
+        .clone();
+    block.transactions.extend(std::iter::repeat_n(transfer, 4));
+    block
+}
+
+#[tokio::test]
+async fn sink_channel_test() {
+    // Matches are forwarded as they are found, in block order
+    let api = common::offline_api();
+    let (mut tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Match>();
+    let event = TransferFromFixedFloatEvent::default();
+    event.event(&BlockContext::new(1, &block(), &api), &mut tx).await;
+    drop(tx);
+
+    let mut received = Vec::new();
+    while let Some((id, _)) = rx.recv().await {
+        received.push(id);
+    }
+    assert_eq!(received, vec![2; 5]);
+}
+
+#[test]
+fn sink_batch_test() {
+    let batches = RefCell::new(Vec::new());
+    {
+        let mut sink = BatchSink::new(2, |batch: Vec<Match>| batches.borrow_mut().push(batch.len()));
+        for id in 0..5 {
+            sink.push(id, json!({}));
+        }
+        assert_eq!(*batches.borrow(), vec![2, 2]);
+    }
+    // Rest is flushed on drop
+    assert_eq!(*batches.borrow(), vec![2, 2, 1]);
+}
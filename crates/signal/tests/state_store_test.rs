@@ -0,0 +1,118 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{block, creation_block, funding_block, offline_api, CONTRACT, CREATOR};
+use insolvent_detect_signal::{
+    address::CanonicalAddress,
+    api::ProviderConfig,
+    chain::ChainConfig,
+    model::Block,
+    registry::DetectorRegistry,
+    sink::Match,
+    state::{StateStore, StateUpdate},
+};
+
+fn state_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("insolvent-state-{}-{}", name, std::process::id())).join("state_1.json")
+}
+
+async fn run(block: &Block, state: &mut StateStore) -> Vec<u32> {
+    let api = offline_api();
+    let chain = ChainConfig::known(ChainConfig::MAINNET, ProviderConfig::new("http://localhost:8545")).unwrap();
+    let detectors = DetectorRegistry::builtin().build(&chain).unwrap();
+    let mut matches: Vec<Match> = Vec::new();
+    detectors.run(1, block, &api, state, &mut matches).await;
+    matches.into_iter().map(|(id, _)| id).collect()
+}
+
+#[tokio::test]
+async fn state_store_feedback_test() {
+    // Funding, contract creation and the call to it in three blocks, each step only fires
+    // because of what the one before it stored
+    let mut state = StateStore::new();
+    // That block has a FixedFloat transfer of its own, to someone else
+    assert_eq!(run(&creation_block(), &mut state).await, vec![2]);
+    assert!(state.suspicious_contracts().is_empty());
+
+    assert_eq!(run(&funding_block(), &mut state).await, vec![2]);
+    assert!(state.suspicious_addresses().contains(&CREATOR.parse().unwrap()));
+
+    assert_eq!(run(&creation_block(), &mut state).await, vec![2, 3]);
+    assert!(state.suspicious_contracts().contains(&CONTRACT.parse().unwrap()));
+
+    // The call event and the signal completing the sequence
+    assert_eq!(run(&block("suspicious_contract_triggered_signal_response.json"), &mut state).await, vec![4, 0]);
+}
+
+#[tokio::test]
+async fn state_store_same_block_test() {
+    // Funding and creation in one block: the created event runs after the transfer event and
+    // sees its recipient
+    let mut block = creation_block();
+    block.transactions.extend(funding_block().transactions);
+    let mut state = StateStore::new();
+    assert_eq!(run(&block, &mut state).await, vec![2, 2, 3]);
+    assert_eq!(state.suspicious_contracts().len(), 1);
+}
+
+#[tokio::test]
+async fn state_store_revert_test() {
+    let mut state = StateStore::new();
+    let funding = funding_block();
+    run(&funding, &mut state).await;
+    let creation = creation_block();
+    run(&creation, &mut state).await;
+    assert_eq!(state.suspicious_contracts().len(), 1);
+
+    // Reorged out creation takes its contract with it, the funding stays
+    state.finalize(funding.hash());
+    assert!(state.revert(creation.hash()));
+    assert!(state.suspicious_contracts().is_empty());
+    assert_eq!(state.suspicious_addresses().len(), 1);
+
+    // Already known, nothing to revert later
+    let address: CanonicalAddress = CREATOR.parse().unwrap();
+    assert!(!state.apply(creation.hash(), StateUpdate::SuspiciousAddress(address)));
+    assert!(!state.revert(creation.hash()));
+}
+
+#[tokio::test]
+async fn state_store_evicted_test() {
+    // Blocks that leave the follower window unfinalized are finalized in the store, their
+    // entries stay and nothing is kept for them
+    let mut state = StateStore::new();
+    let funding = funding_block();
+    run(&funding, &mut state).await;
+    let creation = creation_block();
+    run(&creation, &mut state).await;
+    assert_eq!(state.pending_blocks(), 2);
+
+    state.finalize(funding.hash());
+    state.finalize(creation.hash());
+    assert_eq!(state.pending_blocks(), 0);
+    state.revert(creation.hash());
+    assert!(state.suspicious_addresses().contains(&CREATOR.parse().unwrap()));
+    assert!(state.suspicious_contracts().contains(&CONTRACT.parse().unwrap()));
+}
+
+#[tokio::test]
+async fn state_store_persist_test() {
+    let path = state_path("persist");
+    let _ = std::fs::remove_file(&path);
+
+    let mut state = StateStore::open(&path).unwrap();
+    assert!(state.suspicious_addresses().is_empty());
+    run(&funding_block(), &mut state).await;
+    run(&creation_block(), &mut state).await;
+    state.save().unwrap();
+
+    // Restart picks up where it left off
+    let mut state = StateStore::open(&path).unwrap();
+    assert!(state.suspicious_addresses().contains(&CREATOR.parse().unwrap()));
+    assert_eq!(run(&block("suspicious_contract_triggered_signal_response.json"), &mut state).await, vec![4, 0]);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(StateStore::open(&path).is_err());
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -1,27 +1,45 @@
 use std::{collections::HashSet, fs::File, io::BufReader};
 
-use insolvent_detect_signal::{types::{BlockJson, Event, SuspiciousContractCreatedEvent}, api::InfuraAPIHttp};
+use insolvent_detect_signal::{
+    api::{EthJsonRpc, HttpJsonRpc, RecordingJsonRpc, ReplayJsonRpc},
+    model::Block,
+    sink::Match,
+    types::{BlockContext, Event, SuspiciousContractCreatedEvent},
+};
 
-#[tokio::test]
-async fn suspcious_contract_created_response_test() {
-    // Load block from file that has a contract creation event, this function should detect that
-    // when also passed a HashSet containing a list of accounts funded from anon sources.
-    //
-    // The HashSet should be cached locally in prod.
-    let mut event_id = u32::MAX;
+// Receipt for the block below, written by hand from the mainnet receipt and trimmed to the
+// fields the event reads (no `logs` or `logsBloom`), not recorded from a node. Running with
+// `RECORD=1 TOKEN=<token>` replaces it with a real recording.
+const FIXTURE: &str = "tests/__data__/suspicious_contract_created_rpc.json";
 
-    let api = InfuraAPIHttp;
+async fn event_ids(api: &(impl EthJsonRpc + Sync)) -> Vec<u32> {
+    let mut matches: Vec<Match> = Vec::new();
     let mut suspicious_addresses = HashSet::new();
-    suspicious_addresses.insert("0x864e656c57a5a119f332c47326a35422294db5c9".to_string());
+    suspicious_addresses.insert("0x864e656c57a5a119f332c47326a35422294db5c9".parse().unwrap());
 
     let file = File::open("tests/__data__/suspicious_contract_created_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_block = BlockJson::new(value);
-    let suspicious_contract_created = Event::SuspiciousContractCreated(SuspiciousContractCreatedEvent);
-    if let Some(event) = suspicious_contract_created.event(&api, &json_block, &suspicious_addresses).await {
-        // Suspicious contract created has id of 2
-        event_id = event.0;
-    }
-    assert_eq!(event_id, 3);
+    let json_block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    let suspicious_contract_created = SuspiciousContractCreatedEvent;
+    suspicious_contract_created.event(&BlockContext::new(1, &json_block, api).with_suspicious_addresses(&suspicious_addresses), &mut matches).await;
+    matches.into_iter().map(|(id, _)| id).collect()
+}
+
+#[tokio::test]
+async fn suspcious_contract_created_response_test() {
+    // Load block from file that has a contract creation event, this function should detect that
+    // when also passed a HashSet containing a list of accounts funded from anon sources.
+    //
+    // The HashSet should be cached locally in prod.
+    let event_ids = if std::env::var("RECORD").is_ok() {
+        let api = RecordingJsonRpc::new(HttpJsonRpc::from_env().unwrap(), FIXTURE);
+        let event_ids = event_ids(&api).await;
+        api.save().unwrap();
+        event_ids
+    } else {
+        event_ids(&ReplayJsonRpc::new(FIXTURE).unwrap()).await
+    };
+    // One contract created in the block, suspicious contract created has id of 3
+    assert_eq!(event_ids, vec![3]);
 }
@@ -1,6 +1,9 @@
 use std::{collections::HashSet, fs::File, io::BufReader};
 
-use insolvent_detect_signal::types::{BlockJson, Signal, AnonymouslyFundedSmartContractTriggeredSignal};
+use insolvent_detect_signal::api::{HttpJsonRpc, ProviderConfig};
+use insolvent_detect_signal::model::Block;
+use insolvent_detect_signal::sink::Match;
+use insolvent_detect_signal::types::{BlockContext, Event, SuspiciousContractCalledEvent};
 
 #[tokio::test]
 async fn suspcious_contract_triggered_response_test() {
@@ -8,20 +11,21 @@ async fn suspcious_contract_triggered_response_test() {
     // containing a list of contract addresses that have been marked as suspicious.
     //
     // The HashSet should be cached locally in prod.
-    let mut signal_id = u32::MAX;
+    let mut matches: Vec<Match> = Vec::new();
 
     let mut suspicious_contracts = HashSet::new();
-    suspicious_contracts.insert("0x03e7b13bcd9b8383f403696c1494845560607eca".to_string());
+    suspicious_contracts.insert("0x03e7b13bcd9b8383f403696c1494845560607eca".parse().unwrap());
 
     let file =
         File::open("tests/__data__/suspicious_contract_triggered_signal_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_block = BlockJson::new(value);
-    let suspicious_contract_triggered = Signal::AnonymouslyFundedSmartContractTriggered(AnonymouslyFundedSmartContractTriggeredSignal);
-    if let Some(signal) = suspicious_contract_triggered.signal(&json_block, &suspicious_contracts).await {
-        //Anonymous funded smart contract triggered signal has an id of 0
-        signal_id = signal.0;
-    }
-    assert_eq!(signal_id, 0);
+    let json_block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
+    let suspicious_contract_called = SuspiciousContractCalledEvent;
+    suspicious_contract_called.event(&BlockContext::new(1, &json_block, &api).with_suspicious_contracts(&suspicious_contracts), &mut matches).await;
+    // One call to the contract in the block, suspicious contract called has an id of 4. The
+    // signal itself is the correlation pattern, see correlation_test
+    let ids: Vec<u32> = matches.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![4]);
 }
@@ -1,22 +1,30 @@
-use std::{fs::File, io::BufReader, collections::HashSet};
+use std::{fs::File, io::BufReader};
 
-use insolvent_detect_signal::{types::{BlockJson, Event, TornadoCashWithdrawEvent}, api::InfuraAPIHttp};
+use alloy_primitives::B256;
+use insolvent_detect_signal::{model::Block, sink::Match, types::{BlockContext, Event, TornadoCashWithdrawEvent}, api::{HttpJsonRpc, ProviderConfig}};
 
 #[tokio::test]
 async fn tornado_cash_block_response_test() {
     // Load block from file that has a tornado cash withdrawal transaction, this function
     // should detect that
-    let mut event_id = u32::MAX;
+    let mut matches: Vec<Match> = Vec::new();
 
-    let api = InfuraAPIHttp;
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
     let file = File::open("tests/__data__/tornado_cash_block_response.json").unwrap();
     let reader = BufReader::new(file);
     let value: serde_json::Value = serde_json::from_reader(reader).unwrap();
-    let json_block = BlockJson::new(value);
-    let tornado_cash_withdraw = Event::TornadoCashWithdraw(TornadoCashWithdrawEvent);
-    if let Some(event) = tornado_cash_withdraw.event(&api, &json_block, &HashSet::new()).await {
-        // Tornado cash withdraw has id of 1
-        event_id = event.0;
-    }
-    assert_eq!(event_id, 1);
+    let mut json_block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    let tornado_cash_withdraw = TornadoCashWithdrawEvent::default();
+    tornado_cash_withdraw.event(&BlockContext::new(1, &json_block, &api), &mut matches).await;
+    // One withdrawal in the block, tornado cash withdraw has id of 1
+    let ids: Vec<u32> = matches.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![1]);
+
+    // A second withdrawal in the same block is reported too
+    let hash: B256 = matches[0].1["transaction_hash"].as_str().unwrap().parse().unwrap();
+    let withdraw = json_block.transactions.iter().find(|tx| tx.hash == hash).unwrap().clone();
+    json_block.transactions.push(withdraw);
+    let mut matches: Vec<Match> = Vec::new();
+    tornado_cash_withdraw.event(&BlockContext::new(1, &json_block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 2);
 }
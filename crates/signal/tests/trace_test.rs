@@ -0,0 +1,153 @@
+use alloy_primitives::{address, b256, U256};
+use insolvent_detect_signal::api::{
+    BatchResponse, BlockTag, CallKind, EthJsonRpc, JsonRpcBatch, JsonRpcRequest, RpcError,
+};
+use serde_json::{json, Value};
+
+/// Answers every request for a method with the same result, and checks the params we send.
+struct TraceNode {
+    method: &'static str,
+    params: Value,
+    result: Value,
+}
+
+#[async_trait::async_trait]
+impl EthJsonRpc for TraceNode {
+    async fn request(&self, req: &JsonRpcRequest) -> Result<Value, RpcError> {
+        assert_eq!(req.method, self.method);
+        assert_eq!(serde_json::to_value(&req.params).unwrap(), self.params);
+        Ok(json!({"jsonrpc": "2.0", "id": req.id, "result": self.result}))
+    }
+
+    async fn batch(&self, _batch: JsonRpcBatch) -> Result<BatchResponse, RpcError> {
+        unimplemented!()
+    }
+}
+
+const TX: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+const EOA: &str = "0x00000000000000000000000000000000000000e0";
+const FACTORY: &str = "0x00000000000000000000000000000000000000f0";
+const CHILD: &str = "0x00000000000000000000000000000000000000c0";
+
+/// EOA calls a factory, which CREATE2s a contract with 1 wei that self-destructs back to the EOA
+/// in its constructor. A second create reverts.
+fn call_tracer_frame() -> Value {
+    json!({
+        "type": "CALL", "from": EOA, "to": FACTORY, "value": "0x0", "gas": "0x10000", "gasUsed": "0x8000", "input": "0x1234",
+        "calls": [
+            {
+                "type": "CREATE2", "from": FACTORY, "to": CHILD, "value": "0x1", "gas": "0x9000", "gasUsed": "0x5000", "input": "0x60", "output": "0x",
+                "calls": [{"type": "SELFDESTRUCT", "from": CHILD, "to": EOA, "value": "0x1"}]
+            },
+            {
+                "type": "CREATE", "from": FACTORY, "value": "0x5", "gas": "0x1000", "gasUsed": "0x1000", "input": "0x60", "error": "execution reverted",
+                "calls": [{"type": "CALL", "from": FACTORY, "to": EOA, "value": "0x7", "gas": "0x10", "gasUsed": "0x10", "input": "0x"}]
+            },
+            {"type": "DELEGATECALL", "from": FACTORY, "to": CHILD, "value": "0x9", "gas": "0x10", "gasUsed": "0x10", "input": "0x"}
+        ]
+    })
+}
+
+#[tokio::test]
+async fn debug_trace_transaction_test() {
+    let node = TraceNode {
+        method: "debug_traceTransaction",
+        params: json!([TX, {"tracer": "callTracer"}]),
+        result: call_tracer_frame(),
+    };
+    let hash = b256!("00000000000000000000000000000000000000000000000000000000000000aa");
+    let root = node.debug_trace_transaction(hash).await.unwrap();
+
+    assert_eq!(root.kind, CallKind::Call);
+    assert_eq!(root.gas_used, 0x8000);
+    assert_eq!(root.walk().count(), 6);
+
+    // Reverted create and everything under it is left out
+    let creates: Vec<_> = root.creates().collect();
+    assert_eq!(creates.len(), 1);
+    assert_eq!(creates[0].kind, CallKind::Create2);
+    assert_eq!(creates[0].to, Some(address!("00000000000000000000000000000000000000c0")));
+
+    let destructs: Vec<_> = root.self_destructs().collect();
+    assert_eq!(destructs.len(), 1);
+    assert_eq!(destructs[0].to, Some(address!("00000000000000000000000000000000000000e0")));
+
+    // Create value and the self-destruct refund, not the delegate call or the reverted frames
+    let transfers: Vec<_> = root.internal_transfers().map(|frame| frame.value).collect();
+    assert_eq!(transfers, vec![U256::from(1), U256::from(1)]);
+}
+
+#[tokio::test]
+async fn debug_trace_block_test() {
+    let node = TraceNode {
+        method: "debug_traceBlockByNumber",
+        params: json!(["0x10", {"tracer": "callTracer"}]),
+        result: json!([{"txHash": TX, "result": call_tracer_frame()}, {"result": call_tracer_frame()}]),
+    };
+    let traces = node.debug_trace_block_by_number(BlockTag::Number(16)).await.unwrap();
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[0].transaction_hash.unwrap().to_string(), TX);
+    assert!(traces[1].transaction_hash.is_none());
+    assert_eq!(traces[1].trace.calls.len(), 3);
+
+    // One transaction the node couldn't trace fails the block
+    let node = TraceNode {
+        method: "debug_traceBlockByNumber",
+        params: json!(["0x10", {"tracer": "callTracer"}]),
+        result: json!([{"txHash": TX, "error": "execution timeout"}]),
+    };
+    let err = node.debug_trace_block_by_number(BlockTag::Number(16)).await.unwrap_err();
+    assert!(err.to_string().contains("execution timeout"));
+}
+
+#[tokio::test]
+async fn debug_trace_prestate_test() {
+    let node = TraceNode {
+        method: "debug_traceBlockByNumber",
+        params: json!(["latest", {"tracer": "prestateTracer"}]),
+        result: json!([{"txHash": TX, "result": {
+            EOA: {"balance": "0xde0b6b3a7640000", "nonce": 3},
+            FACTORY: {"balance": "0x0", "nonce": 1, "code": "0x6080", "storage": {
+                "0x0000000000000000000000000000000000000000000000000000000000000000": "0x0000000000000000000000000000000000000000000000000000000000000001"
+            }}
+        }}]),
+    };
+    let traces = node.debug_trace_block_by_number_prestate(BlockTag::Latest).await.unwrap();
+    let state = &traces[0].trace;
+    let eoa = &state[&address!("00000000000000000000000000000000000000e0")];
+    assert_eq!(eoa.balance, Some(U256::from(10u64.pow(18))));
+    assert_eq!(eoa.nonce, Some(3));
+    assert!(eoa.code.is_none());
+    assert_eq!(state[&address!("00000000000000000000000000000000000000f0")].storage.len(), 1);
+}
+
+#[tokio::test]
+async fn trace_block_test() {
+    // Same transaction as debug_trace_transaction_test flattened Parity style, plus a block reward
+    let node = TraceNode {
+        method: "trace_block",
+        params: json!(["0x10"]),
+        result: json!([
+            {"type": "call", "action": {"callType": "call", "from": EOA, "to": FACTORY, "value": "0x0", "gas": "0x10000", "input": "0x1234"},
+             "result": {"gasUsed": "0x8000", "output": "0x"}, "subtraces": 2, "traceAddress": [], "transactionHash": TX},
+            {"type": "create", "action": {"creationMethod": "create2", "from": FACTORY, "value": "0x1", "gas": "0x9000", "init": "0x60"},
+             "result": {"address": CHILD, "code": "0x", "gasUsed": "0x5000"}, "subtraces": 1, "traceAddress": [0], "transactionHash": TX},
+            {"type": "suicide", "action": {"address": CHILD, "refundAddress": EOA, "balance": "0x1"},
+             "result": null, "subtraces": 0, "traceAddress": [0, 0], "transactionHash": TX},
+            {"type": "create", "action": {"from": FACTORY, "value": "0x5", "gas": "0x1000", "init": "0x60"},
+             "error": "Reverted", "subtraces": 0, "traceAddress": [1], "transactionHash": TX},
+            {"type": "reward", "action": {"author": EOA, "rewardType": "block", "value": "0x1"}, "result": null, "subtraces": 0, "traceAddress": []}
+        ]),
+    };
+    let traces = node.trace_block(BlockTag::Number(16)).await.unwrap();
+    assert_eq!(traces.len(), 1);
+    let root = &traces[0].trace;
+    assert_eq!(root.calls.len(), 2);
+    assert_eq!(root.calls[0].calls[0].kind, CallKind::SelfDestruct);
+    assert_eq!(root.calls[1].to, None);
+
+    let creates: Vec<_> = root.creates().map(|frame| frame.kind).collect();
+    assert_eq!(creates, vec![CallKind::Create2]);
+    assert_eq!(root.self_destructs().count(), 1);
+    assert_eq!(root.internal_transfers().count(), 2);
+}
@@ -0,0 +1,73 @@
+use std::{fs::File, io::BufReader};
+
+use alloy_primitives::{address, U256};
+use insolvent_detect_signal::model::{Block, Receipt, Transaction, TransactionKind};
+use serde_json::{json, Value};
+
+const HASH: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+const FROM: &str = "0x00000000000000000000000000000000000000e0";
+
+/// Fields every transaction type has, `extra` adds the type specific ones.
+fn transaction(tx_type: &str, extra: Value) -> Value {
+    let mut value = json!({
+        "hash": HASH, "type": tx_type, "nonce": "0x1", "from": FROM, "to": null, "value": "0xde0b6b3a7640000",
+        "gas": "0x5208", "input": "0x60806040", "v": "0x1", "r": "0x2", "s": "0x3"
+    });
+    value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    value
+}
+
+#[test]
+fn typed_model_transaction_kinds_test() {
+    let access_list = json!([{"address": FROM, "storageKeys": [HASH]}]);
+    let fees = json!({"maxFeePerGas": "0x10", "maxPriorityFeePerGas": "0x1", "accessList": access_list, "chainId": "0x1"});
+    let mut blob = fees.clone();
+    blob["maxFeePerBlobGas"] = json!("0x2");
+    blob["blobVersionedHashes"] = json!([HASH]);
+    let mut set_code = fees.clone();
+    set_code["authorizationList"] = json!([{"chainId": "0x1", "address": FROM, "nonce": "0x0", "yParity": "0x1", "r": "0x2", "s": "0x3"}]);
+
+    let cases = [
+        transaction("0x0", json!({"gasPrice": "0x10"})),
+        transaction("0x1", json!({"gasPrice": "0x10", "accessList": access_list, "chainId": "0x1"})),
+        transaction("0x2", fees),
+        transaction("0x3", blob),
+        transaction("0x4", set_code),
+        transaction("0x7e", json!({"sourceHash": HASH, "mint": "0x0"})),
+    ];
+    for (value, tx_type) in cases.into_iter().zip([0, 1, 2, 3, 4, 0x7e]) {
+        let tx: Transaction = serde_json::from_value(value).unwrap();
+        assert_eq!(tx.kind.tx_type(), tx_type);
+        assert_eq!(tx.from, address!("00000000000000000000000000000000000000e0"));
+        assert_eq!(tx.value, U256::from(10u64.pow(18)));
+        assert!(tx.is_contract_creation());
+
+        // Typed fields survive the trip back to json
+        let round_trip: Transaction = serde_json::from_value(serde_json::to_value(&tx).unwrap()).unwrap();
+        assert_eq!(round_trip, tx);
+    }
+
+    // Blob transaction without its blob fields is malformed rather than silently defaulted
+    let err = serde_json::from_value::<Transaction>(transaction("0x3", json!({"maxFeePerGas": "0x10"}))).unwrap_err();
+    assert!(err.to_string().contains("maxPriorityFeePerGas"));
+}
+
+#[test]
+fn typed_model_block_test() {
+    let file = File::open("tests/__data__/suspicious_contract_created_response.json").unwrap();
+    let value: Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+    assert_eq!(block.number(), 18115254);
+    assert!(block.transactions.iter().all(|tx| tx.block_hash == Some(block.hash())));
+    assert!(block.transactions.iter().any(|tx| matches!(tx.kind, TransactionKind::DynamicFee { .. })));
+
+    let round_trip: Block = serde_json::from_value(serde_json::to_value(&block).unwrap()).unwrap();
+    assert_eq!(round_trip, block);
+
+    let file = File::open("tests/__data__/contract_creation_transaction_response.json").unwrap();
+    let value: Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let receipt: Receipt = serde_json::from_value(value["result"].clone()).unwrap();
+    assert_eq!(receipt.tx_type, 2);
+    let round_trip: Receipt = serde_json::from_value(serde_json::to_value(&receipt).unwrap()).unwrap();
+    assert_eq!(round_trip, receipt);
+}
@@ -0,0 +1,76 @@
+use std::{fs::File, io::BufReader};
+
+use alloy_primitives::U256;
+use insolvent_detect_signal::{
+    api::{HttpJsonRpc, ProviderConfig},
+    model::Block,
+    sink::Match,
+    types::{BlockContext, Event, TransferFromFixedFloatEvent, TransferFromFixedFloatJson},
+    units::{format_ether, parse_ether, WEI_PER_ETH},
+};
+use serde_json::json;
+
+#[test]
+fn wei_value_units_test() {
+    assert_eq!(format_ether(U256::ZERO), "0");
+    assert_eq!(format_ether(WEI_PER_ETH), "1");
+    assert_eq!(format_ether(U256::from(1)), "0.000000000000000001");
+    assert_eq!(format_ether(U256::from(2459028400000000000u64)), "2.4590284");
+
+    // Way past u64, 100k ETH
+    let large = parse_ether("100000").unwrap();
+    assert_eq!(large, U256::from(10u64).pow(U256::from(23)));
+    assert_eq!(format_ether(large), "100000");
+
+    assert_eq!(parse_ether("0.5").unwrap(), WEI_PER_ETH / U256::from(2));
+    assert_eq!(parse_ether(".5").unwrap(), WEI_PER_ETH / U256::from(2));
+    assert_eq!(parse_ether("1.").unwrap(), WEI_PER_ETH);
+    for invalid in ["", ".", "1e18", "-1", "0x10", "0.0000000000000000001"] {
+        assert!(parse_ether(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn wei_value_fixed_float_test() {
+    let api = HttpJsonRpc::new(ProviderConfig::new("http://localhost:8545")).unwrap();
+    let file = File::open("tests/__data__/fixed_float_deposit_response.json").unwrap();
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let block: Block = serde_json::from_value(value["result"].clone()).unwrap();
+
+    // Hex value from the node, not 0
+    let event = TransferFromFixedFloatEvent::default();
+    let mut matches: Vec<Match> = Vec::new();
+    event.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    let (_, payload) = &matches[0];
+    assert_eq!(payload["value"], "2459028400000000000");
+    assert_eq!(payload["value_eth"], "2.4590284");
+
+    // Threshold is inclusive
+    let at = TransferFromFixedFloatEvent::default().with_min_value(parse_ether("2.4590284").unwrap());
+    at.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 2);
+    let above = TransferFromFixedFloatEvent::default().with_min_value(parse_ether("2.5").unwrap());
+    above.event(&BlockContext::new(1, &block, &api), &mut matches).await;
+    assert_eq!(matches.len(), 2);
+}
+
+#[test]
+fn wei_value_payload_test() {
+    let json_resp: TransferFromFixedFloatJson = serde_json::from_value(json!({
+        "recipient": "0x3695a1a579e89f0fc8142ed701564ca32aa61ac2",
+        "value": "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+        "value_eth": "",
+        "block_timestamp": 1,
+        "block": 1,
+        "transaction_hash": "0x"
+    }))
+    .unwrap();
+    assert_eq!(json_resp.value, U256::MAX);
+
+    // Payloads stored before values were strings
+    let json_resp: TransferFromFixedFloatJson = serde_json::from_value(json!({
+        "recipient": "0x3695a1a579e89f0fc8142ed701564ca32aa61ac2", "value": 10, "block_timestamp": 1, "block": 1, "transaction_hash": "0x"
+    }))
+    .unwrap();
+    assert_eq!(json_resp.value, U256::from(10));
+}
@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use insolvent_detect_signal::api::{
+    EthJsonRpc, EthPubSub, JsonRpcBatch, LogFilter, ProviderConfig, RetryPolicy, RpcError, WsJsonRpc,
+};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+type ServerSocket = tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>;
+
+async fn next_request(socket: &mut ServerSocket) -> Value {
+    loop {
+        if let Message::Text(text) = socket.next().await.unwrap().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+async fn reply(socket: &mut ServerSocket, value: Value) {
+    socket.send(Message::Text(value.to_string())).await.unwrap();
+}
+
+fn config(addr: std::net::SocketAddr) -> ProviderConfig {
+    ProviderConfig::new(&format!("ws://{}", addr)).with_retry(RetryPolicy {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        multiplier: 2,
+        jitter: false,
+    })
+}
+
+#[tokio::test]
+async fn ws_new_heads_resubscribe_test() {
+    // Server pushes one head then drops the connection. The client should reconnect, resubscribe
+    // and keep delivering heads on the same stream, then serve normal calls over the new socket.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_subscribe");
+        assert_eq!(req["params"][0], "newHeads");
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xa"})).await;
+        reply(
+            &mut socket,
+            json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0xa", "result": {"number": "0x1"}}}),
+        )
+        .await;
+        socket.close(None).await.unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_subscribe");
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xb"})).await;
+        reply(
+            &mut socket,
+            json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0xb", "result": {"number": "0x2"}}}),
+        )
+        .await;
+
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_getBlockByNumber");
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": {"number": "0x2"}})).await;
+        // Keep the socket open until the client is done
+        let _ = socket.next().await;
+    });
+
+    let api = WsJsonRpc::connect(config(addr)).await.unwrap();
+    let mut heads = api.subscribe_new_heads().await.unwrap();
+
+    let first = tokio::time::timeout(Duration::from_secs(5), heads.next()).await.unwrap().unwrap();
+    assert_eq!(first["number"], "0x1");
+    let second = tokio::time::timeout(Duration::from_secs(5), heads.next()).await.unwrap().unwrap();
+    assert_eq!(second["number"], "0x2");
+
+    let block = api.get_block_by_number_latest().await.unwrap();
+    assert_eq!(block["result"]["number"], "0x2");
+
+    drop(heads);
+    drop(api);
+    tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn ws_logs_subscription_test() {
+    // Logs filter should be passed through as the second eth_subscribe param
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["params"][0], "logs");
+        assert_eq!(req["params"][1]["address"][0], "0x4e5b2e1dc63f6b91cb6cd759936495434c7e972f");
+        assert_eq!(req["params"][1]["topics"][1], Value::Null);
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xc"})).await;
+        reply(
+            &mut socket,
+            json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0xc", "result": {"logIndex": "0x0"}}}),
+        )
+        .await;
+        let _ = socket.next().await;
+    });
+
+    let api = WsJsonRpc::connect(config(addr)).await.unwrap();
+    let filter = LogFilter {
+        address: vec!["0x4E5B2E1DC63F6B91CB6CD759936495434C7E972F".parse().unwrap()],
+        topics: vec![Some(vec!["0xddf2".to_string()]), None],
+        ..Default::default()
+    };
+    let mut logs = api.subscribe_logs(filter).await.unwrap();
+    let log = tokio::time::timeout(Duration::from_secs(5), logs.next()).await.unwrap().unwrap();
+    assert_eq!(log["logIndex"], "0x0");
+}
+
+#[tokio::test]
+async fn ws_batch_test() {
+    // Batch goes out as one array message and the answers are matched back by id
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        let items = req.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        let responses: Vec<Value> = items
+            .iter()
+            .rev()
+            .map(|item| json!({"jsonrpc": "2.0", "id": item["id"], "result": {"transactionHash": item["params"][0]}}))
+            .collect();
+        reply(&mut socket, Value::Array(responses)).await;
+        let _ = socket.next().await;
+    });
+
+    let api = WsJsonRpc::connect(config(addr)).await.unwrap();
+    let batch = JsonRpcBatch::new().get_transaction_receipt("0xa").get_transaction_receipt("0xb");
+    let results = api.batch(batch).await.unwrap();
+    assert_eq!(results[0].as_ref().unwrap()["result"]["transactionHash"], "0xa");
+    assert_eq!(results[1].as_ref().unwrap()["result"]["transactionHash"], "0xb");
+}
+
+#[tokio::test]
+async fn ws_timed_out_not_resent_test() {
+    // A call and a batch time out unanswered, then the connection drops. Their callers are gone
+    // so the reconnect mustn't send them again, the first request on the new socket is the next
+    // call.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (timed_out, wait) = tokio::sync::oneshot::channel::<()>();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(next_request(&mut socket).await["method"], "eth_getBlockByNumber");
+        assert!(next_request(&mut socket).await.is_array());
+        wait.await.unwrap();
+        socket.close(None).await.unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_blockNumber");
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0x2"})).await;
+        // Nothing else until the client goes away
+        while let Some(Ok(message)) = socket.next().await {
+            assert!(!matches!(message, Message::Text(_)), "resent {}", message);
+        }
+    });
+
+    let api = WsJsonRpc::connect(config(addr).with_timeout(Duration::from_millis(200))).await.unwrap();
+    assert!(api.get_block_by_number_latest().await.is_err());
+    assert!(api.batch(JsonRpcBatch::new().get_transaction_receipt("0xa")).await.is_err());
+    timed_out.send(()).unwrap();
+
+    // Reconnecting takes a moment, the call is retried until it gets through
+    let mut number = None;
+    for _ in 0..20 {
+        if let Ok(n) = api.get_block_number().await {
+            number = Some(n);
+            break;
+        }
+    }
+    assert_eq!(number, Some(2));
+    drop(api);
+    tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn ws_resubscribe_refused_test() {
+    // The node takes the subscription, drops the connection and then refuses it. The stream
+    // should end rather than wait for heads that won't come.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "result": "0xa"})).await;
+        socket.close(None).await.unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let req = next_request(&mut socket).await;
+        assert_eq!(req["method"], "eth_subscribe");
+        let error = json!({"code": -32000, "message": "too many subscriptions"});
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": req["id"], "error": error})).await;
+        let _ = socket.next().await;
+    });
+
+    let api = WsJsonRpc::connect(config(addr)).await.unwrap();
+    let mut heads = api.subscribe_new_heads().await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_secs(5), heads.next()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn ws_batch_rejected_test() {
+    // A node refusing the whole batch answers with one error and no id, every item gets it
+    // straight away instead of timing out
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert!(next_request(&mut socket).await.is_array());
+        let error = json!({"code": -32005, "message": "batch too large"});
+        reply(&mut socket, json!({"jsonrpc": "2.0", "id": null, "error": error})).await;
+        let _ = socket.next().await;
+    });
+
+    let api = WsJsonRpc::connect(config(addr).with_timeout(Duration::from_secs(30))).await.unwrap();
+    let batch = JsonRpcBatch::new().get_transaction_receipt("0xa").get_transaction_receipt("0xb");
+    let results = tokio::time::timeout(Duration::from_secs(5), api.batch(batch)).await.unwrap().unwrap();
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert!(matches!(result, Err(RpcError::JsonRpc { code: -32005, .. })));
+    }
+}
@@ -3,9 +3,14 @@ CREATE TABLE IF NOT EXISTS event (
     name VARCHAR NOT NULL
 );
 
+-- Wei amounts in messages are decimal strings, compare them with (message->>'value')::NUMERIC(78, 0)
 CREATE TABLE IF NOT EXISTS event_log (
     event_id INT NOT NULL,
-    message JSONB NOT NULL
+    message JSONB NOT NULL,
+    -- Detection.score, null when the message has no contract or recipient
+    score INT,
+    severity VARCHAR,
+    reasons JSONB
 );
 
 CREATE TABLE IF NOT EXISTS signal (
@@ -15,7 +20,11 @@ CREATE TABLE IF NOT EXISTS signal (
 
 CREATE TABLE IF NOT EXISTS signal_log (
     signal_id INT NOT NULL,
-    message JSONB NOT NULL
+    message JSONB NOT NULL,
+    -- Detection.score, null when the message has no contract or recipient
+    score INT,
+    severity VARCHAR,
+    reasons JSONB
 );
 
 